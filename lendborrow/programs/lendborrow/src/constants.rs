@@ -1,4 +1,3 @@
 pub const WAD: u128 = 1_000_000_000_000_000_000;
 pub const PERCENT_SCALER: u64 = 10_000_000_000_000_000;
-pub const MAX_OBLIGATION_RESERVES: usize = 10;
-pub const LIQUIDATION_CLOSE_FACTOR: u8 = 50;
\ No newline at end of file
+pub const MAX_OBLIGATION_RESERVES: usize = 10;
\ No newline at end of file
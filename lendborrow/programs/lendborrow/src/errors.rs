@@ -193,4 +193,115 @@ pub enum LendingError {
     #[msg("Invalid market owner")]
     InvalidMarketOwner,
 
+    #[msg("Flash loans cannot be invoked via CPI")]
+    FlashLoanCpiNotAllowed,
+
+    #[msg("No matching flash repay instruction found in transaction")]
+    FlashRepayNotFound,
+
+    #[msg("Multiple flash borrows in the same transaction are not allowed")]
+    MultipleFlashBorrows,
+
+    #[msg("Invalid flash repay instruction")]
+    InvalidFlashRepay,
+
+    #[msg("Invalid flash borrow instruction")]
+    InvalidFlashBorrow,
+
+    #[msg("Liquidation threshold decrease exceeds the allowed step size")]
+    LiquidationThresholdStepTooLarge,
+
+    #[msg("No protocol fees to claim")]
+    NoProtocolFeesToClaim,
+
+    #[msg("Operation is paused")]
+    OperationPaused,
+
+    #[msg("Only the guardian or the market owner can change pause flags")]
+    InvalidGuardian,
+
+    #[msg("Only the market owner can unpause operations")]
+    GuardianCannotUnpause,
+
+    #[msg("Invalid pause flags")]
+    InvalidPauseFlags,
+
+    #[msg("Deposit would exceed the reserve deposit limit")]
+    DepositLimitExceeded,
+
+    #[msg("Borrow would exceed the reserve borrow limit")]
+    BorrowLimitExceeded,
+
+    #[msg("Primary and secondary oracle prices diverge beyond the allowed band")]
+    OracleDivergence,
+
+    #[msg("Borrowing is halted while the reserve trades outside its peg band")]
+    ReserveDepegged,
+
+    #[msg("Liquidity mint uses an unsupported Token-2022 extension")]
+    UnsupportedMintExtension,
+
+    #[msg("Flash loan repayment received less than the borrowed amount")]
+    FlashRepayTooSmall,
+
+    #[msg("Isolated collateral cannot be combined with other collateral")]
+    IsolatedCollateralConflict,
+
+    #[msg("Reserve cannot be borrowed against isolated collateral")]
+    IsolationBorrowNotAllowed,
+
+    #[msg("Isolated collateral debt ceiling exceeded")]
+    IsolatedDebtCeilingExceeded,
+
+    #[msg("Missing or invalid isolated collateral reserve")]
+    InvalidIsolatedReserve,
+
+    #[msg("Invalid or disabled e-mode category")]
+    InvalidEModeCategory,
+
+    #[msg("E-mode category cannot change while the obligation has borrows")]
+    ObligationHasBorrows,
+
+    #[msg("Invalid lending market name. Must be a non-empty UTF-8 string")]
+    InvalidMarketName,
+
+    #[msg("No ownership transfer is pending")]
+    NoPendingOwner,
+
+    #[msg("Signer is not the pending owner")]
+    InvalidPendingOwner,
+
+    #[msg("Only the market owner or risk manager can change risk parameters")]
+    InvalidRiskManager,
+
+    #[msg("Only the market owner or fee manager can change fees")]
+    InvalidFeeManager,
+
+    #[msg("Market has a timelock; queue this change with queue_admin_change")]
+    TimelockRequired,
+
+    #[msg("Queued change cannot be executed before its eta slot")]
+    TimelockNotElapsed,
+
+    #[msg("Reserve account does not match the queued change")]
+    InvalidQueuedChangeReserve,
+
+    #[msg("Obligation still has collateral that can be liquidated")]
+    ObligationHasCollateral,
+
+    #[msg("Insurance vault is missing or does not match the reserve")]
+    InvalidInsuranceVault,
+
+    #[msg("Insurance vault balance is too low")]
+    InsufficientInsuranceFunds,
+
+    #[msg("Insurance draw exceeds the reserve's uncovered bad debt")]
+    InsuranceDrawExceedsBadDebt,
+
+    #[msg("A flash loan is open on this reserve")]
+    FlashLoanActive,
+
+    #[msg("Oracle, isolation and EMA price mode settings cannot change after reserve initialization")]
+    ImmutableReserveConfig,
+
 }
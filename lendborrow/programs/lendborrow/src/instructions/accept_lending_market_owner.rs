@@ -0,0 +1,45 @@
+use crate::errors::LendingError;
+use crate::states::LendingMarket;
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<AcceptLendingMarketOwner>) -> Result<()> {
+    let lending_market = &mut ctx.accounts.lending_market;
+
+    let old_owner = lending_market.owner;
+    let new_owner = ctx.accounts.pending_owner.key();
+
+    lending_market.owner = new_owner;
+    lending_market.pending_owner = Pubkey::default();
+
+    emit!(LendingMarketOwnerChanged {
+        lending_market: lending_market.key(),
+        old_owner,
+        new_owner,
+    });
+    Ok(())
+}
+
+/// Accounts context for accepting ownership of a `LendingMarket`.
+///
+/// - `pending_owner` must match the key proposed by the current owner; once a
+///   proposal is accepted or cancelled no key matches.
+/// - `pending_owner` must sign the transaction.
+/// - On timelocked markets the proposal itself waited out the delay, so
+///   accepting it takes effect immediately.
+#[derive(Accounts)]
+pub struct AcceptLendingMarketOwner<'info> {
+    #[account(
+        mut,
+        has_one = pending_owner @ LendingError::InvalidPendingOwner
+    )]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub pending_owner: Signer<'info>,
+}
+
+#[event]
+pub struct LendingMarketOwnerChanged {
+    pub lending_market: Pubkey,
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+}
@@ -1,7 +1,7 @@
 use crate::errors::LendingError;
 use crate::states::{LendingMarket, Obligation, Reserve};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 
 pub fn handler(ctx: Context<BorrowObligationLiquidity>, liquidity_amount: u64) -> Result<()> {
     require!(liquidity_amount > 0, LendingError::InvalidAmount);
@@ -10,6 +10,10 @@ pub fn handler(ctx: Context<BorrowObligationLiquidity>, liquidity_amount: u64) -
     let borrow_reserve = &mut ctx.accounts.borrow_reserve;
     let clock = Clock::get()?;
 
+    ctx.accounts
+        .lending_market
+        .require_not_paused(borrow_reserve.paused_operations, LendingMarket::PAUSE_BORROW)?;
+
     require!(
         !obligation.last_update_slot != clock.slot, 
         LendingError::ObligationStale
@@ -20,6 +24,8 @@ pub fn handler(ctx: Context<BorrowObligationLiquidity>, liquidity_amount: u64) -
         LendingError::ReserveStale
     );
 
+    require!(!borrow_reserve.depegged, LendingError::ReserveDepegged);
+
     require!(
         obligation.deposits_len > 0,
         LendingError::ObligationDepositsEmpty
@@ -47,6 +53,35 @@ pub fn handler(ctx: Context<BorrowObligationLiquidity>, liquidity_amount: u64) -
         LendingError::BorrowTooSmall
     );
 
+    borrow_reserve.check_borrow_limit(borrow_result.borrow_amount_wads)?;
+
+    if obligation.is_isolated() {
+        require!(
+            borrow_reserve.config.isolation_borrowable,
+            LendingError::IsolationBorrowNotAllowed
+        );
+
+        let isolated_reserve = ctx
+            .accounts
+            .isolated_collateral_reserve
+            .as_mut()
+            .ok_or(LendingError::InvalidIsolatedReserve)?;
+
+        require_keys_eq!(
+            isolated_reserve.key(),
+            obligation.isolated_collateral_reserve,
+            LendingError::InvalidIsolatedReserve
+        );
+        require_keys_neq!(
+            isolated_reserve.key(),
+            borrow_reserve.key(),
+            LendingError::InvalidIsolatedReserve
+        );
+
+        let borrow_value = borrow_reserve.debt_value(borrow_result.borrow_amount_wads)?;
+        isolated_reserve.add_isolated_debt(borrow_value)?;
+    }
+
     borrow_reserve.liquidity_borrowed_amount_wads = borrow_reserve
         .liquidity_borrowed_amount_wads
         .checked_add(borrow_result.borrow_amount_wads)
@@ -81,7 +116,12 @@ pub fn handler(ctx: Context<BorrowObligationLiquidity>, liquidity_amount: u64) -
         LendingError::InvalidReserveCount
     );
 
-    refresh_obligation_internal(obligation, ctx.remaining_accounts, &clock)?;
+    refresh_obligation_internal(
+        obligation,
+        &ctx.accounts.lending_market,
+        ctx.remaining_accounts,
+        &clock,
+    )?;
 
     obligation.verify_healthy()?;
 
@@ -90,51 +130,101 @@ pub fn handler(ctx: Context<BorrowObligationLiquidity>, liquidity_amount: u64) -
     let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
     let signer_seeds = &[&authority_seeds[..]];
 
-    if borrow_result.owner_fee > 0 {
-        token::transfer(
+    // Vault init is permissionless, so a market taking an insurance share
+    // can always insist on the vault.
+    require!(
+        ctx.accounts.lending_market.insurance_fee_percentage == 0
+            || ctx.accounts.insurance_vault.is_some(),
+        LendingError::InvalidInsuranceVault
+    );
+
+    let insurance_fee = ctx.accounts.lending_market.insurance_share(borrow_result.owner_fee)?;
+    let owner_fee = borrow_result
+        .owner_fee
+        .checked_sub(insurance_fee)
+        .ok_or(LendingError::MathOverflow)?;
+
+    if let Some(insurance_vault) = ctx
+        .accounts
+        .insurance_vault
+        .as_ref()
+        .filter(|_| insurance_fee > 0)
+    {
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.source_liquidity.to_account_info(),
+                    mint: ctx.accounts.liquidity_mint.to_account_info(),
+                    to: insurance_vault.to_account_info(),
+                    authority: ctx.accounts.lending_market_authority.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            insurance_fee,
+            ctx.accounts.liquidity_mint.decimals,
+        )?;
+    }
+
+    if owner_fee > 0 {
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.source_liquidity.to_account_info(),
+                    mint: ctx.accounts.liquidity_mint.to_account_info(),
                     to: ctx.accounts.borrow_reserve_liquidity_fee_receiver.to_account_info(),
                     authority: ctx.accounts.lending_market_authority.to_account_info(),
                 },
                 signer_seeds,
             ),
-            borrow_result.owner_fee,
+            owner_fee,
+            ctx.accounts.liquidity_mint.decimals,
         )?;
     }
 
     if borrow_result.host_fee > 0 {
         if let Some(host_fee_receiver) = &ctx.accounts.host_fee_receiver {
-            token::transfer(
+            token_interface::transfer_checked(
                 CpiContext::new_with_signer(
                     ctx.accounts.token_program.to_account_info(),
-                    Transfer {
+                    TransferChecked {
                         from: ctx.accounts.source_liquidity.to_account_info(),
+                        mint: ctx.accounts.liquidity_mint.to_account_info(),
                         to: host_fee_receiver.to_account_info(),
                         authority: ctx.accounts.lending_market_authority.to_account_info(),
                     },
                     signer_seeds,
                 ),
                 borrow_result.host_fee,
+                ctx.accounts.liquidity_mint.decimals,
             )?;
         }
     }
 
-    token::transfer(
+    token_interface::transfer_checked(
         CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: ctx.accounts.source_liquidity.to_account_info(),
+                mint: ctx.accounts.liquidity_mint.to_account_info(),
                 to: ctx.accounts.destination_liquidity.to_account_info(),
                 authority: ctx.accounts.lending_market_authority.to_account_info(),
             },
             signer_seeds,
         ),
         borrow_result.receive_amount,
+        ctx.accounts.liquidity_mint.decimals,
     )?;
 
+    let insurance_vault_balance = match ctx.accounts.insurance_vault.as_mut() {
+        Some(insurance_vault) => {
+            insurance_vault.reload()?;
+            insurance_vault.amount
+        }
+        None => 0,
+    };
+
     emit!(LiquidityBorrowed {
         obligation: obligation.key(),
         reserve: borrow_reserve.key(),
@@ -143,18 +233,21 @@ pub fn handler(ctx: Context<BorrowObligationLiquidity>, liquidity_amount: u64) -
         receive_amount: borrow_result.receive_amount,
         borrow_fee: borrow_result.borrow_fee,
         host_fee: borrow_result.host_fee,
-        owner_fee: borrow_result.owner_fee, 
+        owner_fee,
+        insurance_fee,
+        insurance_vault_balance,
         owner: ctx.accounts.obligation_owner.key(),
         slot: clock.slot,
     });
 
     msg!(
-        "Borrowed {} tokens: receive={}, fee={} (owner={}, host={})",
+        "Borrowed {} tokens: receive={}, fee={} (owner={}, host={}, insurance={})",
         liquidity_amount,
         borrow_result.receive_amount,
         borrow_result.borrow_fee,
-        borrow_result.owner_fee,
-        borrow_result.host_fee
+        owner_fee,
+        borrow_result.host_fee,
+        insurance_fee
     );
 
     Ok(())
@@ -162,7 +255,7 @@ pub fn handler(ctx: Context<BorrowObligationLiquidity>, liquidity_amount: u64) -
 
 
 #[derive(Debug)]
-pub struct CalculateBorrowResult {
+struct CalculateBorrowResult {
     pub borrow_amount_wads: u128,
     pub receive_amount: u64,
     pub borrow_fee: u64,
@@ -187,7 +280,7 @@ fn calculate_borrow(
         .ok_or(LendingError::MathOverflow)?;
 
     let borrow_value = (liquidity_amount as u128)
-        .checked_mul(reserve.liquidity_market_price)
+        .checked_mul(reserve.debt_price())
         .and_then(|v| v.checked_div(decimals)) 
         .ok_or(LendingError::MathOverflow)?;
 
@@ -230,6 +323,7 @@ fn calculate_borrow(
 
 fn refresh_obligation_internal(
     obligation: &mut Obligation,
+    lending_market: &LendingMarket,
     reserve_accounts: &[AccountInfo],
     clock: &Clock,
 ) -> Result<()> {
@@ -244,10 +338,15 @@ fn refresh_obligation_internal(
     let mut total_unhealthy_borrow_value: u128 = 0;
     let mut total_borrowed_value: u128 = 0;
 
+    let mut in_emode = obligation.emode_category != 0;
+
     const WAD: u128 = 1_000_000_000_000_000_000;
 
-    for i in 0..obligation.deposits_len as usize {
-        let deposit_reserve_info = &reserve_accounts[i];
+    for (i, deposit_reserve_info) in reserve_accounts
+        .iter()
+        .take(obligation.deposits_len as usize)
+        .enumerate()
+    {
         let (mut collateral, index) = obligation.find_collateral_by_index(i)?;
 
         require!(
@@ -263,6 +362,7 @@ fn refresh_obligation_internal(
             !deposit_reserve.is_stale(clock.slot)?,
             LendingError::ReserveStale
         );
+        in_emode &= deposit_reserve.config.emode_category == obligation.emode_category;
 
         let liquidity_amount =
             deposit_reserve.collateral_to_liquidity(collateral.deposited_amount)?;
@@ -271,7 +371,7 @@ fn refresh_obligation_internal(
             .checked_pow(deposit_reserve.liquidity_mint_decimals as u32)
             .ok_or(LendingError::MathOverflow)?;
 
-        let market_value = (deposit_reserve.liquidity_market_price as u128)
+        let market_value = deposit_reserve.collateral_price()
             .checked_mul(liquidity_amount as u128)
             .and_then(|v| v.checked_div(decimals)) // ✅ FIXED: Only one division
             .ok_or(LendingError::MathOverflow)?;
@@ -322,6 +422,7 @@ fn refresh_obligation_internal(
             !borrow_reserve.is_stale(clock.slot)?,
             LendingError::ReserveStale
         );
+        in_emode &= borrow_reserve.config.emode_category == obligation.emode_category;
 
         liquidity.accrue_interest(borrow_reserve.liquidity_cumulative_borrow_rate_wads)?;
 
@@ -331,7 +432,7 @@ fn refresh_obligation_internal(
 
         let market_value = liquidity
             .borrowed_amount_wads
-            .checked_mul(borrow_reserve.liquidity_market_price)
+            .checked_mul(borrow_reserve.debt_price())
             .and_then(|v| v.checked_div(WAD))
             .and_then(|v| v.checked_div(decimals)) // ✅ FIXED: Only one division
             .ok_or(LendingError::MathOverflow)?;
@@ -345,6 +446,16 @@ fn refresh_obligation_internal(
         obligation.update_liquidity(index, liquidity)?;
     }
     
+    if in_emode {
+        if let Some(category) = lending_market.emode_category(obligation.emode_category) {
+            (total_allowed_borrow_value, total_unhealthy_borrow_value) = category.borrow_values(
+                total_deposited_value,
+                total_allowed_borrow_value,
+                total_unhealthy_borrow_value,
+            )?;
+        }
+    }
+
     obligation.deposited_value = total_deposited_value;
     obligation.borrowed_value = total_borrowed_value;
     obligation.allowed_borrow_value = total_allowed_borrow_value;
@@ -361,7 +472,7 @@ pub struct BorrowObligationLiquidity<'info> {
         constraint = source_liquidity.key() == borrow_reserve.liquidity_supply 
             @ LendingError::InvalidLiquiditySupply,
     )]
-    pub source_liquidity: Box<Account<'info, TokenAccount>>,
+    pub source_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
@@ -370,22 +481,25 @@ pub struct BorrowObligationLiquidity<'info> {
         constraint = destination_liquidity.mint == borrow_reserve.liquidity_mint
             @ LendingError::InvalidMint, // ✅ ADDED
     )]
-    pub destination_liquidity: Box<Account<'info, TokenAccount>>,
+    pub destination_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
         constraint = borrow_reserve.lending_market == lending_market.key() 
             @ LendingError::InvalidLendingMarket,
+        has_one = liquidity_mint @ LendingError::InvalidLiquidityMint,
     )]
     pub borrow_reserve: Box<Account<'info, Reserve>>,
 
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
     #[account(
         mut,
         constraint = borrow_reserve_liquidity_fee_receiver.key() 
             == borrow_reserve.liquidity_fee_receiver 
             @ LendingError::InvalidFeeReceiver,
     )]
-    pub borrow_reserve_liquidity_fee_receiver: Box<Account<'info, TokenAccount>>,
+    pub borrow_reserve_liquidity_fee_receiver: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
@@ -397,6 +511,7 @@ pub struct BorrowObligationLiquidity<'info> {
             Obligation::SEED_PREFIX,
             lending_market.key().as_ref(),
             obligation_owner.key().as_ref(),
+            obligation.id.to_le_bytes().as_ref(),
         ],
         bump
     )]
@@ -414,9 +529,31 @@ pub struct BorrowObligationLiquidity<'info> {
     pub obligation_owner: Signer<'info>,
 
     /// CHECK: Optional host fee receiver (validated if present)
-    pub host_fee_receiver: Option<Account<'info, TokenAccount>>,
+    pub host_fee_receiver: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// Collateral reserve of an isolated obligation; required when borrowing
+    /// against isolated collateral so its debt ceiling can be enforced.
+    #[account(
+        mut,
+        constraint = isolated_collateral_reserve.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+    )]
+    pub isolated_collateral_reserve: Option<Box<Account<'info, Reserve>>>,
 
-    pub token_program: Program<'info, Token>,
+    /// Insurance vault for the liquidity mint; required while the market's
+    /// `insurance_fee_percentage` is non-zero.
+    #[account(
+        mut,
+        seeds = [
+            LendingMarket::INSURANCE_VAULT_SEED,
+            lending_market.key().as_ref(),
+            liquidity_mint.key().as_ref(),
+        ],
+        bump
+    )]
+    pub insurance_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[event]
@@ -428,7 +565,9 @@ pub struct LiquidityBorrowed {
     pub receive_amount: u64,
     pub borrow_fee: u64,
     pub host_fee: u64,
-    pub owner_fee: u64, 
+    pub owner_fee: u64,
+    pub insurance_fee: u64,
+    pub insurance_vault_balance: u64,
     pub owner: Pubkey,
     pub slot: u64,
 }
\ No newline at end of file
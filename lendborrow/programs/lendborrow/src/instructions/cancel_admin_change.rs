@@ -0,0 +1,54 @@
+use crate::errors::LendingError;
+use crate::states::{AdminChange, LendingMarket, QueuedAdminChange};
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<CancelAdminChange>) -> Result<()> {
+    let queued_change = &ctx.accounts.queued_change;
+
+    emit!(AdminChangeCancelled {
+        queued_change: queued_change.key(),
+        lending_market: ctx.accounts.lending_market.key(),
+        reserve: queued_change.reserve,
+        change: queued_change.change.clone(),
+        authority: ctx.accounts.authority.key(),
+    });
+
+    Ok(())
+}
+
+/// Accounts context for withdrawing a queued admin change.
+///
+/// - `authority` must be the change's proposer or the market owner, and sign.
+/// - Rent goes back to the `proposer`.
+#[derive(Accounts)]
+pub struct CancelAdminChange<'info> {
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+        has_one = proposer @ LendingError::InvalidAccountInput,
+        close = proposer,
+    )]
+    pub queued_change: Box<Account<'info, QueuedAdminChange>>,
+
+    #[account(
+        constraint = authority.key() == queued_change.proposer
+            || authority.key() == lending_market.owner
+            @ LendingError::InvalidOwner
+    )]
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    /// CHECK: Receives the queued change's rent; checked by `has_one`
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+#[event]
+pub struct AdminChangeCancelled {
+    pub queued_change: Pubkey,
+    pub lending_market: Pubkey,
+    pub reserve: Pubkey,
+    pub change: AdminChange,
+    pub authority: Pubkey,
+}
@@ -0,0 +1,45 @@
+use crate::errors::LendingError;
+use crate::states::LendingMarket;
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<CancelLendingMarketOwner>) -> Result<()> {
+    let lending_market = &mut ctx.accounts.lending_market;
+
+    require!(
+        lending_market.pending_owner != Pubkey::default(),
+        LendingError::NoPendingOwner
+    );
+
+    let cancelled_owner = lending_market.pending_owner;
+
+    lending_market.pending_owner = Pubkey::default();
+
+    emit!(LendingMarketOwnerProposalCancelled {
+        lending_market: lending_market.key(),
+        owner: ctx.accounts.owner.key(),
+        cancelled_owner,
+    });
+    Ok(())
+}
+
+/// Accounts context for withdrawing a pending ownership proposal.
+///
+/// - `lending_market` must currently be owned by `owner`.
+/// - `owner` must sign the transaction.
+#[derive(Accounts)]
+pub struct CancelLendingMarketOwner<'info> {
+    #[account(
+        mut,
+        has_one = owner @ LendingError::InvalidOwner
+    )]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub owner: Signer<'info>,
+}
+
+#[event]
+pub struct LendingMarketOwnerProposalCancelled {
+    pub lending_market: Pubkey,
+    pub owner: Pubkey,
+    pub cancelled_owner: Pubkey,
+}
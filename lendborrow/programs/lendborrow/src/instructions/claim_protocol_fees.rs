@@ -0,0 +1,102 @@
+use crate::errors::LendingError;
+use crate::states::{LendingMarket, Reserve};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+pub fn handler(ctx: Context<ClaimProtocolFees>) -> Result<()> {
+    let reserve = &mut ctx.accounts.reserve;
+    let clock = Clock::get()?;
+
+    require!(
+        !reserve.is_stale(clock.slot)?,
+        LendingError::ReserveStale
+    );
+
+    // Capped inside at the supply balance not owed to depositors.
+    let claim_amount =
+        reserve.claim_protocol_fees(ctx.accounts.reserve_liquidity_supply.amount)?;
+
+    require!(claim_amount > 0, LendingError::NoProtocolFeesToClaim);
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let authority_bump = ctx.bumps.lending_market_authority;
+    let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
+    let signer_seeds = &[&authority_seeds[..]];
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.reserve_liquidity_supply.to_account_info(),
+                mint: ctx.accounts.liquidity_mint.to_account_info(),
+                to: ctx.accounts.reserve_liquidity_fee_receiver.to_account_info(),
+                authority: ctx.accounts.lending_market_authority.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        claim_amount,
+        ctx.accounts.liquidity_mint.decimals,
+    )?;
+
+    emit!(ProtocolFeesClaimed {
+        reserve: reserve.key(),
+        fee_receiver: ctx.accounts.reserve_liquidity_fee_receiver.key(),
+        amount: claim_amount,
+        remaining_fees_wads: reserve.accumulated_protocol_fees_wads,
+        slot: clock.slot,
+    });
+
+    msg!("Claimed {} protocol fee tokens", claim_amount);
+
+    Ok(())
+}
+
+/// Accounts required to move accumulated protocol fees out of a reserve.
+///
+/// Fees always go to the reserve's own `liquidity_fee_receiver`, so anyone may
+/// trigger the claim.
+#[derive(Accounts)]
+pub struct ClaimProtocolFees<'info> {
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+        has_one = liquidity_mint @ LendingError::InvalidLiquidityMint,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = reserve_liquidity_supply.key() == reserve.liquidity_supply
+            @ LendingError::InvalidLiquiditySupply,
+    )]
+    pub reserve_liquidity_supply: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = reserve_liquidity_fee_receiver.key() == reserve.liquidity_fee_receiver
+            @ LendingError::InvalidFeeReceiver,
+    )]
+    pub reserve_liquidity_fee_receiver: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    /// CHECK: Lending market authority PDA
+    #[account(
+        seeds = [b"authority", lending_market.key().as_ref()],
+        bump
+    )]
+    pub lending_market_authority: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event]
+pub struct ProtocolFeesClaimed {
+    pub reserve: Pubkey,
+    pub fee_receiver: Pubkey,
+    pub amount: u64,
+    pub remaining_fees_wads: u128,
+    pub slot: u64,
+}
@@ -1,8 +1,7 @@
 use crate::errors::LendingError;
-use crate::states::{LendingMarket, Obligation, ObligationCollateral, Reserve};
+use crate::states::{LendingMarket, Obligation, Reserve};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use anchor_spl::token_interface::TokenInterface;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 
 pub fn handler(ctx: Context<DepositObligationCollateral>, collateral_amount: u64) -> Result<()> {
     require!(collateral_amount > 0, LendingError::InvalidAmount);
@@ -12,6 +11,10 @@ pub fn handler(ctx: Context<DepositObligationCollateral>, collateral_amount: u64
     let clock = Clock::get()?;
     const MAX_SLOT_AGE: u64 = 10;
 
+    ctx.accounts
+        .lending_market
+        .require_not_paused(reserve.paused_operations, LendingMarket::PAUSE_DEPOSIT)?;
+
     require!(
         clock.slot.saturating_sub(reserve.last_update_slot) <= MAX_SLOT_AGE,
         LendingError::ReserveStale
@@ -32,15 +35,16 @@ pub fn handler(ctx: Context<DepositObligationCollateral>, collateral_amount: u64
         LendingError::ReserveCollateralDisabled
     );
 
-    let cpi_accounts = Transfer {
+    let cpi_accounts = TransferChecked {
         from: ctx.accounts.source_collateral.to_account_info(),
+        mint: ctx.accounts.collateral_mint.to_account_info(),
         to: ctx.accounts.destination_collateral.to_account_info(),
         authority: ctx.accounts.user_transfer_authority.to_account_info(),
     };
 
     let cpi_program = ctx.accounts.token_program.to_account_info();
     let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(cpi_ctx, collateral_amount)?;
+    token_interface::transfer_checked(cpi_ctx, collateral_amount, ctx.accounts.collateral_mint.decimals)?;
 
     match obligation.find_collateral(reserve.key()) {
         Ok((mut collateral, index)) => {
@@ -48,7 +52,7 @@ pub fn handler(ctx: Context<DepositObligationCollateral>, collateral_amount: u64
             obligation.update_collateral(index, collateral)?;
         }
         Err(_) => {
-            let index = obligation.find_or_add_collateral(reserve.key())?;
+            let index = obligation.find_or_add_collateral(reserve.key(), reserve.config.isolation_mode)?;
             let (mut collateral, _) = obligation.find_collateral_by_index(index)?;
             collateral.deposit(collateral_amount)?;
             obligation.update_collateral(index, collateral)?;
@@ -70,20 +74,23 @@ pub fn handler(ctx: Context<DepositObligationCollateral>, collateral_amount: u64
 #[derive(Accounts)]
 pub struct DepositObligationCollateral<'info> {
     #[account(mut)]
-    pub source_collateral: Box<Account<'info, TokenAccount>>,
+    pub source_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
         constraint = destination_collateral.mint == reserve.collateral_mint @ LendingError::InvalidCollateralMint,
         constraint = destination_collateral.key() == reserve.collateral_supply @ LendingError::InvalidCollateralSupply,
     )]
-    pub destination_collateral: Box<Account<'info, TokenAccount>>,
+    pub destination_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
-        constraint = reserve.lending_market == lending_market.key() @ LendingError::InvalidLendingMarket
+        constraint = reserve.lending_market == lending_market.key() @ LendingError::InvalidLendingMarket,
+        has_one = collateral_mint @ LendingError::InvalidCollateralMint,
     )]
     pub reserve: Box<Account<'info, Reserve>>,
 
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
     #[account(
         mut,
         constraint = obligation.owner == obligation_owner.key() @ LendingError::InvalidObligationOwner,
@@ -92,6 +99,7 @@ pub struct DepositObligationCollateral<'info> {
             Obligation::SEED_PREFIX,
             lending_market.key().as_ref(),
             obligation_owner.key().as_ref(),
+            obligation.id.to_le_bytes().as_ref(),
         ],
         bump
     )]
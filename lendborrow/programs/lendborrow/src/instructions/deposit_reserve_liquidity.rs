@@ -1,7 +1,8 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer, MintTo};
+use anchor_spl::token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked};
 
 use crate::{errors::LendingError, states::{LendingMarket, Reserve}};
+use crate::utils::token::transfer_checked_received;
 
 pub fn handler(ctx: Context<DepositReserveLiquidity>, liquidity_amount: u64) -> Result<()> {
     require!(liquidity_amount > 0, LendingError::InvalidAmount);
@@ -9,24 +10,37 @@ pub fn handler(ctx: Context<DepositReserveLiquidity>, liquidity_amount: u64) ->
     let reserve = &mut ctx.accounts.reserve;
     let clock = Clock::get()?;
 
+    ctx.accounts
+        .lending_market
+        .require_not_paused(reserve.paused_operations, LendingMarket::PAUSE_DEPOSIT)?;
+
     require!(
         !reserve.is_stale(clock.slot)?,
         LendingError::ReserveStale
     );
 
-    let collateral_amount = reserve.deposit_liquidity(liquidity_amount)?;
-
-    reserve.last_update_slot = clock.slot;
-
     let transfer_ctx = CpiContext::new(
         ctx.accounts.token_program.to_account_info(),
-        Transfer {
+        TransferChecked {
             from: ctx.accounts.source_liquidity.to_account_info(),
+            mint: ctx.accounts.liquidity_mint.to_account_info(),
             to: ctx.accounts.reserve_liquidity_supply.to_account_info(),
             authority: ctx.accounts.user_transfer_authority.to_account_info(),
         },
     );
-    token::transfer(transfer_ctx, liquidity_amount)?;
+
+    // Credit only what reached the vault, net of any Token-2022 transfer fee.
+    let received_amount = transfer_checked_received(
+        transfer_ctx,
+        &mut ctx.accounts.reserve_liquidity_supply,
+        liquidity_amount,
+        ctx.accounts.liquidity_mint.decimals,
+    )?;
+
+    let reserve = &mut ctx.accounts.reserve;
+    let collateral_amount = reserve.deposit_liquidity(received_amount)?;
+
+    reserve.last_update_slot = clock.slot;
 
     let lending_market_key = ctx.accounts.lending_market.key();
     let authority_bump = ctx.bumps.lending_market_authority; 
@@ -42,11 +56,11 @@ pub fn handler(ctx: Context<DepositReserveLiquidity>, liquidity_amount: u64) ->
         },
         signer_seeds,
     );
-    token::mint_to(mint_ctx, collateral_amount)?;
+    token_interface::mint_to(mint_ctx, collateral_amount)?;
 
     emit!(LiquidityDeposited {
-        reserve: reserve.key(),
-        liquidity_amount,
+        reserve: ctx.accounts.reserve.key(),
+        liquidity_amount: received_amount,
         collateral_amount,
         depositor: ctx.accounts.user_transfer_authority.key(),
         slot: clock.slot,
@@ -54,7 +68,7 @@ pub fn handler(ctx: Context<DepositReserveLiquidity>, liquidity_amount: u64) ->
 
     msg!(
         "Deposited {} liquidity, minted {} collateral",
-        liquidity_amount,
+        received_amount,
         collateral_amount
     );
 
@@ -64,14 +78,15 @@ pub fn handler(ctx: Context<DepositReserveLiquidity>, liquidity_amount: u64) ->
 #[derive(Accounts)]
 pub struct DepositReserveLiquidity<'info> {
     #[account(mut)]
-    pub source_liquidity: Account<'info, TokenAccount>,
+    pub source_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut)]
-    pub destination_collateral: Account<'info, TokenAccount>,
+    pub destination_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
         has_one = lending_market,
+        has_one = liquidity_mint @ LendingError::InvalidLiquidityMint,
         constraint = reserve.liquidity_supply == reserve_liquidity_supply.key() 
             @ LendingError::InvalidAccountInput,
         constraint = reserve.collateral_mint == reserve_collateral_mint.key() 
@@ -84,15 +99,16 @@ pub struct DepositReserveLiquidity<'info> {
     pub reserve: Account<'info, Reserve>,
 
     #[account(mut)]
-    pub reserve_liquidity_supply: Account<'info, TokenAccount>,
+    pub reserve_liquidity_supply: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut)]
-    pub reserve_collateral_mint: Account<'info, Mint>,
+    pub reserve_collateral_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
-        constraint = lending_market.token_program_id == token_program.key() 
-            @ LendingError::InvalidTokenProgram
+        mint::token_program = token_program,
     )]
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
     pub lending_market: Account<'info, LendingMarket>,
 
     /// CHECK: PDA derived with "authority" seed
@@ -104,7 +120,7 @@ pub struct DepositReserveLiquidity<'info> {
 
     pub user_transfer_authority: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[event]
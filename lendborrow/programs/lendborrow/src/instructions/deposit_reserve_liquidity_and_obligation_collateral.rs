@@ -0,0 +1,180 @@
+use crate::errors::LendingError;
+use crate::states::{LendingMarket, Obligation, Reserve};
+use crate::utils::token::transfer_checked_received;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked};
+
+pub fn handler(
+    ctx: Context<DepositReserveLiquidityAndObligationCollateral>,
+    liquidity_amount: u64,
+) -> Result<()> {
+    require!(liquidity_amount > 0, LendingError::InvalidAmount);
+
+    let reserve = &ctx.accounts.reserve;
+    let clock = Clock::get()?;
+
+    ctx.accounts
+        .lending_market
+        .require_not_paused(reserve.paused_operations, LendingMarket::PAUSE_DEPOSIT)?;
+
+    // Collateral is minted at the exchange rate, so interest must be accrued up to now.
+    require!(
+        reserve.last_update_slot == clock.slot,
+        LendingError::ReserveStale
+    );
+
+    require!(
+        reserve.config.loan_to_value_ratio > 0,
+        LendingError::ReserveCollateralDisabled
+    );
+
+    // Credit only what reached the vault, net of any Token-2022 transfer fee.
+    let received_amount = transfer_checked_received(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.source_liquidity.to_account_info(),
+                mint: ctx.accounts.liquidity_mint.to_account_info(),
+                to: ctx.accounts.reserve_liquidity_supply.to_account_info(),
+                authority: ctx.accounts.user_transfer_authority.to_account_info(),
+            },
+        ),
+        &mut ctx.accounts.reserve_liquidity_supply,
+        liquidity_amount,
+        ctx.accounts.liquidity_mint.decimals,
+    )?;
+
+    let reserve = &mut ctx.accounts.reserve;
+    let collateral_amount = reserve.deposit_liquidity(received_amount)?;
+
+    let obligation = &mut ctx.accounts.obligation;
+    let index = obligation.find_or_add_collateral(reserve.key(), reserve.config.isolation_mode)?;
+    let (mut collateral, _) = obligation.find_collateral_by_index(index)?;
+    collateral.deposit(collateral_amount)?;
+    obligation.update_collateral(index, collateral)?;
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let authority_bump = ctx.bumps.lending_market_authority;
+    let seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    // The collateral never leaves the program: it is minted straight into the
+    // reserve's collateral supply on the obligation's behalf.
+    token_interface::mint_to(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.collateral_supply.to_account_info(),
+                authority: ctx.accounts.lending_market_authority.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        collateral_amount,
+    )?;
+
+    emit!(LiquidityDepositedAsCollateral {
+        obligation: ctx.accounts.obligation.key(),
+        reserve: ctx.accounts.reserve.key(),
+        owner: ctx.accounts.obligation_owner.key(),
+        liquidity_amount: received_amount,
+        collateral_amount,
+        depositor: ctx.accounts.user_transfer_authority.key(),
+        slot: clock.slot,
+    });
+
+    msg!(
+        "Deposited {} liquidity as {} obligation collateral",
+        received_amount,
+        collateral_amount
+    );
+
+    Ok(())
+}
+
+/// Accounts context for depositing liquidity and collateralizing it in one step.
+///
+/// Equivalent to `deposit_reserve_liquidity` followed by
+/// `deposit_obligation_collateral`, without the collateral passing through a
+/// user account.
+///
+/// - `reserve` must be refreshed in the same slot and enabled as collateral.
+/// - The obligation's values are not updated; refresh it before borrowing
+///   against the new collateral.
+/// - `obligation_owner` must own `obligation` and sign.
+/// - `user_transfer_authority` must be able to move `source_liquidity`.
+#[derive(Accounts)]
+pub struct DepositReserveLiquidityAndObligationCollateral<'info> {
+    #[account(
+        mut,
+        constraint = source_liquidity.key() != reserve.liquidity_supply
+            @ LendingError::InvalidAccountInput,
+    )]
+    pub source_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+        has_one = liquidity_mint @ LendingError::InvalidLiquidityMint,
+        has_one = collateral_mint @ LendingError::InvalidCollateralMint,
+        has_one = collateral_supply @ LendingError::InvalidCollateralSupply,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    #[account(
+        mut,
+        constraint = reserve_liquidity_supply.key() == reserve.liquidity_supply
+            @ LendingError::InvalidLiquiditySupply,
+    )]
+    pub reserve_liquidity_supply: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub collateral_supply: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = obligation.owner == obligation_owner.key()
+            @ LendingError::InvalidObligationOwner,
+        constraint = obligation.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+        seeds = [
+            Obligation::SEED_PREFIX,
+            lending_market.key().as_ref(),
+            obligation_owner.key().as_ref(),
+            obligation.id.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub obligation: Box<Account<'info, Obligation>>,
+
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    /// CHECK: Lending market authority PDA
+    #[account(
+        seeds = [b"authority", lending_market.key().as_ref()],
+        bump
+    )]
+    pub lending_market_authority: UncheckedAccount<'info>,
+
+    pub obligation_owner: Signer<'info>,
+
+    pub user_transfer_authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event]
+pub struct LiquidityDepositedAsCollateral {
+    pub obligation: Pubkey,
+    pub reserve: Pubkey,
+    pub owner: Pubkey,
+    pub liquidity_amount: u64,
+    pub collateral_amount: u64,
+    pub depositor: Pubkey,
+    pub slot: u64,
+}
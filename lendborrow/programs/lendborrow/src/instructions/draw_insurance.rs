@@ -0,0 +1,181 @@
+use crate::errors::LendingError;
+use crate::states::{LendingMarket, Reserve};
+use crate::utils::token::transfer_checked_received;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Burn, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+pub fn handler(ctx: Context<DrawInsurance>, amount: u64) -> Result<()> {
+    require!(amount > 0, LendingError::InvalidAmount);
+
+    let clock = Clock::get()?;
+
+    require!(
+        ctx.accounts.reserve.last_update_slot == clock.slot,
+        LendingError::ReserveStale
+    );
+
+    require!(
+        ctx.accounts.insurance_vault.amount >= amount,
+        LendingError::InsufficientInsuranceFunds
+    );
+
+    let exchange_rate_before = ctx.accounts.reserve.collateral_exchange_rate()?;
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let authority_bump = ctx.bumps.lending_market_authority;
+    let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
+    let signer_seeds = &[&authority_seeds[..]];
+
+    // Either way the reserve's depositors end up with more liquidity per
+    // collateral token: liquidity is donated to the supply, or insurance-held
+    // collateral tokens are burned without withdrawing their liquidity.
+    let covered_amount = if ctx.accounts.mint.key() == ctx.accounts.reserve.liquidity_mint {
+        let received_amount = transfer_checked_received(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.insurance_vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.reserve_liquidity_supply.to_account_info(),
+                    authority: ctx.accounts.lending_market_authority.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            &mut ctx.accounts.reserve_liquidity_supply,
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let reserve = &mut ctx.accounts.reserve;
+        reserve.cover_bad_debt(received_amount)?;
+        reserve.liquidity_available_amount = reserve
+            .liquidity_available_amount
+            .checked_add(received_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        received_amount
+    } else {
+        let reserve = &mut ctx.accounts.reserve;
+
+        // Burned collateral hands its liquidity value to the other depositors.
+        let liquidity_amount = reserve.collateral_to_liquidity(amount)?;
+        reserve.cover_bad_debt(liquidity_amount)?;
+
+        reserve.collateral_mint_total_supply = reserve
+            .collateral_mint_total_supply
+            .checked_sub(amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        // Burning the last collateral token would reset the exchange rate.
+        require!(
+            reserve.collateral_mint_total_supply > 0,
+            LendingError::InvalidAmount
+        );
+
+        token_interface::burn(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    from: ctx.accounts.insurance_vault.to_account_info(),
+                    authority: ctx.accounts.lending_market_authority.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+
+        amount
+    };
+
+    ctx.accounts.insurance_vault.reload()?;
+
+    let exchange_rate_after = ctx.accounts.reserve.collateral_exchange_rate()?;
+
+    emit!(InsuranceDrawn {
+        lending_market: lending_market_key,
+        reserve: ctx.accounts.reserve.key(),
+        mint: ctx.accounts.mint.key(),
+        amount: covered_amount,
+        exchange_rate_before,
+        exchange_rate_after,
+        vault_balance: ctx.accounts.insurance_vault.amount,
+        slot: clock.slot,
+    });
+
+    msg!(
+        "Drew {} from insurance vault, {} left",
+        covered_amount,
+        ctx.accounts.insurance_vault.amount
+    );
+
+    Ok(())
+}
+
+/// Accounts context for covering a reserve's losses from the insurance vault.
+///
+/// - `owner` must own the lending market and sign.
+/// - `mint` selects the vault: the reserve's liquidity mint donates liquidity
+///   to `reserve_liquidity_supply`, its collateral mint burns the vault's
+///   collateral tokens.
+/// - `reserve` must be refreshed in this slot, and the value drawn may not
+///   exceed its `socialized_bad_debt_wads`.
+#[derive(Accounts)]
+pub struct DrawInsurance<'info> {
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+        constraint = mint.key() == reserve.liquidity_mint || mint.key() == reserve.collateral_mint
+            @ LendingError::InvalidMint,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    #[account(
+        has_one = owner @ LendingError::InvalidOwner
+    )]
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    /// CHECK: Lending market authority PDA
+    #[account(
+        seeds = [b"authority", lending_market.key().as_ref()],
+        bump
+    )]
+    pub lending_market_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            LendingMarket::INSURANCE_VAULT_SEED,
+            lending_market.key().as_ref(),
+            mint.key().as_ref(),
+        ],
+        bump
+    )]
+    pub insurance_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = reserve_liquidity_supply.key() == reserve.liquidity_supply
+            @ LendingError::InvalidLiquiditySupply,
+    )]
+    pub reserve_liquidity_supply: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event]
+pub struct InsuranceDrawn {
+    pub lending_market: Pubkey,
+    pub reserve: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub exchange_rate_before: u128,
+    pub exchange_rate_after: u128,
+    pub vault_balance: u64,
+    pub slot: u64,
+}
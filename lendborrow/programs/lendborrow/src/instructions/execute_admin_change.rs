@@ -0,0 +1,163 @@
+use crate::errors::LendingError;
+use crate::states::{AdminChange, LendingMarket, QueuedAdminChange, Reserve};
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<ExecuteAdminChange>) -> Result<()> {
+    let queued_change = &ctx.accounts.queued_change;
+    let lending_market = &mut ctx.accounts.lending_market;
+    let clock = Clock::get()?;
+
+    require!(
+        clock.slot >= queued_change.ready_slot(lending_market)?,
+        LendingError::TimelockNotElapsed
+    );
+
+    // The proposer may have lost the role the change needs since queueing it.
+    queued_change.change.require_authority(
+        lending_market,
+        ctx.accounts.reserve.as_deref().map(|reserve| &**reserve),
+        &queued_change.proposer,
+    )?;
+
+    match &queued_change.change {
+        AdminChange::ReserveConfig(config) => {
+            let reserve = queued_reserve(&mut ctx.accounts.reserve, queued_change)?;
+
+            // Interest must be accrued under the old rate curve before it is replaced.
+            require!(
+                reserve.last_update_slot == clock.slot,
+                LendingError::ReserveStale
+            );
+
+            reserve.update_config(config.clone(), lending_market)?;
+        }
+        AdminChange::EModeCategory {
+            category_id,
+            loan_to_value_ratio,
+            liquidation_threshold,
+        } => {
+            lending_market.set_emode_category(
+                *category_id,
+                *loan_to_value_ratio,
+                *liquidation_threshold,
+            )?;
+        }
+        AdminChange::MaxLiquidationThresholdStep(max_step) => {
+            lending_market.set_max_liquidation_threshold_step(*max_step)?;
+        }
+        AdminChange::TimelockDelay(delay_slots) => {
+            lending_market.set_timelock_delay_slots(*delay_slots)?;
+        }
+        AdminChange::SecondaryOracle(secondary_oracle) => {
+            let reserve = queued_reserve(&mut ctx.accounts.reserve, queued_change)?;
+
+            // The new oracle is first read by the next `refresh_reserve`.
+            reserve.secondary_oracle = *secondary_oracle;
+            reserve.liquidity_secondary_market_price = 0;
+        }
+        AdminChange::PendingOwner(new_owner) => {
+            require!(*new_owner != lending_market.owner, LendingError::SameOwner);
+
+            lending_market.pending_owner = *new_owner;
+        }
+        AdminChange::InsuranceFeePercentage(insurance_fee_percentage) => {
+            lending_market.set_insurance_fee_percentage(*insurance_fee_percentage)?;
+        }
+        AdminChange::FeeReceiver(new_fee_receiver) => {
+            let reserve = queued_reserve(&mut ctx.accounts.reserve, queued_change)?;
+            reserve.liquidity_fee_receiver = *new_fee_receiver;
+        }
+        AdminChange::Guardian(new_guardian) => {
+            require!(*new_guardian != lending_market.owner, LendingError::InvalidGuardian);
+
+            lending_market.guardian = *new_guardian;
+        }
+        AdminChange::RiskManager(new_risk_manager) => {
+            require!(
+                *new_risk_manager != lending_market.owner,
+                LendingError::InvalidRiskManager
+            );
+
+            lending_market.risk_manager = *new_risk_manager;
+        }
+        AdminChange::FeeManager(new_fee_manager) => {
+            require!(
+                *new_fee_manager != lending_market.owner,
+                LendingError::InvalidFeeManager
+            );
+
+            lending_market.fee_manager = *new_fee_manager;
+        }
+    }
+
+    emit!(AdminChangeExecuted {
+        queued_change: queued_change.key(),
+        lending_market: lending_market.key(),
+        reserve: queued_change.reserve,
+        change: queued_change.change.clone(),
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+/// Returns the queued change's target reserve, which must have been passed in.
+fn queued_reserve<'a, 'info>(
+    reserve: &'a mut Option<Box<Account<'info, Reserve>>>,
+    queued_change: &QueuedAdminChange,
+) -> Result<&'a mut Account<'info, Reserve>> {
+    let reserve = reserve
+        .as_mut()
+        .ok_or(LendingError::InvalidQueuedChangeReserve)?;
+
+    require_keys_eq!(
+        reserve.key(),
+        queued_change.reserve,
+        LendingError::InvalidQueuedChangeReserve
+    );
+
+    Ok(reserve)
+}
+
+/// Accounts context for applying a queued admin change after its eta.
+///
+/// - Anyone may execute; rent goes back to the `proposer`, who must still be
+///   allowed to make the change.
+/// - The market's current timelock must also have passed since queueing.
+/// - `reserve` must be the queued reserve, refreshed in this slot, for
+///   `AdminChange::ReserveConfig`.
+/// - `reserve` must be the queued reserve for `AdminChange::SecondaryOracle`
+///   and `AdminChange::FeeReceiver`.
+#[derive(Accounts)]
+pub struct ExecuteAdminChange<'info> {
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+        has_one = proposer @ LendingError::InvalidAccountInput,
+        close = proposer,
+    )]
+    pub queued_change: Box<Account<'info, QueuedAdminChange>>,
+
+    #[account(mut)]
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    #[account(
+        mut,
+        constraint = reserve.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+    )]
+    pub reserve: Option<Box<Account<'info, Reserve>>>,
+
+    /// CHECK: Receives the queued change's rent; checked by `has_one`
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+}
+
+#[event]
+pub struct AdminChangeExecuted {
+    pub queued_change: Pubkey,
+    pub lending_market: Pubkey,
+    pub reserve: Pubkey,
+    pub change: AdminChange,
+    pub slot: u64,
+}
@@ -0,0 +1,183 @@
+use crate::errors::LendingError;
+use crate::instruction::{
+    FlashBorrowReserveLiquidity as FlashBorrowIx, FlashRepayReserveLiquidity as FlashRepayIx,
+};
+use crate::instructions::flash_repay_reserve_liquidity::FLASH_REPAY_RESERVE_ACCOUNT_INDEX;
+use crate::states::{LendingMarket, Reserve};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT};
+#[allow(deprecated)]
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+/// Position of `reserve` in `FlashBorrowReserveLiquidity`, used by the repay
+/// instruction to match its borrow through instruction introspection.
+pub const FLASH_BORROW_RESERVE_ACCOUNT_INDEX: usize = 2;
+
+pub fn handler(ctx: Context<FlashBorrowReserveLiquidity>, liquidity_amount: u64) -> Result<()> {
+    require!(liquidity_amount > 0, LendingError::InvalidAmount);
+
+    require!(
+        get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT,
+        LendingError::FlashLoanCpiNotAllowed
+    );
+
+    let reserve = &mut ctx.accounts.reserve;
+    let clock = Clock::get()?;
+
+    ctx.accounts
+        .lending_market
+        .require_not_paused(reserve.paused_operations, LendingMarket::PAUSE_FLASH_LOAN)?;
+
+    // Refreshed in this slot, so no interest can accrue before the repayment
+    // lands later in the same transaction.
+    require!(
+        reserve.last_update_slot == clock.slot,
+        LendingError::ReserveStale
+    );
+
+    let instructions_sysvar = ctx.accounts.instructions_sysvar.to_account_info();
+    let current_index = load_current_index_checked(&instructions_sysvar)? as usize;
+
+    let mut index = current_index + 1;
+    let mut repay_found = false;
+
+    // Scan the rest of the transaction for the matching repayment.
+    while let Ok(ix) = load_instruction_at_checked(index, &instructions_sysvar) {
+        index += 1;
+
+        if ix.program_id != crate::ID {
+            continue;
+        }
+
+        require!(
+            !ix.data.starts_with(FlashBorrowIx::DISCRIMINATOR),
+            LendingError::MultipleFlashBorrows
+        );
+
+        if !ix.data.starts_with(FlashRepayIx::DISCRIMINATOR) {
+            continue;
+        }
+
+        let repay = FlashRepayIx::try_from_slice(&ix.data[FlashRepayIx::DISCRIMINATOR.len()..])
+            .map_err(|_| LendingError::InvalidFlashRepay)?;
+
+        let repay_reserve = ix
+            .accounts
+            .get(FLASH_REPAY_RESERVE_ACCOUNT_INDEX)
+            .ok_or(LendingError::InvalidFlashRepay)?;
+
+        require!(
+            repay_reserve.pubkey == reserve.key(),
+            LendingError::InvalidFlashRepay
+        );
+
+        require!(
+            repay.liquidity_amount == liquidity_amount,
+            LendingError::InvalidFlashRepay
+        );
+
+        require!(
+            repay.borrow_instruction_index as usize == current_index,
+            LendingError::InvalidFlashRepay
+        );
+
+        repay_found = true;
+        break;
+    }
+
+    require!(repay_found, LendingError::FlashRepayNotFound);
+
+    reserve.flash_borrow(liquidity_amount)?;
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let authority_bump = ctx.bumps.lending_market_authority;
+    let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
+    let signer_seeds = &[&authority_seeds[..]];
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.source_liquidity.to_account_info(),
+                mint: ctx.accounts.liquidity_mint.to_account_info(),
+                to: ctx.accounts.destination_liquidity.to_account_info(),
+                authority: ctx.accounts.lending_market_authority.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        liquidity_amount,
+        ctx.accounts.liquidity_mint.decimals,
+    )?;
+
+    emit!(FlashLoanBorrowed {
+        reserve: reserve.key(),
+        liquidity_amount,
+        destination: ctx.accounts.destination_liquidity.key(),
+        slot: clock.slot,
+    });
+
+    msg!("Flash borrowed {} liquidity", liquidity_amount);
+
+    Ok(())
+}
+
+/// Accounts required to take a flash loan from a reserve.
+///
+/// The loan must be repaid by a `flash_repay_reserve_liquidity` instruction
+/// later in the same transaction; this is enforced through the instructions
+/// sysvar before any liquidity leaves the reserve.
+///
+/// `reserve` must be refreshed earlier in the same slot.
+#[derive(Accounts)]
+pub struct FlashBorrowReserveLiquidity<'info> {
+    #[account(
+        mut,
+        constraint = source_liquidity.key() == reserve.liquidity_supply
+            @ LendingError::InvalidLiquiditySupply,
+    )]
+    pub source_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = destination_liquidity.key() != reserve.liquidity_supply
+            @ LendingError::InvalidDestinationAccount,
+        constraint = destination_liquidity.mint == reserve.liquidity_mint
+            @ LendingError::InvalidMint,
+    )]
+    pub destination_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = reserve.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+        has_one = liquidity_mint @ LendingError::InvalidLiquidityMint,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Lending market authority PDA
+    #[account(
+        seeds = [b"authority", lending_market.key().as_ref()],
+        bump
+    )]
+    pub lending_market_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar, validated by the `load_*_checked` helpers
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event]
+pub struct FlashLoanBorrowed {
+    pub reserve: Pubkey,
+    pub liquidity_amount: u64,
+    pub destination: Pubkey,
+    pub slot: u64,
+}
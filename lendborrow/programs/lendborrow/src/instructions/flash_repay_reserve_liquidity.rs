@@ -0,0 +1,235 @@
+use crate::errors::LendingError;
+use crate::instruction::{
+    FlashBorrowReserveLiquidity as FlashBorrowIx, FlashRepayReserveLiquidity as FlashRepayIx,
+};
+use crate::instructions::flash_borrow_reserve_liquidity::FLASH_BORROW_RESERVE_ACCOUNT_INDEX;
+use crate::states::{LendingMarket, Reserve};
+use crate::utils::calculate_flash_loan;
+use crate::utils::token::transfer_checked_received;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT};
+#[allow(deprecated)]
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+/// Position of `reserve` in `FlashRepayReserveLiquidity`, used by the borrow
+/// instruction to find its repayment through instruction introspection.
+pub const FLASH_REPAY_RESERVE_ACCOUNT_INDEX: usize = 4;
+
+pub fn handler(
+    ctx: Context<FlashRepayReserveLiquidity>,
+    liquidity_amount: u64,
+    borrow_instruction_index: u8,
+) -> Result<()> {
+    require!(liquidity_amount > 0, LendingError::InvalidAmount);
+
+    require!(
+        get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT,
+        LendingError::FlashLoanCpiNotAllowed
+    );
+
+    let reserve = &mut ctx.accounts.reserve;
+    let clock = Clock::get()?;
+
+    let instructions_sysvar = ctx.accounts.instructions_sysvar.to_account_info();
+    let current_index = load_current_index_checked(&instructions_sysvar)? as usize;
+
+    require!(
+        (borrow_instruction_index as usize) < current_index,
+        LendingError::InvalidFlashBorrow
+    );
+
+    let borrow_ix =
+        load_instruction_at_checked(borrow_instruction_index as usize, &instructions_sysvar)?;
+
+    require!(
+        borrow_ix.program_id == crate::ID
+            && borrow_ix.data.starts_with(FlashBorrowIx::DISCRIMINATOR),
+        LendingError::InvalidFlashBorrow
+    );
+
+    let borrow = FlashBorrowIx::try_from_slice(&borrow_ix.data[FlashBorrowIx::DISCRIMINATOR.len()..])
+        .map_err(|_| LendingError::InvalidFlashBorrow)?;
+
+    let borrow_reserve = borrow_ix
+        .accounts
+        .get(FLASH_BORROW_RESERVE_ACCOUNT_INDEX)
+        .ok_or(LendingError::InvalidFlashBorrow)?;
+
+    require!(
+        borrow_reserve.pubkey == reserve.key(),
+        LendingError::InvalidFlashBorrow
+    );
+
+    require!(
+        borrow.liquidity_amount == liquidity_amount,
+        LendingError::InvalidFlashBorrow
+    );
+
+    // Only the first repay after a borrow may settle it.
+    for index in (borrow_instruction_index as usize + 1)..current_index {
+        let ix = load_instruction_at_checked(index, &instructions_sysvar)?;
+        require!(
+            ix.program_id != crate::ID
+                || !ix.data.starts_with(FlashRepayIx::DISCRIMINATOR),
+            LendingError::InvalidFlashRepay
+        );
+    }
+
+    let flash_loan_result = calculate_flash_loan(reserve, liquidity_amount)?;
+
+    // Without a host account the whole fee goes to the reserve's fee receiver.
+    let (owner_fee, host_fee) = if ctx.accounts.host_fee_receiver.is_some() {
+        (flash_loan_result.owner_fee, flash_loan_result.host_fee)
+    } else {
+        (flash_loan_result.flash_loan_fee, 0)
+    };
+
+    let decimals = ctx.accounts.liquidity_mint.decimals;
+
+    let received_amount = transfer_checked_received(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.source_liquidity.to_account_info(),
+                mint: ctx.accounts.liquidity_mint.to_account_info(),
+                to: ctx.accounts.destination_liquidity.to_account_info(),
+                authority: ctx.accounts.user_transfer_authority.to_account_info(),
+            },
+        ),
+        &mut ctx.accounts.destination_liquidity,
+        liquidity_amount,
+        decimals,
+    )?;
+
+    // A transfer fee on the mint must not leave the reserve short of principal.
+    require!(
+        received_amount >= liquidity_amount,
+        LendingError::FlashRepayTooSmall
+    );
+
+    ctx.accounts.reserve.flash_repay(liquidity_amount)?;
+
+    if owner_fee > 0 {
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.source_liquidity.to_account_info(),
+                    mint: ctx.accounts.liquidity_mint.to_account_info(),
+                    to: ctx.accounts.reserve_liquidity_fee_receiver.to_account_info(),
+                    authority: ctx.accounts.user_transfer_authority.to_account_info(),
+                },
+            ),
+            owner_fee,
+            decimals,
+        )?;
+    }
+
+    if host_fee > 0 {
+        if let Some(host_fee_receiver) = &ctx.accounts.host_fee_receiver {
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.source_liquidity.to_account_info(),
+                        mint: ctx.accounts.liquidity_mint.to_account_info(),
+                        to: host_fee_receiver.to_account_info(),
+                        authority: ctx.accounts.user_transfer_authority.to_account_info(),
+                    },
+                ),
+                host_fee,
+                decimals,
+            )?;
+        }
+    }
+
+    emit!(FlashLoanRepaid {
+        reserve: ctx.accounts.reserve.key(),
+        liquidity_amount,
+        flash_loan_fee: flash_loan_result.flash_loan_fee,
+        owner_fee,
+        host_fee,
+        repayer: ctx.accounts.user_transfer_authority.key(),
+        slot: clock.slot,
+    });
+
+    msg!(
+        "Flash repaid {} liquidity: fee={} (owner={}, host={})",
+        liquidity_amount,
+        flash_loan_result.flash_loan_fee,
+        owner_fee,
+        host_fee
+    );
+
+    Ok(())
+}
+
+/// Accounts required to repay a flash loan.
+///
+/// `borrow_instruction_index` must point at the `flash_borrow_reserve_liquidity`
+/// instruction earlier in the same transaction for the same reserve and amount.
+/// The principal goes back to the liquidity supply and the fee is split between
+/// `reserve_liquidity_fee_receiver` and the optional `host_fee_receiver`.
+#[derive(Accounts)]
+pub struct FlashRepayReserveLiquidity<'info> {
+    #[account(
+        mut,
+        constraint = source_liquidity.key() != reserve.liquidity_supply
+            @ LendingError::InvalidAccountInput,
+    )]
+    pub source_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = destination_liquidity.key() == reserve.liquidity_supply
+            @ LendingError::InvalidLiquiditySupply,
+    )]
+    pub destination_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = reserve_liquidity_fee_receiver.key() == reserve.liquidity_fee_receiver
+            @ LendingError::InvalidFeeReceiver,
+    )]
+    pub reserve_liquidity_fee_receiver: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = host_fee_receiver.mint == reserve.liquidity_mint
+            @ LendingError::InvalidFeeReceiver,
+    )]
+    pub host_fee_receiver: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        constraint = reserve.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+        has_one = liquidity_mint @ LendingError::InvalidLiquidityMint,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub user_transfer_authority: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, validated by the `load_*_checked` helpers
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event]
+pub struct FlashLoanRepaid {
+    pub reserve: Pubkey,
+    pub liquidity_amount: u64,
+    pub flash_loan_fee: u64,
+    pub owner_fee: u64,
+    pub host_fee: u64,
+    pub repayer: Pubkey,
+    pub slot: u64,
+}
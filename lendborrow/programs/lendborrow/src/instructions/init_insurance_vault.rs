@@ -0,0 +1,75 @@
+use crate::errors::LendingError;
+use crate::states::{LendingMarket, Reserve};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+pub fn handler(ctx: Context<InitInsuranceVault>) -> Result<()> {
+    emit!(InsuranceVaultInitialized {
+        lending_market: ctx.accounts.lending_market.key(),
+        reserve: ctx.accounts.reserve.key(),
+        mint: ctx.accounts.mint.key(),
+        insurance_vault: ctx.accounts.insurance_vault.key(),
+    });
+
+    Ok(())
+}
+
+/// Accounts context for creating the market's insurance vault for one mint.
+///
+/// - `mint` must be the reserve's liquidity mint, which receives the insurance
+///   share of borrow fees, or its collateral mint, which receives the share of
+///   liquidation bonuses.
+/// - Anyone may pay for the vault; it is owned by the lending market authority.
+#[derive(Accounts)]
+pub struct InitInsuranceVault<'info> {
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    /// CHECK: Lending market authority PDA
+    #[account(
+        seeds = [b"authority", lending_market.key().as_ref()],
+        bump
+    )]
+    pub lending_market_authority: UncheckedAccount<'info>,
+
+    #[account(
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+        constraint = mint.key() == reserve.liquidity_mint || mint.key() == reserve.collateral_mint
+            @ LendingError::InvalidMint,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = payer,
+        token::mint = mint,
+        token::authority = lending_market_authority,
+        token::token_program = token_program,
+        seeds = [
+            LendingMarket::INSURANCE_VAULT_SEED,
+            lending_market.key().as_ref(),
+            mint.key().as_ref(),
+        ],
+        bump
+    )]
+    pub insurance_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event]
+pub struct InsuranceVaultInitialized {
+    pub lending_market: Pubkey,
+    pub reserve: Pubkey,
+    pub mint: Pubkey,
+    pub insurance_vault: Pubkey,
+}
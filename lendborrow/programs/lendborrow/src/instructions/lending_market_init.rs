@@ -6,13 +6,20 @@ use crate::states::lending_market::LendingMarket;
 
 pub fn lending_market_init(
     ctx: Context<InitLendingMarket>,
+    market_id: u16,
     quote_currency: [u8; 32],
+    name: [u8; 32],
 ) -> Result<()> {
     require!(
         LendingMarket::validate_quote_currency(&quote_currency),
         LendingError::InvalidQuoteCurrency
     );
 
+    require!(
+        LendingMarket::validate_name(&name),
+        LendingError::InvalidMarketName
+    );
+
     let lending_market = &mut ctx.accounts.lending_market;
     
     let (authority, authority_bump) = Pubkey::find_program_address(
@@ -26,16 +33,31 @@ pub fn lending_market_init(
     lending_market.version = LendingMarket::PROGRAM_VERSION as u64;
     lending_market.bump_seed = ctx.bumps.lending_market;
     lending_market.owner = ctx.accounts.owner.key();
+    lending_market.pending_owner = Pubkey::default();
+    lending_market.market_id = market_id;
     lending_market.authority = authority;
     lending_market.authority_bump = authority_bump;
     lending_market.quote_currency = quote_currency;
+    lending_market.name = name;
     lending_market.token_program_id = ctx.accounts.token_program.key();
+    lending_market.max_liquidation_threshold_step =
+        LendingMarket::DEFAULT_MAX_LIQUIDATION_THRESHOLD_STEP;
+    lending_market.guardian = Pubkey::default();
+    lending_market.risk_manager = Pubkey::default();
+    lending_market.fee_manager = Pubkey::default();
+    lending_market.timelock_delay_slots = 0;
+    lending_market.queued_change_count = 0;
+    lending_market.paused_operations = 0;
+    lending_market.insurance_fee_percentage = 0;
+    lending_market.emode_categories = Default::default();
 
     emit!(LendingMarketInitialized {
         lending_market: lending_market.key(),
         owner: lending_market.owner,
+        market_id,
         authority,
         quote_currency,
+        name,
         bump: lending_market.bump_seed,
     });
 
@@ -43,6 +65,7 @@ pub fn lending_market_init(
 }
 
 #[derive(Accounts)]
+#[instruction(market_id: u16)]
 pub struct InitLendingMarket<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
@@ -51,7 +74,11 @@ pub struct InitLendingMarket<'info> {
         init,
         payer = owner,
         space = 8 + LendingMarket::INIT_SPACE,
-        seeds = [LendingMarket::SEED_PREFIX, owner.key().as_ref()],
+        seeds = [
+            LendingMarket::SEED_PREFIX,
+            owner.key().as_ref(),
+            market_id.to_le_bytes().as_ref(),
+        ],
         bump
     )]
     pub lending_market: Account<'info, LendingMarket>,
@@ -64,7 +91,9 @@ pub struct InitLendingMarket<'info> {
 pub struct LendingMarketInitialized {
     pub lending_market: Pubkey,
     pub owner: Pubkey,
+    pub market_id: u16,
     pub authority:Pubkey,
     pub quote_currency: [u8; 32],
+    pub name: [u8; 32],
     pub bump: u8,
 }
\ No newline at end of file
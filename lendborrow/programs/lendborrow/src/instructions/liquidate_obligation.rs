@@ -1,17 +1,178 @@
 use crate::errors::LendingError;
 use crate::states::{LendingMarket, Obligation, Reserve};
 use crate::utils::calculate_liquidation;
+use crate::utils::token::transfer_checked_received;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 
-pub fn handler(ctx: Context<LiquidateObligation>, liquidity_amount: u64) -> Result<()> {
+pub fn handler(mut ctx: Context<LiquidateObligation>, liquidity_amount: u64) -> Result<()> {
+    let authority_bump = ctx.bumps.lending_market_authority;
+    let isolated_reserve_key = isolated_collateral_reserve_key(&ctx.accounts.obligation);
+
+    let accounts = &mut ctx.accounts;
+    let liquidation = liquidate(
+        LiquidationAccounts {
+            source_liquidity: &accounts.source_liquidity,
+            repay_reserve: &mut accounts.repay_reserve,
+            liquidity_mint: &accounts.liquidity_mint,
+            destination_liquidity: &mut accounts.destination_liquidity,
+            withdraw_reserve: &mut accounts.withdraw_reserve,
+            collateral_mint: &accounts.collateral_mint,
+            withdraw_reserve_collateral_supply: &accounts.withdraw_reserve_collateral_supply,
+            obligation: &mut accounts.obligation,
+            lending_market: &accounts.lending_market,
+            lending_market_authority: &accounts.lending_market_authority,
+            user_transfer_authority: &accounts.user_transfer_authority,
+            insurance_vault: accounts.insurance_vault.as_deref_mut(),
+            token_program: &accounts.token_program,
+            withdraw_token_program: &accounts.withdraw_token_program,
+        },
+        authority_bump,
+        liquidity_amount,
+    )?;
+
+    // `withdraw_reserve` is read-only in this instruction, so the isolated
+    // reserve is passed as its own writable account.
+    if let Some(isolated_reserve_key) = isolated_reserve_key {
+        let isolated_reserve = ctx
+            .accounts
+            .isolated_collateral_reserve
+            .as_mut()
+            .ok_or(LendingError::InvalidIsolatedReserve)?;
+
+        release_isolated_debt(
+            isolated_reserve,
+            isolated_reserve_key,
+            &ctx.accounts.repay_reserve,
+            liquidation.settle_amount_wads,
+        )?;
+    }
+
+    let liquidator_collateral = liquidation
+        .withdraw_collateral
+        .checked_sub(liquidation.insurance_collateral)
+        .ok_or(LendingError::MathOverflow)?;
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.withdraw_token_program.to_account_info(),
+            TransferChecked {
+                from: ctx
+                    .accounts
+                    .withdraw_reserve_collateral_supply
+                    .to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: ctx.accounts.destination_collateral.to_account_info(),
+                authority: ctx.accounts.lending_market_authority.to_account_info(),
+            },
+            &[authority_seeds],
+        ),
+        liquidator_collateral,
+        ctx.accounts.collateral_mint.decimals,
+    )?;
+
+    emit!(liquidation);
+
+    Ok(())
+}
+
+/// Accounts used by `liquidate`, borrowed from either liquidation instruction.
+pub(crate) struct LiquidationAccounts<'a, 'info> {
+    pub source_liquidity: &'a InterfaceAccount<'info, TokenAccount>,
+    pub repay_reserve: &'a mut Account<'info, Reserve>,
+    pub liquidity_mint: &'a InterfaceAccount<'info, Mint>,
+    pub destination_liquidity: &'a mut InterfaceAccount<'info, TokenAccount>,
+    pub withdraw_reserve: &'a mut Account<'info, Reserve>,
+    pub collateral_mint: &'a InterfaceAccount<'info, Mint>,
+    pub withdraw_reserve_collateral_supply: &'a InterfaceAccount<'info, TokenAccount>,
+    pub obligation: &'a mut Account<'info, Obligation>,
+    pub lending_market: &'a Account<'info, LendingMarket>,
+    pub lending_market_authority: &'a UncheckedAccount<'info>,
+    pub user_transfer_authority: &'a Signer<'info>,
+    pub insurance_vault: Option<&'a mut InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: &'a Interface<'info, TokenInterface>,
+    pub withdraw_token_program: &'a Interface<'info, TokenInterface>,
+}
+
+/// Key of the obligation's isolated collateral reserve, if it is isolated.
+///
+/// Read before liquidating, since settling the last borrow and deposit clears it.
+pub(crate) fn isolated_collateral_reserve_key(obligation: &Obligation) -> Option<Pubkey> {
+    obligation
+        .is_isolated()
+        .then_some(obligation.isolated_collateral_reserve)
+}
+
+/// Releases `settle_amount_wads` of liquidated debt from the ceiling of the
+/// obligation's isolated collateral reserve.
+pub(crate) fn release_isolated_debt(
+    isolated_reserve: &mut Account<Reserve>,
+    isolated_reserve_key: Pubkey,
+    repay_reserve: &Account<Reserve>,
+    settle_amount_wads: u128,
+) -> Result<()> {
+    require_keys_eq!(
+        isolated_reserve.key(),
+        isolated_reserve_key,
+        LendingError::InvalidIsolatedReserve
+    );
+    require_keys_neq!(
+        isolated_reserve.key(),
+        repay_reserve.key(),
+        LendingError::InvalidIsolatedReserve
+    );
+
+    isolated_reserve.remove_isolated_debt(repay_reserve.debt_value(settle_amount_wads)?);
+
+    Ok(())
+}
+
+/// Repays debt and seizes collateral from an unhealthy obligation.
+///
+/// Everything up to paying out the seized collateral is shared by both
+/// liquidation instructions: the repayment is taken from the liquidator, the
+/// obligation and reserves are settled and the insurance share is moved to the
+/// insurance vault. The liquidator's part of `withdraw_collateral`, net of
+/// `insurance_collateral`, is left in `withdraw_reserve_collateral_supply` for
+/// the caller to pay out. Releasing isolated debt is left to the caller, which
+/// knows which reserve account is written back.
+pub(crate) fn liquidate(
+    accounts: LiquidationAccounts,
+    authority_bump: u8,
+    liquidity_amount: u64,
+) -> Result<ObligationLiquidated> {
     require!(liquidity_amount > 0, LendingError::InvalidAmount);
 
-    let obligation = &mut ctx.accounts.obligation;
-    let repay_reserve = &mut ctx.accounts.repay_reserve;  
-    let withdraw_reserve = &ctx.accounts.withdraw_reserve;
+    let LiquidationAccounts {
+        source_liquidity,
+        repay_reserve,
+        liquidity_mint,
+        destination_liquidity,
+        withdraw_reserve,
+        collateral_mint,
+        withdraw_reserve_collateral_supply,
+        obligation,
+        lending_market,
+        lending_market_authority,
+        user_transfer_authority,
+        insurance_vault,
+        token_program,
+        withdraw_token_program,
+    } = accounts;
     let clock = Clock::get()?;
 
+    lending_market.require_not_paused(
+        repay_reserve.paused_operations,
+        LendingMarket::PAUSE_LIQUIDATE,
+    )?;
+    lending_market.require_not_paused(
+        withdraw_reserve.paused_operations,
+        LendingMarket::PAUSE_LIQUIDATE,
+    )?;
+
     require!(
         obligation.last_update_slot == clock.slot,
         LendingError::ObligationStale
@@ -69,65 +230,118 @@ pub fn handler(ctx: Context<LiquidateObligation>, liquidity_amount: u64) -> Resu
         LendingError::LiquidationTooSmall
     );
 
-    repay_reserve.liquidity_borrowed_amount_wads = repay_reserve
-        .liquidity_borrowed_amount_wads
-        .checked_sub(liquidation_result.settle_amount_wads)
-        .ok_or(LendingError::MathOverflow)?;
-
-    repay_reserve.liquidity_available_amount = repay_reserve
-        .liquidity_available_amount
-        .checked_add(liquidation_result.repay_amount)
-        .ok_or(LendingError::MathOverflow)?;
-
-    
-    obligation.repay(liquidity_index, liquidation_result.settle_amount_wads)?;
-    obligation.withdraw(collateral_index, liquidation_result.withdraw_collateral)?;
-
-    token::transfer(
+    let received_amount = transfer_checked_received(
         CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.source_liquidity.to_account_info(),
-                to: ctx.accounts.destination_liquidity.to_account_info(),
-                authority: ctx.accounts.user_transfer_authority.to_account_info(),
+            token_program.to_account_info(),
+            TransferChecked {
+                from: source_liquidity.to_account_info(),
+                mint: liquidity_mint.to_account_info(),
+                to: destination_liquidity.to_account_info(),
+                authority: user_transfer_authority.to_account_info(),
             },
         ),
+        destination_liquidity,
         liquidation_result.repay_amount,
+        liquidity_mint.decimals,
     )?;
 
-    let lending_market_key = ctx.accounts.lending_market.key();
-    let authority_bump = ctx.bumps.lending_market_authority;
-    let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
+    // A transfer fee on the repay mint shrinks the repayment, so the debt
+    // settled and the collateral seized shrink with it.
+    let (settle_amount_wads, withdraw_collateral) =
+        if received_amount < liquidation_result.repay_amount {
+            let settle_amount_wads = liquidation_result
+                .settle_amount_wads
+                .checked_mul(received_amount as u128)
+                .and_then(|v| v.checked_div(liquidation_result.repay_amount as u128))
+                .ok_or(LendingError::MathOverflow)?;
+            let withdraw_collateral = (liquidation_result.withdraw_collateral as u128)
+                .checked_mul(received_amount as u128)
+                .and_then(|v| v.checked_div(liquidation_result.repay_amount as u128))
+                .ok_or(LendingError::MathOverflow)? as u64;
+            (settle_amount_wads, withdraw_collateral)
+        } else {
+            (
+                liquidation_result.settle_amount_wads,
+                liquidation_result.withdraw_collateral,
+            )
+        };
+
+    require!(withdraw_collateral > 0, LendingError::LiquidationTooSmall);
+
+    repay_reserve.settle_repayment(received_amount, settle_amount_wads)?;
+
+    obligation.repay(liquidity_index, settle_amount_wads)?;
+    obligation.withdraw(collateral_index, withdraw_collateral)?;
+
+    // Its cached values still show the pre-liquidation position, so another
+    // liquidation needs a refresh first; otherwise the close factor could be
+    // applied again and again within one transaction.
+    obligation.mark_stale();
+
+    // When both reserves are writable, `withdraw_reserve` is serialized last,
+    // so if they are the same account it has to carry the repayment too.
+    if withdraw_reserve.key() == repay_reserve.key() {
+        withdraw_reserve.set_inner((**repay_reserve).clone());
+    }
+
+    // The insurance share comes out of the bonus part of the seized collateral.
+    let bonus_collateral = (withdraw_collateral as u128)
+        .checked_mul(liquidation_result.liquidation_bonus as u128)
+        .and_then(|v| v.checked_div(100 + liquidation_result.liquidation_bonus as u128))
+        .ok_or(LendingError::MathOverflow)? as u64;
+
+    // Vault init is permissionless, so a market taking an insurance share
+    // can always insist on the vault.
+    require!(
+        lending_market.insurance_fee_percentage == 0 || insurance_vault.is_some(),
+        LendingError::InvalidInsuranceVault
+    );
 
-    token::transfer(
-        CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx
-                    .accounts
-                    .withdraw_reserve_collateral_supply
-                    .to_account_info(),
-                to: ctx.accounts.destination_collateral.to_account_info(),
-                authority: ctx.accounts.lending_market_authority.to_account_info(),
-            },
-            &[authority_seeds],
-        ),
-        liquidation_result.withdraw_collateral,
-    )?;
+    let insurance_collateral = lending_market.insurance_share(bonus_collateral)?;
 
-    emit!(ObligationLiquidated {
+    let lending_market_key = lending_market.key();
+    let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
+
+    if let Some(insurance_vault) = insurance_vault.as_ref().filter(|_| insurance_collateral > 0) {
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                withdraw_token_program.to_account_info(),
+                TransferChecked {
+                    from: withdraw_reserve_collateral_supply.to_account_info(),
+                    mint: collateral_mint.to_account_info(),
+                    to: insurance_vault.to_account_info(),
+                    authority: lending_market_authority.to_account_info(),
+                },
+                &[authority_seeds],
+            ),
+            insurance_collateral,
+            collateral_mint.decimals,
+        )?;
+    }
+
+    let insurance_vault_balance = match insurance_vault {
+        Some(insurance_vault) => {
+            insurance_vault.reload()?;
+            insurance_vault.amount
+        }
+        None => 0,
+    };
+
+    Ok(ObligationLiquidated {
         obligation: obligation.key(),
         repay_reserve: repay_reserve.key(),
         withdraw_reserve: withdraw_reserve.key(),
         liquidity_amount,
-        repay_amount: liquidation_result.repay_amount,
-        settle_amount_wads: liquidation_result.settle_amount_wads,
-        withdraw_collateral: liquidation_result.withdraw_collateral,
-        liquidator: ctx.accounts.user_transfer_authority.key(),
+        repay_amount: received_amount,
+        settle_amount_wads,
+        withdraw_collateral,
+        insurance_collateral,
+        insurance_vault_balance,
+        close_factor: liquidation_result.close_factor,
+        liquidation_bonus: liquidation_result.liquidation_bonus,
+        liquidator: user_transfer_authority.key(),
         slot: clock.slot,
-    });
-
-    Ok(())
+    })
 }
 
 #[derive(Accounts)]
@@ -137,41 +351,47 @@ pub struct LiquidateObligation<'info> {
         constraint = source_liquidity.key() != repay_reserve.liquidity_supply 
             @ LendingError::InvalidAccountInput,
     )]
-    pub source_liquidity: Box<Account<'info, TokenAccount>>,
+    pub source_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
         constraint = destination_collateral.key() != withdraw_reserve.collateral_supply 
             @ LendingError::InvalidAccountInput,
     )]
-    pub destination_collateral: Box<Account<'info, TokenAccount>>,
+    pub destination_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
         constraint = repay_reserve.lending_market == lending_market.key() 
             @ LendingError::InvalidLendingMarket,
+        has_one = liquidity_mint @ LendingError::InvalidLiquidityMint,
     )]
     pub repay_reserve: Box<Account<'info, Reserve>>,
 
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
     #[account(
         mut,
         constraint = destination_liquidity.key() == repay_reserve.liquidity_supply 
             @ LendingError::InvalidLiquiditySupply,
     )]
-    pub destination_liquidity: Box<Account<'info, TokenAccount>>,
+    pub destination_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         constraint = withdraw_reserve.lending_market == lending_market.key() 
             @ LendingError::InvalidLendingMarket,
+        has_one = collateral_mint @ LendingError::InvalidCollateralMint,
     )]
     pub withdraw_reserve: Box<Account<'info, Reserve>>,
 
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
     #[account(
         mut,
         constraint = withdraw_reserve_collateral_supply.key() == withdraw_reserve.collateral_supply 
             @ LendingError::InvalidCollateralSupply,
     )]
-    pub withdraw_reserve_collateral_supply: Box<Account<'info, TokenAccount>>,
+    pub withdraw_reserve_collateral_supply: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
@@ -181,6 +401,7 @@ pub struct LiquidateObligation<'info> {
             Obligation::SEED_PREFIX,
             lending_market.key().as_ref(),
             obligation.owner.as_ref(),
+            obligation.id.to_le_bytes().as_ref(),
         ],
         bump
     )]
@@ -197,7 +418,33 @@ pub struct LiquidateObligation<'info> {
 
     pub user_transfer_authority: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// Collateral reserve of an isolated obligation; required so liquidated
+    /// debt is released from its debt ceiling.
+    #[account(
+        mut,
+        constraint = isolated_collateral_reserve.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+    )]
+    pub isolated_collateral_reserve: Option<Box<Account<'info, Reserve>>>,
+
+    /// Insurance vault for the withdraw reserve's collateral mint; required
+    /// while the market's `insurance_fee_percentage` is non-zero.
+    #[account(
+        mut,
+        seeds = [
+            LendingMarket::INSURANCE_VAULT_SEED,
+            lending_market.key().as_ref(),
+            collateral_mint.key().as_ref(),
+        ],
+        bump
+    )]
+    pub insurance_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// Token program of the repay reserve's liquidity mint.
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Token program of the withdraw reserve's collateral mint.
+    pub withdraw_token_program: Interface<'info, TokenInterface>,
 }
 
 #[event]
@@ -209,6 +456,10 @@ pub struct ObligationLiquidated {
     pub repay_amount: u64,
     pub settle_amount_wads: u128,
     pub withdraw_collateral: u64,
+    pub insurance_collateral: u64,
+    pub insurance_vault_balance: u64,
+    pub close_factor: u8,
+    pub liquidation_bonus: u8,
     pub liquidator: Pubkey,
     pub slot: u64,
-}
\ No newline at end of file
+}
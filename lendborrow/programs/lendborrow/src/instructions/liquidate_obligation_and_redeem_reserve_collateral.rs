@@ -0,0 +1,276 @@
+use crate::errors::LendingError;
+use crate::instructions::liquidate_obligation::{
+    isolated_collateral_reserve_key, liquidate, release_isolated_debt, LiquidationAccounts,
+};
+use crate::instructions::redeem_reserve_collateral::CollateralRedeemed;
+use crate::states::{LendingMarket, Obligation, Reserve};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    self, Burn, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+pub fn handler(
+    mut ctx: Context<LiquidateObligationAndRedeemReserveCollateral>,
+    liquidity_amount: u64,
+) -> Result<()> {
+    let authority_bump = ctx.bumps.lending_market_authority;
+    let isolated_reserve_key = isolated_collateral_reserve_key(&ctx.accounts.obligation);
+
+    let accounts = &mut ctx.accounts;
+    let liquidation = liquidate(
+        LiquidationAccounts {
+            source_liquidity: &accounts.source_liquidity,
+            repay_reserve: &mut accounts.repay_reserve,
+            liquidity_mint: &accounts.liquidity_mint,
+            destination_liquidity: &mut accounts.destination_liquidity,
+            withdraw_reserve: &mut accounts.withdraw_reserve,
+            collateral_mint: &accounts.collateral_mint,
+            withdraw_reserve_collateral_supply: &accounts.withdraw_reserve_collateral_supply,
+            obligation: &mut accounts.obligation,
+            lending_market: &accounts.lending_market,
+            lending_market_authority: &accounts.lending_market_authority,
+            user_transfer_authority: &accounts.user_transfer_authority,
+            insurance_vault: accounts.insurance_vault.as_deref_mut(),
+            token_program: &accounts.token_program,
+            withdraw_token_program: &accounts.withdraw_token_program,
+        },
+        authority_bump,
+        liquidity_amount,
+    )?;
+
+    // An isolated obligation only holds collateral in its isolated reserve, so
+    // that is the withdraw reserve and its debt ceiling is released there.
+    if let Some(isolated_reserve_key) = isolated_reserve_key {
+        release_isolated_debt(
+            &mut accounts.withdraw_reserve,
+            isolated_reserve_key,
+            &accounts.repay_reserve,
+            liquidation.settle_amount_wads,
+        )?;
+    }
+
+    let collateral_amount = liquidation
+        .withdraw_collateral
+        .checked_sub(liquidation.insurance_collateral)
+        .ok_or(LendingError::MathOverflow)?;
+
+    let withdraw_reserve = &mut accounts.withdraw_reserve;
+
+    // The withdraw reserve was checked fresh by the liquidation itself, so the
+    // exchange rate used here is current.
+    let can_redeem = accounts
+        .lending_market
+        .require_not_paused(
+            withdraw_reserve.paused_operations,
+            LendingMarket::PAUSE_REDEEM,
+        )
+        .is_ok()
+        && withdraw_reserve
+            .redeem_collateral(collateral_amount)
+            .is_ok_and(|liquidity_out| {
+                liquidity_out > 0 && liquidity_out <= withdraw_reserve.liquidity_available_amount
+            });
+
+    let liquidity_out = if can_redeem {
+        withdraw_reserve.withdraw_liquidity(collateral_amount)?
+    } else {
+        0
+    };
+
+    let lending_market_key = accounts.lending_market.key();
+    let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
+
+    if can_redeem {
+        token_interface::burn(
+            CpiContext::new_with_signer(
+                accounts.withdraw_token_program.to_account_info(),
+                Burn {
+                    mint: accounts.collateral_mint.to_account_info(),
+                    from: accounts
+                        .withdraw_reserve_collateral_supply
+                        .to_account_info(),
+                    authority: accounts.lending_market_authority.to_account_info(),
+                },
+                &[authority_seeds],
+            ),
+            collateral_amount,
+        )?;
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                accounts.withdraw_token_program.to_account_info(),
+                TransferChecked {
+                    from: accounts.withdraw_reserve_liquidity_supply.to_account_info(),
+                    mint: accounts.withdraw_liquidity_mint.to_account_info(),
+                    to: accounts.destination_withdraw_liquidity.to_account_info(),
+                    authority: accounts.lending_market_authority.to_account_info(),
+                },
+                &[authority_seeds],
+            ),
+            liquidity_out,
+            accounts.withdraw_liquidity_mint.decimals,
+        )?;
+    } else {
+        msg!("Withdraw reserve cannot redeem, paying out collateral instead");
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                accounts.withdraw_token_program.to_account_info(),
+                TransferChecked {
+                    from: accounts
+                        .withdraw_reserve_collateral_supply
+                        .to_account_info(),
+                    mint: accounts.collateral_mint.to_account_info(),
+                    to: accounts.destination_collateral.to_account_info(),
+                    authority: accounts.lending_market_authority.to_account_info(),
+                },
+                &[authority_seeds],
+            ),
+            collateral_amount,
+            accounts.collateral_mint.decimals,
+        )?;
+    }
+
+    emit!(liquidation);
+
+    if can_redeem {
+        emit!(CollateralRedeemed {
+            reserve: withdraw_reserve.key(),
+            collateral_amount,
+            liquidity_amount: liquidity_out,
+            redeemer: accounts.user_transfer_authority.key(),
+            slot: liquidation.slot,
+        });
+    }
+
+    Ok(())
+}
+
+/// Accounts context for liquidating an obligation and redeeming the seized
+/// collateral to liquidity in one step.
+///
+/// - Takes the accounts of `liquidate_obligation`, with `withdraw_reserve` and
+///   `collateral_mint` writable so the collateral can be burned.
+/// - For an isolated obligation `withdraw_reserve` is its isolated collateral
+///   reserve, and liquidated debt is released from that reserve's ceiling.
+/// - The seized collateral is burned from the withdraw reserve's collateral
+///   supply and its liquidity sent to `destination_withdraw_liquidity`.
+/// - When the withdraw reserve lacks available liquidity or has redemptions
+///   paused, the collateral goes to `destination_collateral` instead.
+#[derive(Accounts)]
+pub struct LiquidateObligationAndRedeemReserveCollateral<'info> {
+    #[account(
+        mut,
+        constraint = source_liquidity.key() != repay_reserve.liquidity_supply
+            @ LendingError::InvalidAccountInput,
+    )]
+    pub source_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = destination_collateral.key() != withdraw_reserve.collateral_supply
+            @ LendingError::InvalidAccountInput,
+    )]
+    pub destination_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = repay_reserve.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+        has_one = liquidity_mint @ LendingError::InvalidLiquidityMint,
+    )]
+    pub repay_reserve: Box<Account<'info, Reserve>>,
+
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = destination_liquidity.key() == repay_reserve.liquidity_supply
+            @ LendingError::InvalidLiquiditySupply,
+    )]
+    pub destination_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = withdraw_reserve.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+        has_one = collateral_mint @ LendingError::InvalidCollateralMint,
+    )]
+    pub withdraw_reserve: Box<Account<'info, Reserve>>,
+
+    #[account(mut)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = withdraw_reserve_collateral_supply.key() == withdraw_reserve.collateral_supply
+            @ LendingError::InvalidCollateralSupply,
+    )]
+    pub withdraw_reserve_collateral_supply: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = withdraw_reserve_liquidity_supply.key() == withdraw_reserve.liquidity_supply
+            @ LendingError::InvalidLiquiditySupply,
+    )]
+    pub withdraw_reserve_liquidity_supply: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = withdraw_liquidity_mint.key() == withdraw_reserve.liquidity_mint
+            @ LendingError::InvalidLiquidityMint,
+    )]
+    pub withdraw_liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = destination_withdraw_liquidity.key() != withdraw_reserve.liquidity_supply
+            @ LendingError::InvalidAccountInput,
+        constraint = destination_withdraw_liquidity.mint == withdraw_reserve.liquidity_mint
+            @ LendingError::InvalidMint,
+    )]
+    pub destination_withdraw_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = obligation.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+        seeds = [
+            Obligation::SEED_PREFIX,
+            lending_market.key().as_ref(),
+            obligation.owner.as_ref(),
+            obligation.id.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub obligation: Box<Account<'info, Obligation>>,
+
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    /// CHECK: Lending market authority PDA
+    #[account(
+        seeds = [b"authority", lending_market.key().as_ref()],
+        bump
+    )]
+    pub lending_market_authority: UncheckedAccount<'info>,
+
+    pub user_transfer_authority: Signer<'info>,
+
+    /// Insurance vault for the withdraw reserve's collateral mint; required
+    /// while the market's `insurance_fee_percentage` is non-zero.
+    #[account(
+        mut,
+        seeds = [
+            LendingMarket::INSURANCE_VAULT_SEED,
+            lending_market.key().as_ref(),
+            collateral_mint.key().as_ref(),
+        ],
+        bump
+    )]
+    pub insurance_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// Token program of the repay reserve's liquidity mint.
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Token program of the withdraw reserve's collateral and liquidity mints.
+    pub withdraw_token_program: Interface<'info, TokenInterface>,
+}
@@ -7,21 +7,193 @@ pub mod refresh_obligation;
 pub mod refresh_reserve;
 pub mod repay_obligation_liquidity;
 pub mod reserve_init;
-pub mod set_lending_market_owner;
+pub mod propose_lending_market_owner;
+pub mod accept_lending_market_owner;
+pub mod cancel_lending_market_owner;
 pub mod withdraw_obligation_collateral;
 pub mod deposit_reserve_liquidity;
 pub mod redeem_reserve_collateral;
+pub mod flash_borrow_reserve_liquidity;
+pub mod flash_repay_reserve_liquidity;
+pub mod update_reserve_config;
+pub mod set_max_liquidation_threshold_step;
+pub mod claim_protocol_fees;
+pub mod set_lending_market_guardian;
+pub mod set_lending_market_risk_manager;
+pub mod set_lending_market_fee_manager;
+pub mod set_lending_market_pause_flags;
+pub mod set_reserve_pause_flags;
+pub mod set_reserve_secondary_oracle;
+pub mod set_emode_category;
+pub mod set_obligation_emode;
+pub mod set_reserve_fee_receiver;
+pub mod set_timelock_delay;
+pub mod queue_admin_change;
+pub mod execute_admin_change;
+pub mod cancel_admin_change;
+pub mod socialize_bad_debt;
+pub mod init_insurance_vault;
+pub mod set_insurance_fee_percentage;
+pub mod draw_insurance;
+pub mod liquidate_obligation_and_redeem_reserve_collateral;
+pub mod deposit_reserve_liquidity_and_obligation_collateral;
+pub mod withdraw_obligation_collateral_and_redeem_reserve_collateral;
 
-pub use borrow_obligation_liquidity::*;
-pub use deposit_obligation_collateral::*;
-pub use lending_market_init::*;
-pub use liquidate_obligation::*;
-pub use obligation_init::*;
-pub use refresh_obligation::*;
-pub use refresh_reserve::*;
-pub use repay_obligation_liquidity::*;
-pub use reserve_init::*;
-pub use set_lending_market_owner::*;
-pub use withdraw_obligation_collateral::*;
-pub use deposit_reserve_liquidity::*;
-pub use redeem_reserve_collateral::*;
+pub use borrow_obligation_liquidity::{BorrowObligationLiquidity, LiquidityBorrowed};
+pub use deposit_obligation_collateral::{DepositObligationCollateral, CollateralDeposited};
+pub use lending_market_init::{lending_market_init, InitLendingMarket, LendingMarketInitialized};
+pub use liquidate_obligation::{LiquidateObligation, ObligationLiquidated};
+pub use obligation_init::{InitObligation, ObligationInitialized};
+pub use refresh_obligation::{RefreshObligation, ObligationRefreshed};
+pub use refresh_reserve::{RefreshReserve, ReserveRefreshed};
+pub use repay_obligation_liquidity::{RepayObligationLiquidity, LiquidityRepaid};
+pub use reserve_init::{InitReserve, ReserveInitialized};
+pub use propose_lending_market_owner::{ProposeLendingMarketOwner, LendingMarketOwnerProposed};
+pub use accept_lending_market_owner::{AcceptLendingMarketOwner, LendingMarketOwnerChanged};
+pub use cancel_lending_market_owner::{
+    CancelLendingMarketOwner,
+    LendingMarketOwnerProposalCancelled,
+};
+pub use withdraw_obligation_collateral::{WithdrawObligationCollateral, CollateralWithdrawn};
+pub use deposit_reserve_liquidity::{DepositReserveLiquidity, LiquidityDeposited};
+pub use redeem_reserve_collateral::{RedeemReserveCollateral, CollateralRedeemed};
+pub use flash_borrow_reserve_liquidity::{
+    FLASH_BORROW_RESERVE_ACCOUNT_INDEX,
+    FlashBorrowReserveLiquidity,
+    FlashLoanBorrowed,
+};
+pub use flash_repay_reserve_liquidity::{
+    FLASH_REPAY_RESERVE_ACCOUNT_INDEX,
+    FlashRepayReserveLiquidity,
+    FlashLoanRepaid,
+};
+pub use update_reserve_config::{UpdateReserveConfig, ReserveConfigUpdated};
+pub use set_max_liquidation_threshold_step::{
+    SetMaxLiquidationThresholdStep,
+    MaxLiquidationThresholdStepChanged,
+};
+pub use claim_protocol_fees::{ClaimProtocolFees, ProtocolFeesClaimed};
+pub use set_lending_market_guardian::{SetLendingMarketGuardian, LendingMarketGuardianChanged};
+pub use set_lending_market_risk_manager::{
+    SetLendingMarketRiskManager,
+    LendingMarketRiskManagerChanged,
+};
+pub use set_lending_market_fee_manager::{
+    SetLendingMarketFeeManager,
+    LendingMarketFeeManagerChanged,
+};
+pub use set_lending_market_pause_flags::{
+    SetLendingMarketPauseFlags,
+    LendingMarketPauseFlagsChanged,
+};
+pub use set_reserve_pause_flags::{SetReservePauseFlags, ReservePauseFlagsChanged};
+pub use set_reserve_secondary_oracle::{SetReserveSecondaryOracle, ReserveSecondaryOracleChanged};
+pub use set_emode_category::{SetEModeCategory, EModeCategoryChanged};
+pub use set_obligation_emode::{SetObligationEMode, ObligationEModeChanged};
+pub use set_reserve_fee_receiver::{SetReserveFeeReceiver, ReserveFeeReceiverChanged};
+pub use set_timelock_delay::{SetTimelockDelay, TimelockDelayChanged};
+pub use queue_admin_change::{QueueAdminChange, AdminChangeQueued};
+pub use execute_admin_change::{ExecuteAdminChange, AdminChangeExecuted};
+pub use cancel_admin_change::{CancelAdminChange, AdminChangeCancelled};
+pub use socialize_bad_debt::{SocializeBadDebt, BadDebtSocialized};
+pub use init_insurance_vault::{InitInsuranceVault, InsuranceVaultInitialized};
+pub use set_insurance_fee_percentage::{SetInsuranceFeePercentage, InsuranceFeePercentageChanged};
+pub use draw_insurance::{DrawInsurance, InsuranceDrawn};
+pub use liquidate_obligation_and_redeem_reserve_collateral::{
+    LiquidateObligationAndRedeemReserveCollateral,
+};
+pub use deposit_reserve_liquidity_and_obligation_collateral::{
+    DepositReserveLiquidityAndObligationCollateral,
+    LiquidityDepositedAsCollateral,
+};
+pub use withdraw_obligation_collateral_and_redeem_reserve_collateral::{
+    WithdrawObligationCollateralAndRedeemReserveCollateral,
+};
+
+// `#[program]` expects the client account modules generated by `#[derive(Accounts)]` at the
+// crate root.
+pub(crate) use self::{
+    borrow_obligation_liquidity::__client_accounts_borrow_obligation_liquidity,
+    deposit_obligation_collateral::__client_accounts_deposit_obligation_collateral,
+    lending_market_init::__client_accounts_init_lending_market,
+    liquidate_obligation::__client_accounts_liquidate_obligation,
+    obligation_init::__client_accounts_init_obligation,
+    refresh_obligation::__client_accounts_refresh_obligation,
+    refresh_reserve::__client_accounts_refresh_reserve,
+    repay_obligation_liquidity::__client_accounts_repay_obligation_liquidity,
+    reserve_init::__client_accounts_init_reserve,
+    propose_lending_market_owner::__client_accounts_propose_lending_market_owner,
+    accept_lending_market_owner::__client_accounts_accept_lending_market_owner,
+    cancel_lending_market_owner::__client_accounts_cancel_lending_market_owner,
+    withdraw_obligation_collateral::__client_accounts_withdraw_obligation_collateral,
+    deposit_reserve_liquidity::__client_accounts_deposit_reserve_liquidity,
+    redeem_reserve_collateral::__client_accounts_redeem_reserve_collateral,
+    flash_borrow_reserve_liquidity::__client_accounts_flash_borrow_reserve_liquidity,
+    flash_repay_reserve_liquidity::__client_accounts_flash_repay_reserve_liquidity,
+    update_reserve_config::__client_accounts_update_reserve_config,
+    set_max_liquidation_threshold_step::__client_accounts_set_max_liquidation_threshold_step,
+    claim_protocol_fees::__client_accounts_claim_protocol_fees,
+    set_lending_market_guardian::__client_accounts_set_lending_market_guardian,
+    set_lending_market_risk_manager::__client_accounts_set_lending_market_risk_manager,
+    set_lending_market_fee_manager::__client_accounts_set_lending_market_fee_manager,
+    set_lending_market_pause_flags::__client_accounts_set_lending_market_pause_flags,
+    set_reserve_pause_flags::__client_accounts_set_reserve_pause_flags,
+    set_reserve_secondary_oracle::__client_accounts_set_reserve_secondary_oracle,
+    set_emode_category::__client_accounts_set_e_mode_category,
+    set_obligation_emode::__client_accounts_set_obligation_e_mode,
+    set_reserve_fee_receiver::__client_accounts_set_reserve_fee_receiver,
+    set_timelock_delay::__client_accounts_set_timelock_delay,
+    queue_admin_change::__client_accounts_queue_admin_change,
+    execute_admin_change::__client_accounts_execute_admin_change,
+    cancel_admin_change::__client_accounts_cancel_admin_change,
+    socialize_bad_debt::__client_accounts_socialize_bad_debt,
+    init_insurance_vault::__client_accounts_init_insurance_vault,
+    set_insurance_fee_percentage::__client_accounts_set_insurance_fee_percentage,
+    draw_insurance::__client_accounts_draw_insurance,
+    liquidate_obligation_and_redeem_reserve_collateral::__client_accounts_liquidate_obligation_and_redeem_reserve_collateral,
+    deposit_reserve_liquidity_and_obligation_collateral::__client_accounts_deposit_reserve_liquidity_and_obligation_collateral,
+    withdraw_obligation_collateral_and_redeem_reserve_collateral::__client_accounts_withdraw_obligation_collateral_and_redeem_reserve_collateral,
+};
+#[cfg(feature = "cpi")]
+pub(crate) use self::{
+    borrow_obligation_liquidity::__cpi_client_accounts_borrow_obligation_liquidity,
+    deposit_obligation_collateral::__cpi_client_accounts_deposit_obligation_collateral,
+    lending_market_init::__cpi_client_accounts_init_lending_market,
+    liquidate_obligation::__cpi_client_accounts_liquidate_obligation,
+    obligation_init::__cpi_client_accounts_init_obligation,
+    refresh_obligation::__cpi_client_accounts_refresh_obligation,
+    refresh_reserve::__cpi_client_accounts_refresh_reserve,
+    repay_obligation_liquidity::__cpi_client_accounts_repay_obligation_liquidity,
+    reserve_init::__cpi_client_accounts_init_reserve,
+    propose_lending_market_owner::__cpi_client_accounts_propose_lending_market_owner,
+    accept_lending_market_owner::__cpi_client_accounts_accept_lending_market_owner,
+    cancel_lending_market_owner::__cpi_client_accounts_cancel_lending_market_owner,
+    withdraw_obligation_collateral::__cpi_client_accounts_withdraw_obligation_collateral,
+    deposit_reserve_liquidity::__cpi_client_accounts_deposit_reserve_liquidity,
+    redeem_reserve_collateral::__cpi_client_accounts_redeem_reserve_collateral,
+    flash_borrow_reserve_liquidity::__cpi_client_accounts_flash_borrow_reserve_liquidity,
+    flash_repay_reserve_liquidity::__cpi_client_accounts_flash_repay_reserve_liquidity,
+    update_reserve_config::__cpi_client_accounts_update_reserve_config,
+    set_max_liquidation_threshold_step::__cpi_client_accounts_set_max_liquidation_threshold_step,
+    claim_protocol_fees::__cpi_client_accounts_claim_protocol_fees,
+    set_lending_market_guardian::__cpi_client_accounts_set_lending_market_guardian,
+    set_lending_market_risk_manager::__cpi_client_accounts_set_lending_market_risk_manager,
+    set_lending_market_fee_manager::__cpi_client_accounts_set_lending_market_fee_manager,
+    set_lending_market_pause_flags::__cpi_client_accounts_set_lending_market_pause_flags,
+    set_reserve_pause_flags::__cpi_client_accounts_set_reserve_pause_flags,
+    set_reserve_secondary_oracle::__cpi_client_accounts_set_reserve_secondary_oracle,
+    set_emode_category::__cpi_client_accounts_set_e_mode_category,
+    set_obligation_emode::__cpi_client_accounts_set_obligation_e_mode,
+    set_reserve_fee_receiver::__cpi_client_accounts_set_reserve_fee_receiver,
+    set_timelock_delay::__cpi_client_accounts_set_timelock_delay,
+    queue_admin_change::__cpi_client_accounts_queue_admin_change,
+    execute_admin_change::__cpi_client_accounts_execute_admin_change,
+    cancel_admin_change::__cpi_client_accounts_cancel_admin_change,
+    socialize_bad_debt::__cpi_client_accounts_socialize_bad_debt,
+    init_insurance_vault::__cpi_client_accounts_init_insurance_vault,
+    set_insurance_fee_percentage::__cpi_client_accounts_set_insurance_fee_percentage,
+    draw_insurance::__cpi_client_accounts_draw_insurance,
+    liquidate_obligation_and_redeem_reserve_collateral::__cpi_client_accounts_liquidate_obligation_and_redeem_reserve_collateral,
+    deposit_reserve_liquidity_and_obligation_collateral::__cpi_client_accounts_deposit_reserve_liquidity_and_obligation_collateral,
+    withdraw_obligation_collateral_and_redeem_reserve_collateral::__cpi_client_accounts_withdraw_obligation_collateral_and_redeem_reserve_collateral,
+};
@@ -1,7 +1,7 @@
 use crate::states::{LendingMarket, Obligation};
 use anchor_lang::prelude::*;
 
-pub fn handler(ctx: Context<InitObligation>) -> Result<()> {
+pub fn handler(ctx: Context<InitObligation>, id: u16) -> Result<()> {
     let obligation = &mut ctx.accounts.obligation;
     let clock = Clock::get()?;
 
@@ -9,18 +9,22 @@ pub fn handler(ctx: Context<InitObligation>) -> Result<()> {
     obligation.last_update_slot = clock.slot;
     obligation.lending_market = ctx.accounts.lending_market.key();
     obligation.owner = ctx.accounts.owner.key();
+    obligation.id = id;
     obligation.deposited_value = 0;
     obligation.borrowed_value = 0;
     obligation.allowed_borrow_value = 0;
     obligation.unhealthy_borrow_value = 0;
     obligation.deposits_len = 0;
     obligation.borrows_len = 0;
+    obligation.isolated_collateral_reserve = Pubkey::default();
+    obligation.emode_category = 0;
     obligation.data_flat = Vec::new();
 
     emit!(ObligationInitialized {
         obligation: obligation.key(),
         lending_market: ctx.accounts.lending_market.key(),
         owner: ctx.accounts.owner.key(),
+        id,
         slot: clock.slot,
     });
 
@@ -28,6 +32,7 @@ pub fn handler(ctx: Context<InitObligation>) -> Result<()> {
 }
 
 #[derive(Accounts)]
+#[instruction(id: u16)]
 pub struct InitObligation<'info> {
     #[account(
         init,
@@ -37,6 +42,7 @@ pub struct InitObligation<'info> {
             Obligation::SEED_PREFIX,
             lending_market.key().as_ref(),
             owner.key().as_ref(),
+            id.to_le_bytes().as_ref(),
         ],
         bump
     )]
@@ -57,5 +63,6 @@ pub struct ObligationInitialized {
     pub obligation: Pubkey,
     pub lending_market: Pubkey,
     pub owner: Pubkey,
+    pub id: u16,
     pub slot: u64,
 }
@@ -2,10 +2,12 @@ use crate::errors::LendingError;
 use crate::states::LendingMarket;
 use anchor_lang::prelude::*;
 
-pub fn handler(ctx: Context<SetLendingMarketOwner>, new_owner: Pubkey) -> Result<()> {
+pub fn handler(ctx: Context<ProposeLendingMarketOwner>, new_owner: Pubkey) -> Result<()> {
     let lending_market = &mut ctx.accounts.lending_market;
     let current_owner = ctx.accounts.owner.key();
 
+    lending_market.require_no_timelock()?;
+
     require!(new_owner != current_owner, LendingError::SameOwner);
 
     require!(
@@ -13,24 +15,24 @@ pub fn handler(ctx: Context<SetLendingMarketOwner>, new_owner: Pubkey) -> Result
         LendingError::InvalidNewOwner
     );
 
-    let old_owner = lending_market.owner;
-
-    lending_market.owner = new_owner;
+    lending_market.pending_owner = new_owner;
 
-    emit!(LendingMarketOwnerChanged {
+    emit!(LendingMarketOwnerProposed {
         lending_market: lending_market.key(),
-        old_owner,
-        new_owner,
+        owner: current_owner,
+        pending_owner: new_owner,
     });
     Ok(())
 }
 
-/// Accounts context for updating the owner of a `LendingMarket`.
+/// Accounts context for proposing a new owner of a `LendingMarket`.
 ///
 /// - `lending_market` must currently be owned by `owner`.
 /// - `owner` must sign the transaction.
+/// - Ownership only moves once the proposed key calls `accept_lending_market_owner`.
+/// - Markets with a timelock must queue `AdminChange::PendingOwner` instead.
 #[derive(Accounts)]
-pub struct SetLendingMarketOwner<'info> {
+pub struct ProposeLendingMarketOwner<'info> {
     #[account(
         mut,
         has_one = owner @ LendingError::InvalidOwner
@@ -41,8 +43,8 @@ pub struct SetLendingMarketOwner<'info> {
 }
 
 #[event]
-pub struct LendingMarketOwnerChanged {
+pub struct LendingMarketOwnerProposed {
     pub lending_market: Pubkey,
-    pub old_owner: Pubkey,
-    pub new_owner: Pubkey,
+    pub owner: Pubkey,
+    pub pending_owner: Pubkey,
 }
@@ -0,0 +1,210 @@
+use crate::errors::LendingError;
+use crate::states::{AdminChange, LendingMarket, QueuedAdminChange, Reserve};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+pub fn handler(ctx: Context<QueueAdminChange>, change: AdminChange) -> Result<()> {
+    let lending_market = &mut ctx.accounts.lending_market;
+    let authority = ctx.accounts.authority.key();
+    let clock = Clock::get()?;
+
+    // Authorization and validation happen now, so depositors see a change that
+    // is known to apply when its eta arrives. Both are repeated on execution.
+    change.require_authority(
+        lending_market,
+        ctx.accounts.reserve.as_deref().map(|reserve| &**reserve),
+        &authority,
+    )?;
+
+    let reserve_key = match &change {
+        AdminChange::ReserveConfig(config) => {
+            let reserve = ctx
+                .accounts
+                .reserve
+                .as_ref()
+                .ok_or(LendingError::InvalidQueuedChangeReserve)?;
+
+            reserve.validate_config_update(config, lending_market)?;
+
+            reserve.key()
+        }
+        AdminChange::EModeCategory {
+            category_id,
+            loan_to_value_ratio,
+            liquidation_threshold,
+        } => {
+            LendingMarket::validate_emode_category(
+                *category_id,
+                *loan_to_value_ratio,
+                *liquidation_threshold,
+            )?;
+
+            Pubkey::default()
+        }
+        AdminChange::MaxLiquidationThresholdStep(max_step) => {
+            require!(*max_step <= 100, LendingError::InvalidConfig);
+
+            Pubkey::default()
+        }
+        AdminChange::TimelockDelay(delay_slots) => {
+            require!(
+                *delay_slots <= LendingMarket::MAX_TIMELOCK_DELAY_SLOTS,
+                LendingError::InvalidConfig
+            );
+
+            Pubkey::default()
+        }
+        AdminChange::SecondaryOracle(secondary_oracle) => {
+            let reserve = ctx
+                .accounts
+                .reserve
+                .as_ref()
+                .ok_or(LendingError::InvalidQueuedChangeReserve)?;
+
+            require_keys_neq!(
+                *secondary_oracle,
+                reserve.liquidity_oracle,
+                LendingError::InvalidOracleConfig
+            );
+
+            reserve.key()
+        }
+        AdminChange::PendingOwner(new_owner) => {
+            require!(*new_owner != authority, LendingError::SameOwner);
+            require!(*new_owner != Pubkey::default(), LendingError::InvalidNewOwner);
+
+            Pubkey::default()
+        }
+        AdminChange::InsuranceFeePercentage(insurance_fee_percentage) => {
+            require!(*insurance_fee_percentage <= 100, LendingError::InvalidConfig);
+
+            Pubkey::default()
+        }
+        AdminChange::FeeReceiver(new_fee_receiver) => {
+            let reserve = ctx
+                .accounts
+                .reserve
+                .as_ref()
+                .ok_or(LendingError::InvalidQueuedChangeReserve)?;
+            let fee_receiver = ctx
+                .accounts
+                .new_fee_receiver
+                .as_ref()
+                .ok_or(LendingError::InvalidFeeReceiver)?;
+
+            require_keys_eq!(
+                fee_receiver.key(),
+                *new_fee_receiver,
+                LendingError::InvalidFeeReceiver
+            );
+            require_keys_eq!(
+                fee_receiver.mint,
+                reserve.liquidity_mint,
+                LendingError::InvalidFeeReceiver
+            );
+            require_keys_neq!(
+                *new_fee_receiver,
+                reserve.liquidity_supply,
+                LendingError::InvalidFeeReceiver
+            );
+
+            reserve.key()
+        }
+        AdminChange::Guardian(new_guardian) => {
+            require!(*new_guardian != authority, LendingError::InvalidGuardian);
+
+            Pubkey::default()
+        }
+        AdminChange::RiskManager(new_risk_manager) => {
+            require!(*new_risk_manager != authority, LendingError::InvalidRiskManager);
+
+            Pubkey::default()
+        }
+        AdminChange::FeeManager(new_fee_manager) => {
+            require!(*new_fee_manager != authority, LendingError::InvalidFeeManager);
+
+            Pubkey::default()
+        }
+    };
+
+    let id = lending_market.queued_change_count;
+    let eta_slot = clock
+        .slot
+        .checked_add(lending_market.timelock_delay_slots)
+        .ok_or(LendingError::MathOverflow)?;
+
+    lending_market.queued_change_count = id.checked_add(1).ok_or(LendingError::MathOverflow)?;
+
+    let queued_change = &mut ctx.accounts.queued_change;
+    queued_change.lending_market = lending_market.key();
+    queued_change.reserve = reserve_key;
+    queued_change.proposer = authority;
+    queued_change.id = id;
+    queued_change.change = change.clone();
+    queued_change.queued_slot = clock.slot;
+    queued_change.eta_slot = eta_slot;
+
+    emit!(AdminChangeQueued {
+        queued_change: queued_change.key(),
+        lending_market: lending_market.key(),
+        reserve: reserve_key,
+        proposer: authority,
+        change,
+        eta_slot,
+    });
+
+    Ok(())
+}
+
+/// Accounts context for queueing a timelocked admin change.
+///
+/// - Reserve config, secondary oracle and e-mode changes need the owner or
+///   risk manager; fee changes also need fee authority.
+/// - Insurance fee share and fee receiver changes need the owner or fee manager.
+/// - Market settings, role rotations and owner proposals need the owner.
+/// - `reserve` is required for `AdminChange::ReserveConfig`,
+///   `AdminChange::SecondaryOracle` and `AdminChange::FeeReceiver`, and
+///   ignored otherwise.
+/// - `new_fee_receiver` is required for `AdminChange::FeeReceiver` and must
+///   hold the reserve's liquidity mint.
+#[derive(Accounts)]
+pub struct QueueAdminChange<'info> {
+    #[account(mut)]
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + QueuedAdminChange::INIT_SPACE,
+        seeds = [
+            LendingMarket::QUEUED_CHANGE_SEED,
+            lending_market.key().as_ref(),
+            lending_market.queued_change_count.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub queued_change: Box<Account<'info, QueuedAdminChange>>,
+
+    #[account(
+        constraint = reserve.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+    )]
+    pub reserve: Option<Box<Account<'info, Reserve>>>,
+
+    pub new_fee_receiver: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event]
+pub struct AdminChangeQueued {
+    pub queued_change: Pubkey,
+    pub lending_market: Pubkey,
+    pub reserve: Pubkey,
+    pub proposer: Pubkey,
+    pub change: AdminChange,
+    pub eta_slot: u64,
+}
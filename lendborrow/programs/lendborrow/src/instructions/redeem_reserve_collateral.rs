@@ -1,5 +1,5 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Burn, Token, TokenAccount, Mint, Transfer};
+use anchor_spl::token_interface::{self, Burn, Mint, TokenAccount, TokenInterface, TransferChecked};
 use crate::states::{Reserve, LendingMarket};
 use crate::errors::LendingError;
 
@@ -15,26 +15,15 @@ pub fn handler(
     let reserve = &mut ctx.accounts.reserve;
     let clock = Clock::get()?;
 
+    ctx.accounts
+        .lending_market
+        .require_not_paused(reserve.paused_operations, LendingMarket::PAUSE_REDEEM)?;
+
     require!(
         !reserve.is_stale(clock.slot)?,
         LendingError::ReserveStale
     );
-    let liquidity_amount = reserve.redeem_collateral(collateral_amount)?;
-
-    require!(
-        reserve.liquidity_available_amount >= liquidity_amount,
-        LendingError::InsufficientLiquidity
-    );
-
-    reserve.liquidity_available_amount = reserve
-        .liquidity_available_amount
-        .checked_sub(liquidity_amount)
-        .ok_or(LendingError::MathOverflow)?;
-
-    reserve.collateral_mint_total_supply = reserve
-        .collateral_mint_total_supply
-        .checked_sub(collateral_amount)
-        .ok_or(LendingError::MathOverflow)?;
+    let liquidity_amount = reserve.withdraw_liquidity(collateral_amount)?;
 
     reserve.last_update_slot = clock.slot;
 
@@ -49,7 +38,7 @@ pub fn handler(
         cpi_accounts,
     );
 
-    token::burn(cpi_ctx, collateral_amount)?;
+    token_interface::burn(cpi_ctx, collateral_amount)?;
 
     let lending_market_key = ctx.accounts.lending_market.key();
     let authority_bump = ctx.bumps.lending_market_authority;
@@ -60,8 +49,9 @@ pub fn handler(
     ];
     let signer_seeds = &[&seeds[..]];
 
-    let cpi_accounts = Transfer {
+    let cpi_accounts = TransferChecked {
         from: ctx.accounts.reserve_liquidity_supply.to_account_info(),
+        mint: ctx.accounts.liquidity_mint.to_account_info(),
         to: ctx.accounts.destination_liquidity.to_account_info(),
         authority: ctx.accounts.lending_market_authority.to_account_info(),
     };
@@ -72,7 +62,7 @@ pub fn handler(
         signer_seeds,
     );
 
-    token::transfer(cpi_ctx, liquidity_amount)?;
+    token_interface::transfer_checked(cpi_ctx, liquidity_amount, ctx.accounts.liquidity_mint.decimals)?;
 
     emit!(CollateralRedeemed {
         reserve: reserve.key(),
@@ -95,10 +85,10 @@ pub fn handler(
 #[derive(Accounts)]
 pub struct RedeemReserveCollateral<'info> {
     #[account(mut)]
-    pub source_collateral: Account<'info, TokenAccount>,
+    pub source_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut)]
-    pub destination_liquidity: Account<'info, TokenAccount>,
+    pub destination_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
@@ -113,23 +103,23 @@ pub struct RedeemReserveCollateral<'info> {
         constraint = reserve_liquidity_supply.key() == reserve.liquidity_supply @ LendingError::InvalidLiquiditySupply,
         constraint = reserve_liquidity_supply.mint == reserve.liquidity_mint @ LendingError::InvalidLiquidityMint,
     )]
-    pub reserve_liquidity_supply: Account<'info, TokenAccount>,
+    pub reserve_liquidity_supply: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
         constraint = reserve_collateral_mint.key() == reserve.collateral_mint @ LendingError::InvalidCollateralMint,
     )]
-    pub reserve_collateral_mint: Account<'info, Mint>,
+    pub reserve_collateral_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         constraint = liquidity_mint.key() == reserve.liquidity_mint @ LendingError::InvalidLiquidityMint,
     )]
-    pub liquidity_mint: Account<'info, Mint>,
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         constraint = collateral_mint.key() == reserve.collateral_mint @ LendingError::InvalidCollateralMint,
     )]
-    pub collateral_mint: Account<'info, Mint>,
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
 
     pub lending_market: Account<'info, LendingMarket>,
 
@@ -142,7 +132,7 @@ pub struct RedeemReserveCollateral<'info> {
 
     pub user_transfer_authority: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[event]
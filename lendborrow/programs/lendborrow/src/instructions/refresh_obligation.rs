@@ -1,5 +1,5 @@
 use crate::errors::LendingError;
-use crate::states::Obligation;
+use crate::states::{LendingMarket, Obligation};
 use crate::utils::{
     deserialize_reserve, refresh_collateral, refresh_liquidity, verify_reserve_freshness,
 };
@@ -24,6 +24,9 @@ pub fn handler(ctx: Context<RefreshObligation>) -> Result<()> {
 
     let mut reserve_index = 0;
 
+    // E-mode applies only if every deposit and borrow is in the obligation's category.
+    let mut in_emode = obligation.emode_category != 0;
+
     for i in 0..obligation.deposits_len as usize {
         let deposit_reserve_info = &reserve_accounts[reserve_index];
         reserve_index += 1;
@@ -36,6 +39,7 @@ pub fn handler(ctx: Context<RefreshObligation>) -> Result<()> {
 
         let deposit_reserve = deserialize_reserve(deposit_reserve_info)?;
         verify_reserve_freshness(&deposit_reserve, clock.slot)?;
+        in_emode &= deposit_reserve.config.emode_category == obligation.emode_category;
 
         let result = refresh_collateral(obligation, i, &deposit_reserve)?;
 
@@ -64,6 +68,7 @@ pub fn handler(ctx: Context<RefreshObligation>) -> Result<()> {
 
         let borrow_reserve = deserialize_reserve(borrow_reserve_info)?;
         verify_reserve_freshness(&borrow_reserve, clock.slot)?;
+        in_emode &= borrow_reserve.config.emode_category == obligation.emode_category;
 
         let market_value = refresh_liquidity(obligation, i, &borrow_reserve)?;
 
@@ -71,6 +76,17 @@ pub fn handler(ctx: Context<RefreshObligation>) -> Result<()> {
             .checked_add(market_value)
             .ok_or(LendingError::MathOverflow)?;
     }
+
+    if in_emode {
+        if let Some(category) = ctx.accounts.lending_market.emode_category(obligation.emode_category) {
+            (total_allowed_borrow_value, total_unhealthy_borrow_value) = category.borrow_values(
+                total_deposited_value,
+                total_allowed_borrow_value,
+                total_unhealthy_borrow_value,
+            )?;
+        }
+    }
+
     obligation.deposited_value = total_deposited_value;
     obligation.borrowed_value = total_borrowed_value;
     obligation.allowed_borrow_value = total_allowed_borrow_value;
@@ -91,8 +107,13 @@ pub fn handler(ctx: Context<RefreshObligation>) -> Result<()> {
 
 #[derive(Accounts)]
 pub struct RefreshObligation<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+    )]
     pub obligation: Box<Account<'info, Obligation>>,
+
+    pub lending_market: Box<Account<'info, LendingMarket>>,
 }
 
 #[event]
@@ -4,7 +4,9 @@ use crate::states::{LendingMarket, Reserve};
 use anchor_lang::prelude::*;
 
 #[cfg(not(feature = "testing"))]
-use crate::utils::oracle::validate_pyth_price;
+use crate::states::OracleType;
+#[cfg(not(feature = "testing"))]
+use crate::utils::oracle::get_oracle_price;
 
 pub fn handler(ctx: Context<RefreshReserve>) -> Result<()> {
     let reserve = &mut ctx.accounts.reserve;
@@ -26,22 +28,85 @@ pub fn handler(ctx: Context<RefreshReserve>) -> Result<()> {
     #[cfg(feature = "testing")]
     {
         reserve.liquidity_market_price = 1_000_000u128;
+        reserve.liquidity_ema_price = reserve.liquidity_market_price;
+        reserve.liquidity_secondary_market_price = if reserve.has_secondary_oracle() {
+            reserve.liquidity_market_price
+        } else {
+            0
+        };
         
         msg!("Testing mode: Mock price = {}", reserve.liquidity_market_price);
     }
 
     #[cfg(not(feature = "testing"))]
     {
-        let current_price = validate_pyth_price(
+        // Switchboard feeds are long-lived accounts, so pin them to the one
+        // the reserve was initialized with.
+        if reserve.config.oracle_type == OracleType::SwitchboardOnDemand {
+            require_keys_eq!(
+                ctx.accounts.pyth_price.key(),
+                reserve.liquidity_oracle,
+                LendingError::InvalidOracleConfig
+            );
+        }
+
+        let current_price = get_oracle_price(
             &ctx.accounts.pyth_price,
             &ctx.accounts.lending_market,
-            reserve.config.pyth_price_feed_id.clone(),
+            reserve.config.oracle_type,
+            reserve.config.pyth_price_feed_id,
+            &reserve.config,
         )?;
         
-        reserve.liquidity_market_price = current_price;
-        msg!("Production: Pyth price = {}", current_price);
+        reserve.liquidity_market_price = current_price.price;
+        reserve.liquidity_ema_price = current_price.ema_price;
+        msg!(
+            "Production: Oracle price = {}, EMA = {}",
+            current_price.price,
+            current_price.ema_price
+        );
+
+        reserve.liquidity_secondary_market_price = if reserve.has_secondary_oracle() {
+            let secondary_price_account = ctx
+                .accounts
+                .secondary_price
+                .as_ref()
+                .ok_or(LendingError::InvalidOracleConfig)?;
+
+            require_keys_eq!(
+                secondary_price_account.key(),
+                reserve.secondary_oracle,
+                LendingError::InvalidOracleConfig
+            );
+
+            get_oracle_price(
+                secondary_price_account,
+                &ctx.accounts.lending_market,
+                reserve.config.secondary_oracle_type,
+                reserve.config.secondary_pyth_price_feed_id,
+                &reserve.config,
+            )?
+            .price
+        } else {
+            0
+        };
+
+        if reserve.oracle_prices_diverged() {
+            require!(
+                reserve.config.oracle_divergence_fallback,
+                LendingError::OracleDivergence
+            );
+
+            msg!(
+                "WARNING: Oracle prices diverge (primary={}, secondary={}), using conservative prices",
+                reserve.liquidity_market_price,
+                reserve.liquidity_secondary_market_price
+            );
+        }
     }
 
+    reserve.apply_peg();
+
     reserve.accrue_interest(clock.slot)?;
 
     reserve.last_update_slot = clock.slot;
@@ -50,10 +115,15 @@ pub fn handler(ctx: Context<RefreshReserve>) -> Result<()> {
         reserve: reserve.key(),
         slot: clock.slot,
         market_price: reserve.liquidity_market_price,
+        ema_price: reserve.liquidity_ema_price,
+        depegged: reserve.depegged,
+        secondary_market_price: reserve.liquidity_secondary_market_price,
         cumulative_borrow_rate: reserve.liquidity_cumulative_borrow_rate_wads,
         utilization_rate: reserve.calculate_utilization_rate()?,
         borrowed_amount: reserve.liquidity_borrowed_amount_wads,
         available_amount: reserve.liquidity_available_amount,
+        max_price_age_secs: reserve.config.max_price_age_secs,
+        max_confidence_bps: reserve.config.max_confidence_bps,
     });
 
     Ok(())
@@ -69,8 +139,11 @@ pub struct RefreshReserve<'info> {
 
     pub lending_market: Account<'info, LendingMarket>,
 
-    /// CHECK: Pyth price account validated in handler for production
+    /// CHECK: Pyth or Switchboard price account validated in handler for production
     pub pyth_price: UncheckedAccount<'info>,
+
+    /// CHECK: Must match `reserve.secondary_oracle`; required only when one is set
+    pub secondary_price: Option<UncheckedAccount<'info>>,
 }
 
 #[event]
@@ -78,8 +151,13 @@ pub struct ReserveRefreshed {
     pub reserve: Pubkey,
     pub slot: u64,
     pub market_price: u128,
+    pub ema_price: u128,
+    pub depegged: bool,
+    pub secondary_market_price: u128,
     pub cumulative_borrow_rate: u128,
     pub utilization_rate: u128,
     pub borrowed_amount: u128,
     pub available_amount: u64,
+    pub max_price_age_secs: u64,
+    pub max_confidence_bps: u16,
 }
\ No newline at end of file
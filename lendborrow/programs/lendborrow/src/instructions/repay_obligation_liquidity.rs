@@ -1,8 +1,9 @@
 use crate::calculate_repay;
 use crate::errors::LendingError;
 use crate::states::{LendingMarket, Obligation, Reserve};
+use crate::utils::token::transfer_checked_received;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked};
 
 pub fn handler(ctx: Context<RepayObligationLiquidity>, liquidity_amount: u64) -> Result<()> {
     require!(liquidity_amount > 0, LendingError::InvalidAmount);
@@ -11,6 +12,10 @@ pub fn handler(ctx: Context<RepayObligationLiquidity>, liquidity_amount: u64) ->
     let repay_reserve = &mut ctx.accounts.repay_reserve;
     let clock = Clock::get()?;
 
+    ctx.accounts
+        .lending_market
+        .require_not_paused(repay_reserve.paused_operations, LendingMarket::PAUSE_REPAY)?;
+
     require!(
         obligation.last_update_slot == clock.slot,
         LendingError::ObligationStale
@@ -21,7 +26,7 @@ pub fn handler(ctx: Context<RepayObligationLiquidity>, liquidity_amount: u64) ->
         LendingError::ReserveStale
     );
 
-    let (mut liquidity, liquidity_index) = obligation
+    let (liquidity, liquidity_index) = obligation
         .find_liquidity(repay_reserve.key())
         .map_err(|_| LendingError::ObligationLiquidityNotFound)?;
 
@@ -38,22 +43,11 @@ pub fn handler(ctx: Context<RepayObligationLiquidity>, liquidity_amount: u64) ->
 
     let actual_repay_amount = if liquidity_amount == u64::MAX {
         let user_balance = ctx.accounts.source_liquidity.amount;
-        let actual_amount = std::cmp::min(repay_result.repay_amount, user_balance);
-        actual_amount
+        std::cmp::min(repay_result.repay_amount, user_balance)
     } else {
         repay_result.repay_amount
     };
 
-    let actual_settle_amount = if actual_repay_amount < repay_result.repay_amount {
-        const WAD: u128 = 1_000_000_000_000_000_000;
-        (actual_repay_amount as u128)
-            .checked_mul(WAD)
-            .ok_or(LendingError::MathOverflow)?
-            .min(repay_result.settle_amount_wads)
-    } else {
-        repay_result.settle_amount_wads
-    };
-
     require!(
         ctx.accounts.source_liquidity.amount >= actual_repay_amount,
         LendingError::InsufficientLiquidity
@@ -61,35 +55,70 @@ pub fn handler(ctx: Context<RepayObligationLiquidity>, liquidity_amount: u64) ->
 
     require!(actual_repay_amount > 0, LendingError::RepayTooSmall);
 
-    repay_reserve.liquidity_borrowed_amount_wads = repay_reserve
-        .liquidity_borrowed_amount_wads
-        .checked_sub(actual_settle_amount)
-        .ok_or(LendingError::MathOverflow)?;
-
-    repay_reserve.liquidity_available_amount = repay_reserve
-        .liquidity_available_amount
-        .checked_add(actual_repay_amount)
-        .ok_or(LendingError::MathOverflow)?;
-
-    obligation.repay(liquidity_index, actual_settle_amount)?;
-    repay_reserve.last_update_slot = clock.slot;
-
-    let cpi_accounts = Transfer {
+    let cpi_accounts = TransferChecked {
         from: ctx.accounts.source_liquidity.to_account_info(),
+        mint: ctx.accounts.liquidity_mint.to_account_info(),
         to: ctx.accounts.destination_liquidity.to_account_info(),
         authority: ctx.accounts.user_transfer_authority.to_account_info(),
     };
 
     let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
 
-    token::transfer(cpi_ctx, actual_repay_amount)?;
+    // Settle only what reached the vault, net of any Token-2022 transfer fee.
+    let received_amount = transfer_checked_received(
+        cpi_ctx,
+        &mut ctx.accounts.destination_liquidity,
+        actual_repay_amount,
+        ctx.accounts.liquidity_mint.decimals,
+    )?;
+
+    require!(received_amount > 0, LendingError::RepayTooSmall);
+
+    let actual_settle_amount = if received_amount < repay_result.repay_amount {
+        const WAD: u128 = 1_000_000_000_000_000_000;
+        (received_amount as u128)
+            .checked_mul(WAD)
+            .ok_or(LendingError::MathOverflow)?
+            .min(repay_result.settle_amount_wads)
+    } else {
+        repay_result.settle_amount_wads
+    };
+
+    let obligation = &mut ctx.accounts.obligation;
+    let repay_reserve = &mut ctx.accounts.repay_reserve;
+
+    repay_reserve.settle_repayment(received_amount, actual_settle_amount)?;
+
+    obligation.repay(liquidity_index, actual_settle_amount)?;
+    repay_reserve.last_update_slot = clock.slot;
+
+    if obligation.is_isolated() {
+        let isolated_reserve = ctx
+            .accounts
+            .isolated_collateral_reserve
+            .as_mut()
+            .ok_or(LendingError::InvalidIsolatedReserve)?;
+
+        require_keys_eq!(
+            isolated_reserve.key(),
+            obligation.isolated_collateral_reserve,
+            LendingError::InvalidIsolatedReserve
+        );
+        require_keys_neq!(
+            isolated_reserve.key(),
+            repay_reserve.key(),
+            LendingError::InvalidIsolatedReserve
+        );
+
+        isolated_reserve.remove_isolated_debt(repay_reserve.debt_value(actual_settle_amount)?);
+    }
 
     emit!(LiquidityRepaid {
         obligation: obligation.key(),
         reserve: repay_reserve.key(),
-        liquidity_amount: liquidity_amount,
+        liquidity_amount,
         settle_amount_wads: actual_settle_amount,
-        repay_amount: actual_repay_amount,
+        repay_amount: received_amount,
         owner: ctx.accounts.obligation_owner.key(),
         slot: clock.slot,
     });
@@ -103,22 +132,25 @@ pub fn handler(ctx: Context<RepayObligationLiquidity>, liquidity_amount: u64) ->
 #[derive(Accounts)]
 pub struct RepayObligationLiquidity<'info> {
     #[account(mut)]
-    pub source_liquidity: Box<Account<'info, TokenAccount>>,
+    pub source_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
         constraint = destination_liquidity.key() == repay_reserve.liquidity_supply 
             @ LendingError::InvalidLiquiditySupply,
     )]
-    pub destination_liquidity: Box<Account<'info, TokenAccount>>,
+    pub destination_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
         constraint = repay_reserve.lending_market == lending_market.key() 
             @ LendingError::InvalidLendingMarket,
+        has_one = liquidity_mint @ LendingError::InvalidLiquidityMint,
     )]
     pub repay_reserve: Box<Account<'info, Reserve>>,
 
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
     #[account(
         mut,
         constraint = obligation.owner == obligation_owner.key() 
@@ -129,6 +161,7 @@ pub struct RepayObligationLiquidity<'info> {
             Obligation::SEED_PREFIX,
             lending_market.key().as_ref(),
             obligation_owner.key().as_ref(),
+            obligation.id.to_le_bytes().as_ref(),
         ],
         bump
     )]
@@ -140,7 +173,16 @@ pub struct RepayObligationLiquidity<'info> {
 
     pub user_transfer_authority: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// Collateral reserve of an isolated obligation; required so repaid debt
+    /// is released from its debt ceiling.
+    #[account(
+        mut,
+        constraint = isolated_collateral_reserve.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+    )]
+    pub isolated_collateral_reserve: Option<Box<Account<'info, Reserve>>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[event]
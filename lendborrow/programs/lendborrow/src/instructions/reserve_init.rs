@@ -1,11 +1,14 @@
 use crate::errors::LendingError;
 use crate::states::{LendingMarket, Reserve, ReserveConfig};
 #[cfg(not(feature = "testing"))]
-use crate::utils::oracle::validate_pyth_price;
+use crate::utils::oracle::get_oracle_price;
+#[cfg(feature = "testing")]
+use crate::utils::oracle::OraclePrice;
 use anchor_lang::prelude::*;
+use crate::utils::token::{transfer_checked_received, validate_mint_extensions};
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{mint_to, transfer, Mint, MintTo, Token, TokenAccount, Transfer},
+    token_interface::{mint_to, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked},
 };
 
 pub fn handler(
@@ -26,19 +29,49 @@ pub fn handler(
     );
     config.validate()?;
 
+    validate_mint_extensions(&ctx.accounts.liquidity_mint)?;
+
+    require!(
+        liquidity_amount <= config.deposit_limit,
+        LendingError::DepositLimitExceeded
+    );
+
     #[cfg(not(feature = "testing"))]
-    let initial_price = validate_pyth_price(
+    let initial_price = get_oracle_price(
         &ctx.accounts.pyth_price,
         &ctx.accounts.lending_market,
-        config.pyth_price_feed_id, // Now passing [u8; 32] directly
+        config.oracle_type,
+        config.pyth_price_feed_id,
+        &config,
     )?;
 
     #[cfg(feature = "testing")]
-    let initial_price: u128 = {
+    let initial_price = {
         msg!("Testing mode: using mock price");
-        1_000_000
+        OraclePrice {
+            price: 1_000_000,
+            ema_price: 1_000_000,
+        }
     };
 
+    // Only what lands in the vault is credited, net of any Token-2022 transfer fee.
+    let received_amount = transfer_checked_received(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.source_liquidity.to_account_info(),
+                mint: ctx.accounts.liquidity_mint.to_account_info(),
+                to: ctx.accounts.liquidity_supply.to_account_info(),
+                authority: ctx.accounts.user_transfer_authority.to_account_info(),
+            },
+        ),
+        &mut ctx.accounts.liquidity_supply,
+        liquidity_amount,
+        ctx.accounts.liquidity_mint.decimals,
+    )?;
+
+    require!(received_amount > 0, LendingError::InvalidLiquidityAmount);
+
     let reserve = &mut ctx.accounts.reserve;
     let clock = Clock::get()?;
 
@@ -51,30 +84,28 @@ pub fn handler(
     reserve.liquidity_supply = ctx.accounts.liquidity_supply.key();
     reserve.liquidity_fee_receiver = ctx.accounts.liquidity_fee_receiver.key();
     reserve.liquidity_oracle = ctx.accounts.pyth_price.key();
-    reserve.liquidity_available_amount = liquidity_amount;
+    reserve.secondary_oracle = Pubkey::default();
+    reserve.liquidity_available_amount = received_amount;
     reserve.liquidity_borrowed_amount_wads = 0;
     reserve.liquidity_cumulative_borrow_rate_wads = Reserve::INITIAL_BORROW_RATE;
-    reserve.liquidity_market_price = initial_price;
+    reserve.liquidity_market_price = initial_price.price;
+    reserve.liquidity_ema_price = initial_price.ema_price;
+    reserve.liquidity_secondary_market_price = 0;
 
     reserve.collateral_mint = ctx.accounts.collateral_mint.key();
     reserve.collateral_supply = ctx.accounts.collateral_supply.key();
-    reserve.collateral_mint_total_supply = liquidity_amount;
+    reserve.collateral_mint_total_supply = received_amount;
+    reserve.accumulated_protocol_fees_wads = 0;
+    reserve.claimable_protocol_fees = 0;
+    reserve.paused_operations = 0;
+    reserve.isolated_debt = 0;
+    reserve.flash_borrowed_amount = 0;
+    reserve.socialized_bad_debt_wads = 0;
 
     // Kloniramo config pre nego što ga dodelimo
     let config_clone = config.clone();
     reserve.config = config;
-
-    transfer(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.source_liquidity.to_account_info(),
-                to: ctx.accounts.liquidity_supply.to_account_info(),
-                authority: ctx.accounts.user_transfer_authority.to_account_info(),
-            },
-        ),
-        liquidity_amount,
-    )?;
+    reserve.apply_peg();
 
     let lending_market_key = ctx.accounts.lending_market.key();
     let authority_bump = ctx.bumps.lending_market_authority;
@@ -91,7 +122,7 @@ pub fn handler(
             },
             signer_seeds,
         ),
-        liquidity_amount,
+        received_amount,
     )?;
 
     emit!(ReserveInitialized {
@@ -99,8 +130,8 @@ pub fn handler(
         lending_market: ctx.accounts.lending_market.key(),
         liquidity_mint: ctx.accounts.liquidity_mint.key(),
         collateral_mint: ctx.accounts.collateral_mint.key(),
-        liquidity_amount,
-        initial_price,
+        liquidity_amount: received_amount,
+        initial_price: initial_price.price,
         config: config_clone, 
     });
 
@@ -127,9 +158,12 @@ pub struct InitReserve<'info> {
         constraint = source_liquidity.mint == liquidity_mint.key() 
             @ LendingError::InvalidMint,
     )]
-    pub source_liquidity: Box<Account<'info, TokenAccount>>,
+    pub source_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    pub liquidity_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         has_one = owner @ LendingError::InvalidOwner
@@ -161,6 +195,7 @@ pub struct InitReserve<'info> {
         payer = owner,
         token::mint = liquidity_mint,
         token::authority = lending_market_authority,
+        token::token_program = token_program,
         seeds = [
             b"liquidity-supply",
             lending_market.key().as_ref(),
@@ -168,13 +203,14 @@ pub struct InitReserve<'info> {
         ],
         bump
     )]
-    pub liquidity_supply: Box<Account<'info, TokenAccount>>,
+    pub liquidity_supply: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         init,
         payer = owner,
         token::mint = liquidity_mint,
         token::authority = lending_market_authority,
+        token::token_program = token_program,
         seeds = [
             b"fee-receiver",
             lending_market.key().as_ref(),
@@ -182,9 +218,9 @@ pub struct InitReserve<'info> {
         ],
         bump
     )]
-    pub liquidity_fee_receiver: Box<Account<'info, TokenAccount>>,
+    pub liquidity_fee_receiver: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    /// CHECK: Pyth price account - validated in handler
+    /// CHECK: Pyth or Switchboard price account - validated in handler
     pub pyth_price: UncheckedAccount<'info>,
 
     #[account(
@@ -192,6 +228,7 @@ pub struct InitReserve<'info> {
         payer = owner,
         mint::decimals = liquidity_mint.decimals,
         mint::authority = lending_market_authority,
+        mint::token_program = token_program,
         seeds = [
             b"collateral-mint",
             lending_market.key().as_ref(),
@@ -199,21 +236,23 @@ pub struct InitReserve<'info> {
         ],
         bump
     )]
-    pub collateral_mint: Box<Account<'info, Mint>>,
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         init_if_needed,
         payer = owner,
         associated_token::mint = collateral_mint,
         associated_token::authority = owner,
+        associated_token::token_program = token_program,
     )]
-    pub destination_collateral: Box<Account<'info, TokenAccount>>,
+    pub destination_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         init,
         payer = owner,
         token::mint = collateral_mint,
         token::authority = lending_market_authority,
+        token::token_program = token_program,
         seeds = [
             b"collateral-supply",
             lending_market.key().as_ref(),
@@ -221,14 +260,14 @@ pub struct InitReserve<'info> {
         ],
         bump
     )]
-    pub collateral_supply: Box<Account<'info, TokenAccount>>,
+    pub collateral_supply: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut)]
     pub owner: Signer<'info>,
 
     pub user_transfer_authority: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 
     pub associated_token_program: Program<'info, AssociatedToken>,
 
@@ -0,0 +1,51 @@
+use crate::errors::LendingError;
+use crate::states::LendingMarket;
+use anchor_lang::prelude::*;
+
+pub fn handler(
+    ctx: Context<SetEModeCategory>,
+    category_id: u8,
+    loan_to_value_ratio: u8,
+    liquidation_threshold: u8,
+) -> Result<()> {
+    let lending_market = &mut ctx.accounts.lending_market;
+
+    lending_market.require_no_timelock()?;
+    lending_market.set_emode_category(category_id, loan_to_value_ratio, liquidation_threshold)?;
+
+    emit!(EModeCategoryChanged {
+        lending_market: lending_market.key(),
+        category_id,
+        loan_to_value_ratio,
+        liquidation_threshold,
+    });
+
+    Ok(())
+}
+
+/// Accounts context for configuring an e-mode category.
+///
+/// - `authority` must be the market owner or its risk manager, and sign.
+/// - Setting `loan_to_value_ratio` to 0 disables the category.
+/// - The liquidation threshold may drop by at most the market's
+///   `max_liquidation_threshold_step`; disabling counts as dropping it to 0.
+/// - Markets with a timelock must use `queue_admin_change` instead.
+#[derive(Accounts)]
+pub struct SetEModeCategory<'info> {
+    #[account(
+        mut,
+        constraint = lending_market.is_risk_authority(&authority.key())
+            @ LendingError::InvalidRiskManager
+    )]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub authority: Signer<'info>,
+}
+
+#[event]
+pub struct EModeCategoryChanged {
+    pub lending_market: Pubkey,
+    pub category_id: u8,
+    pub loan_to_value_ratio: u8,
+    pub liquidation_threshold: u8,
+}
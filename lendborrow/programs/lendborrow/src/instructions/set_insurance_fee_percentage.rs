@@ -0,0 +1,44 @@
+use crate::errors::LendingError;
+use crate::states::LendingMarket;
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<SetInsuranceFeePercentage>, insurance_fee_percentage: u8) -> Result<()> {
+    let lending_market = &mut ctx.accounts.lending_market;
+    let old_insurance_fee_percentage = lending_market.insurance_fee_percentage;
+
+    lending_market.require_no_timelock()?;
+    lending_market.set_insurance_fee_percentage(insurance_fee_percentage)?;
+
+    emit!(InsuranceFeePercentageChanged {
+        lending_market: lending_market.key(),
+        old_insurance_fee_percentage,
+        new_insurance_fee_percentage: insurance_fee_percentage,
+        authority: ctx.accounts.authority.key(),
+    });
+
+    Ok(())
+}
+
+/// Accounts context for setting the share of fees paid into insurance vaults.
+///
+/// - `authority` must be the market owner or its fee manager, and sign.
+/// - Markets with a timelock must use `queue_admin_change` instead.
+#[derive(Accounts)]
+pub struct SetInsuranceFeePercentage<'info> {
+    #[account(
+        mut,
+        constraint = lending_market.is_fee_authority(&authority.key())
+            @ LendingError::InvalidFeeManager
+    )]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub authority: Signer<'info>,
+}
+
+#[event]
+pub struct InsuranceFeePercentageChanged {
+    pub lending_market: Pubkey,
+    pub old_insurance_fee_percentage: u8,
+    pub new_insurance_fee_percentage: u8,
+    pub authority: Pubkey,
+}
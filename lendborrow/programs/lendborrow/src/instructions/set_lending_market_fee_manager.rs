@@ -0,0 +1,50 @@
+use crate::errors::LendingError;
+use crate::states::LendingMarket;
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<SetLendingMarketFeeManager>, new_fee_manager: Pubkey) -> Result<()> {
+    let lending_market = &mut ctx.accounts.lending_market;
+
+    lending_market.require_no_timelock()?;
+
+    // The fee manager is a separate key from the owner; `Pubkey::default()` removes it.
+    require!(
+        new_fee_manager != lending_market.owner,
+        LendingError::InvalidFeeManager
+    );
+
+    let old_fee_manager = lending_market.fee_manager;
+
+    lending_market.fee_manager = new_fee_manager;
+
+    emit!(LendingMarketFeeManagerChanged {
+        lending_market: lending_market.key(),
+        old_fee_manager,
+        new_fee_manager,
+    });
+
+    Ok(())
+}
+
+/// Accounts context for rotating the fee manager of a `LendingMarket`.
+///
+/// - `lending_market` must currently be owned by `owner`.
+/// - `owner` must sign the transaction.
+/// - Markets with a timelock must queue `AdminChange::FeeManager` instead.
+#[derive(Accounts)]
+pub struct SetLendingMarketFeeManager<'info> {
+    #[account(
+        mut,
+        has_one = owner @ LendingError::InvalidOwner
+    )]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub owner: Signer<'info>,
+}
+
+#[event]
+pub struct LendingMarketFeeManagerChanged {
+    pub lending_market: Pubkey,
+    pub old_fee_manager: Pubkey,
+    pub new_fee_manager: Pubkey,
+}
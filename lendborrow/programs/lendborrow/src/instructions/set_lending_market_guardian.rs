@@ -0,0 +1,50 @@
+use crate::errors::LendingError;
+use crate::states::LendingMarket;
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<SetLendingMarketGuardian>, new_guardian: Pubkey) -> Result<()> {
+    let lending_market = &mut ctx.accounts.lending_market;
+
+    lending_market.require_no_timelock()?;
+
+    // The guardian is a separate hot key; `Pubkey::default()` disables it.
+    require!(
+        new_guardian != lending_market.owner,
+        LendingError::InvalidGuardian
+    );
+
+    let old_guardian = lending_market.guardian;
+
+    lending_market.guardian = new_guardian;
+
+    emit!(LendingMarketGuardianChanged {
+        lending_market: lending_market.key(),
+        old_guardian,
+        new_guardian,
+    });
+
+    Ok(())
+}
+
+/// Accounts context for rotating the emergency guardian of a `LendingMarket`.
+///
+/// - `lending_market` must currently be owned by `owner`.
+/// - `owner` must sign the transaction.
+/// - Markets with a timelock must queue `AdminChange::Guardian` instead.
+#[derive(Accounts)]
+pub struct SetLendingMarketGuardian<'info> {
+    #[account(
+        mut,
+        has_one = owner @ LendingError::InvalidOwner
+    )]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub owner: Signer<'info>,
+}
+
+#[event]
+pub struct LendingMarketGuardianChanged {
+    pub lending_market: Pubkey,
+    pub old_guardian: Pubkey,
+    pub new_guardian: Pubkey,
+}
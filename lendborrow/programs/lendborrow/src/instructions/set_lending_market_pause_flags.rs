@@ -0,0 +1,57 @@
+use crate::errors::LendingError;
+use crate::states::LendingMarket;
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<SetLendingMarketPauseFlags>, paused_operations: u8) -> Result<()> {
+    require!(
+        paused_operations & !LendingMarket::PAUSE_ALL == 0,
+        LendingError::InvalidPauseFlags
+    );
+
+    let lending_market = &mut ctx.accounts.lending_market;
+    let old_paused_operations = lending_market.paused_operations;
+
+    // The guardian may only add flags; clearing any of them needs the owner.
+    if ctx.accounts.authority.key() != lending_market.owner {
+        require!(
+            paused_operations & old_paused_operations == old_paused_operations,
+            LendingError::GuardianCannotUnpause
+        );
+    }
+
+    lending_market.paused_operations = paused_operations;
+
+    emit!(LendingMarketPauseFlagsChanged {
+        lending_market: lending_market.key(),
+        old_paused_operations,
+        new_paused_operations: paused_operations,
+        authority: ctx.accounts.authority.key(),
+    });
+
+    Ok(())
+}
+
+/// Accounts context for pausing or unpausing operations market-wide.
+///
+/// - `authority` must be either the market `owner` or its `guardian`.
+/// - Only the owner may clear flags.
+#[derive(Accounts)]
+pub struct SetLendingMarketPauseFlags<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == lending_market.owner
+            || authority.key() == lending_market.guardian
+            @ LendingError::InvalidGuardian
+    )]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub authority: Signer<'info>,
+}
+
+#[event]
+pub struct LendingMarketPauseFlagsChanged {
+    pub lending_market: Pubkey,
+    pub old_paused_operations: u8,
+    pub new_paused_operations: u8,
+    pub authority: Pubkey,
+}
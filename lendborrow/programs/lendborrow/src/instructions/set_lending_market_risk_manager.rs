@@ -0,0 +1,50 @@
+use crate::errors::LendingError;
+use crate::states::LendingMarket;
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<SetLendingMarketRiskManager>, new_risk_manager: Pubkey) -> Result<()> {
+    let lending_market = &mut ctx.accounts.lending_market;
+
+    lending_market.require_no_timelock()?;
+
+    // The risk manager is a separate key from the owner; `Pubkey::default()` removes it.
+    require!(
+        new_risk_manager != lending_market.owner,
+        LendingError::InvalidRiskManager
+    );
+
+    let old_risk_manager = lending_market.risk_manager;
+
+    lending_market.risk_manager = new_risk_manager;
+
+    emit!(LendingMarketRiskManagerChanged {
+        lending_market: lending_market.key(),
+        old_risk_manager,
+        new_risk_manager,
+    });
+
+    Ok(())
+}
+
+/// Accounts context for rotating the risk manager of a `LendingMarket`.
+///
+/// - `lending_market` must currently be owned by `owner`.
+/// - `owner` must sign the transaction.
+/// - Markets with a timelock must queue `AdminChange::RiskManager` instead.
+#[derive(Accounts)]
+pub struct SetLendingMarketRiskManager<'info> {
+    #[account(
+        mut,
+        has_one = owner @ LendingError::InvalidOwner
+    )]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub owner: Signer<'info>,
+}
+
+#[event]
+pub struct LendingMarketRiskManagerChanged {
+    pub lending_market: Pubkey,
+    pub old_risk_manager: Pubkey,
+    pub new_risk_manager: Pubkey,
+}
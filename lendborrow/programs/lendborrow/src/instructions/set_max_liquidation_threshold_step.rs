@@ -0,0 +1,43 @@
+use crate::errors::LendingError;
+use crate::states::LendingMarket;
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<SetMaxLiquidationThresholdStep>, max_step: u8) -> Result<()> {
+    let lending_market = &mut ctx.accounts.lending_market;
+    let old_max_step = lending_market.max_liquidation_threshold_step;
+
+    lending_market.require_no_timelock()?;
+    lending_market.set_max_liquidation_threshold_step(max_step)?;
+
+    emit!(MaxLiquidationThresholdStepChanged {
+        lending_market: lending_market.key(),
+        old_max_step,
+        new_max_step: max_step,
+    });
+
+    Ok(())
+}
+
+/// Accounts context for changing how far `update_reserve_config` may lower a
+/// reserve's liquidation threshold in a single update.
+///
+/// - `lending_market` must currently be owned by `owner`.
+/// - `owner` must sign the transaction.
+/// - Markets with a timelock must use `queue_admin_change` instead.
+#[derive(Accounts)]
+pub struct SetMaxLiquidationThresholdStep<'info> {
+    #[account(
+        mut,
+        has_one = owner @ LendingError::InvalidOwner
+    )]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub owner: Signer<'info>,
+}
+
+#[event]
+pub struct MaxLiquidationThresholdStepChanged {
+    pub lending_market: Pubkey,
+    pub old_max_step: u8,
+    pub new_max_step: u8,
+}
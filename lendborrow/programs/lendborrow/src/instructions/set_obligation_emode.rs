@@ -0,0 +1,56 @@
+use crate::errors::LendingError;
+use crate::states::{LendingMarket, Obligation};
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<SetObligationEMode>, category_id: u8) -> Result<()> {
+    let obligation = &mut ctx.accounts.obligation;
+
+    require!(
+        obligation.borrows_len == 0,
+        LendingError::ObligationHasBorrows
+    );
+
+    require!(
+        category_id == 0
+            || ctx.accounts.lending_market.emode_category(category_id).is_some(),
+        LendingError::InvalidEModeCategory
+    );
+
+    let old_category_id = obligation.emode_category;
+    obligation.emode_category = category_id;
+
+    emit!(ObligationEModeChanged {
+        obligation: obligation.key(),
+        old_category_id,
+        new_category_id: category_id,
+    });
+
+    Ok(())
+}
+
+/// Accounts context for opting an obligation into an e-mode category.
+///
+/// - `obligation` must belong to `obligation_owner`, who must sign.
+/// - The category can only change while the obligation has no borrows.
+/// - Passing category 0 leaves e-mode.
+#[derive(Accounts)]
+pub struct SetObligationEMode<'info> {
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+        constraint = obligation.owner == obligation_owner.key()
+            @ LendingError::InvalidObligationOwner,
+    )]
+    pub obligation: Box<Account<'info, Obligation>>,
+
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    pub obligation_owner: Signer<'info>,
+}
+
+#[event]
+pub struct ObligationEModeChanged {
+    pub obligation: Pubkey,
+    pub old_category_id: u8,
+    pub new_category_id: u8,
+}
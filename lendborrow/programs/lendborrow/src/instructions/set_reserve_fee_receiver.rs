@@ -0,0 +1,67 @@
+use crate::errors::LendingError;
+use crate::states::{LendingMarket, Reserve};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+pub fn handler(ctx: Context<SetReserveFeeReceiver>) -> Result<()> {
+    ctx.accounts.lending_market.require_no_timelock()?;
+
+    let reserve = &mut ctx.accounts.reserve;
+    let old_fee_receiver = reserve.liquidity_fee_receiver;
+    let new_fee_receiver = ctx.accounts.new_fee_receiver.key();
+
+    require_keys_neq!(
+        new_fee_receiver,
+        reserve.liquidity_supply,
+        LendingError::InvalidFeeReceiver
+    );
+
+    reserve.liquidity_fee_receiver = new_fee_receiver;
+
+    emit!(ReserveFeeReceiverChanged {
+        reserve: reserve.key(),
+        lending_market: ctx.accounts.lending_market.key(),
+        old_fee_receiver,
+        new_fee_receiver,
+        authority: ctx.accounts.authority.key(),
+    });
+
+    Ok(())
+}
+
+/// Accounts context for redirecting where a reserve's fees are paid.
+///
+/// - `authority` must be the market owner or its fee manager, and sign.
+/// - `new_fee_receiver` must hold the reserve's liquidity mint.
+/// - Markets with a timelock must queue `AdminChange::FeeReceiver` instead.
+#[derive(Accounts)]
+pub struct SetReserveFeeReceiver<'info> {
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    #[account(
+        constraint = lending_market.is_fee_authority(&authority.key())
+            @ LendingError::InvalidFeeManager
+    )]
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = new_fee_receiver.mint == reserve.liquidity_mint
+            @ LendingError::InvalidFeeReceiver,
+    )]
+    pub new_fee_receiver: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[event]
+pub struct ReserveFeeReceiverChanged {
+    pub reserve: Pubkey,
+    pub lending_market: Pubkey,
+    pub old_fee_receiver: Pubkey,
+    pub new_fee_receiver: Pubkey,
+    pub authority: Pubkey,
+}
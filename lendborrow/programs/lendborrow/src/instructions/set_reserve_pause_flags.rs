@@ -0,0 +1,64 @@
+use crate::errors::LendingError;
+use crate::states::{LendingMarket, Reserve};
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<SetReservePauseFlags>, paused_operations: u8) -> Result<()> {
+    require!(
+        paused_operations & !LendingMarket::PAUSE_ALL == 0,
+        LendingError::InvalidPauseFlags
+    );
+
+    let reserve = &mut ctx.accounts.reserve;
+    let old_paused_operations = reserve.paused_operations;
+
+    // The guardian may only add flags; clearing any of them needs the owner.
+    if ctx.accounts.authority.key() != ctx.accounts.lending_market.owner {
+        require!(
+            paused_operations & old_paused_operations == old_paused_operations,
+            LendingError::GuardianCannotUnpause
+        );
+    }
+
+    reserve.paused_operations = paused_operations;
+
+    emit!(ReservePauseFlagsChanged {
+        reserve: reserve.key(),
+        lending_market: ctx.accounts.lending_market.key(),
+        old_paused_operations,
+        new_paused_operations: paused_operations,
+        authority: ctx.accounts.authority.key(),
+    });
+
+    Ok(())
+}
+
+/// Accounts context for pausing or unpausing operations on a single reserve.
+///
+/// - `authority` must be either the market `owner` or its `guardian`.
+/// - Only the owner may clear flags.
+#[derive(Accounts)]
+pub struct SetReservePauseFlags<'info> {
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    #[account(
+        constraint = authority.key() == lending_market.owner
+            || authority.key() == lending_market.guardian
+            @ LendingError::InvalidGuardian
+    )]
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[event]
+pub struct ReservePauseFlagsChanged {
+    pub reserve: Pubkey,
+    pub lending_market: Pubkey,
+    pub old_paused_operations: u8,
+    pub new_paused_operations: u8,
+    pub authority: Pubkey,
+}
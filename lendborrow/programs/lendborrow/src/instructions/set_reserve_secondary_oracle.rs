@@ -0,0 +1,87 @@
+use crate::errors::LendingError;
+use crate::states::{LendingMarket, Reserve};
+use anchor_lang::prelude::*;
+
+#[cfg(not(feature = "testing"))]
+use crate::utils::oracle::get_oracle_price;
+
+pub fn handler(ctx: Context<SetReserveSecondaryOracle>) -> Result<()> {
+    ctx.accounts.lending_market.require_no_timelock()?;
+
+    let reserve = &mut ctx.accounts.reserve;
+    let old_secondary_oracle = reserve.secondary_oracle;
+
+    let (new_secondary_oracle, secondary_price) = match &ctx.accounts.secondary_oracle {
+        Some(secondary_oracle) => {
+            require_keys_neq!(
+                secondary_oracle.key(),
+                reserve.liquidity_oracle,
+                LendingError::InvalidOracleConfig
+            );
+
+            #[cfg(not(feature = "testing"))]
+            let secondary_price = get_oracle_price(
+                secondary_oracle,
+                &ctx.accounts.lending_market,
+                reserve.config.secondary_oracle_type,
+                reserve.config.secondary_pyth_price_feed_id,
+                &reserve.config,
+            )?
+            .price;
+
+            #[cfg(feature = "testing")]
+            let secondary_price = reserve.liquidity_market_price;
+
+            (secondary_oracle.key(), secondary_price)
+        }
+        None => (Pubkey::default(), 0),
+    };
+
+    reserve.secondary_oracle = new_secondary_oracle;
+    reserve.liquidity_secondary_market_price = secondary_price;
+
+    emit!(ReserveSecondaryOracleChanged {
+        reserve: reserve.key(),
+        lending_market: ctx.accounts.lending_market.key(),
+        old_secondary_oracle,
+        new_secondary_oracle,
+        secondary_price,
+    });
+
+    Ok(())
+}
+
+/// Accounts context for setting or clearing a reserve's secondary oracle.
+///
+/// - `authority` must be the market owner or its risk manager, and sign.
+/// - Omitting `secondary_oracle` removes the secondary oracle.
+/// - The oracle is read once using `secondary_oracle_type` from the reserve config.
+/// - Markets with a timelock must queue `AdminChange::SecondaryOracle` instead.
+#[derive(Accounts)]
+pub struct SetReserveSecondaryOracle<'info> {
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    #[account(
+        constraint = lending_market.is_risk_authority(&authority.key())
+            @ LendingError::InvalidRiskManager
+    )]
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: Pyth or Switchboard price account validated in handler for production
+    pub secondary_oracle: Option<UncheckedAccount<'info>>,
+}
+
+#[event]
+pub struct ReserveSecondaryOracleChanged {
+    pub reserve: Pubkey,
+    pub lending_market: Pubkey,
+    pub old_secondary_oracle: Pubkey,
+    pub new_secondary_oracle: Pubkey,
+    pub secondary_price: u128,
+}
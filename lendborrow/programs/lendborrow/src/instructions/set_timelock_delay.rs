@@ -0,0 +1,50 @@
+use crate::errors::LendingError;
+use crate::states::LendingMarket;
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<SetTimelockDelay>, delay_slots: u64) -> Result<()> {
+    let lending_market = &mut ctx.accounts.lending_market;
+    let old_delay_slots = lending_market.timelock_delay_slots;
+
+    // Shortening the delay would let the owner skip the notice depositors rely
+    // on, so only increases take effect immediately.
+    require!(
+        delay_slots >= old_delay_slots,
+        LendingError::TimelockRequired
+    );
+
+    lending_market.set_timelock_delay_slots(delay_slots)?;
+
+    emit!(TimelockDelayChanged {
+        lending_market: lending_market.key(),
+        old_delay_slots,
+        new_delay_slots: delay_slots,
+    });
+
+    Ok(())
+}
+
+/// Accounts context for lengthening the timelock on risk parameter changes.
+///
+/// - `lending_market` must currently be owned by `owner`.
+/// - `owner` must sign the transaction.
+/// - Shorter delays must be queued as `AdminChange::TimelockDelay`.
+/// - The delay is capped at `LendingMarket::MAX_TIMELOCK_DELAY_SLOTS`, and a
+///   longer one also holds back changes that are already queued.
+#[derive(Accounts)]
+pub struct SetTimelockDelay<'info> {
+    #[account(
+        mut,
+        has_one = owner @ LendingError::InvalidOwner
+    )]
+    pub lending_market: Account<'info, LendingMarket>,
+
+    pub owner: Signer<'info>,
+}
+
+#[event]
+pub struct TimelockDelayChanged {
+    pub lending_market: Pubkey,
+    pub old_delay_slots: u64,
+    pub new_delay_slots: u64,
+}
@@ -0,0 +1,137 @@
+use crate::errors::LendingError;
+use crate::states::{LendingMarket, Obligation, Reserve};
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<SocializeBadDebt>) -> Result<()> {
+    let obligation = &mut ctx.accounts.obligation;
+    let reserve = &mut ctx.accounts.reserve;
+    let clock = Clock::get()?;
+
+    require!(
+        obligation.last_update_slot == clock.slot,
+        LendingError::ObligationStale
+    );
+
+    require!(
+        reserve.last_update_slot == clock.slot,
+        LendingError::ReserveStale
+    );
+
+    // Debt is only bad once liquidators have nothing left to seize.
+    require!(
+        obligation.deposits_len == 0,
+        LendingError::ObligationHasCollateral
+    );
+
+    let (liquidity, liquidity_index) = obligation
+        .find_liquidity(reserve.key())
+        .map_err(|_| LendingError::ObligationLiquidityNotFound)?;
+
+    require!(
+        liquidity.borrowed_amount_wads > 0,
+        LendingError::ObligationLiquidityEmpty
+    );
+
+    // Written-off debt no longer counts against the isolated reserve's ceiling.
+    if obligation.is_isolated() {
+        let isolated_reserve = ctx
+            .accounts
+            .isolated_collateral_reserve
+            .as_mut()
+            .ok_or(LendingError::InvalidIsolatedReserve)?;
+
+        require_keys_eq!(
+            isolated_reserve.key(),
+            obligation.isolated_collateral_reserve,
+            LendingError::InvalidIsolatedReserve
+        );
+        require_keys_neq!(
+            isolated_reserve.key(),
+            reserve.key(),
+            LendingError::InvalidIsolatedReserve
+        );
+
+        isolated_reserve.remove_isolated_debt(reserve.debt_value(liquidity.borrowed_amount_wads)?);
+    }
+
+    let exchange_rate_before = reserve.collateral_exchange_rate()?;
+
+    let (covered_wads, socialized_wads) =
+        reserve.write_off_bad_debt(liquidity.borrowed_amount_wads)?;
+
+    let exchange_rate_after = reserve.collateral_exchange_rate()?;
+
+    obligation.repay(liquidity_index, liquidity.borrowed_amount_wads)?;
+
+    emit!(BadDebtSocialized {
+        obligation: obligation.key(),
+        reserve: reserve.key(),
+        lending_market: ctx.accounts.lending_market.key(),
+        bad_debt_wads: liquidity.borrowed_amount_wads,
+        covered_wads,
+        socialized_wads,
+        exchange_rate_before,
+        exchange_rate_after,
+        slot: clock.slot,
+    });
+
+    msg!(
+        "Wrote off {} bad debt wads: {} covered by protocol fees, {} socialized",
+        liquidity.borrowed_amount_wads,
+        covered_wads,
+        socialized_wads
+    );
+
+    Ok(())
+}
+
+/// Accounts context for writing off an obligation's residual debt.
+///
+/// - Anyone may call this; there is nothing to gain from it beyond correct accounting.
+/// - `obligation` must have no collateral left and be refreshed in this slot.
+/// - `reserve` is the borrow reserve whose debt is written off, also refreshed in this slot.
+/// - `isolated_collateral_reserve` is required for isolated obligations, so the
+///   debt is released from its ceiling.
+#[derive(Accounts)]
+pub struct SocializeBadDebt<'info> {
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+        seeds = [
+            Obligation::SEED_PREFIX,
+            lending_market.key().as_ref(),
+            obligation.owner.as_ref(),
+            obligation.id.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub obligation: Box<Account<'info, Obligation>>,
+
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    #[account(
+        mut,
+        constraint = isolated_collateral_reserve.lending_market == lending_market.key()
+            @ LendingError::InvalidLendingMarket,
+    )]
+    pub isolated_collateral_reserve: Option<Box<Account<'info, Reserve>>>,
+}
+
+#[event]
+pub struct BadDebtSocialized {
+    pub obligation: Pubkey,
+    pub reserve: Pubkey,
+    pub lending_market: Pubkey,
+    pub bad_debt_wads: u128,
+    pub covered_wads: u128,
+    pub socialized_wads: u128,
+    pub exchange_rate_before: u128,
+    pub exchange_rate_after: u128,
+    pub slot: u64,
+}
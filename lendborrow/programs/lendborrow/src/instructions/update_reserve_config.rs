@@ -0,0 +1,73 @@
+use crate::errors::LendingError;
+use crate::states::{LendingMarket, Reserve, ReserveConfig};
+use anchor_lang::prelude::*;
+
+pub fn handler(ctx: Context<UpdateReserveConfig>, config: ReserveConfig) -> Result<()> {
+    let lending_market = &ctx.accounts.lending_market;
+    let reserve = &mut ctx.accounts.reserve;
+    let clock = Clock::get()?;
+
+    // Interest must be accrued under the old rate curve before it is replaced.
+    require!(
+        reserve.last_update_slot == clock.slot,
+        LendingError::ReserveStale
+    );
+
+    lending_market.require_no_timelock()?;
+
+    let fees_changed = config.fees != reserve.config.fees
+        || config.protocol_take_rate != reserve.config.protocol_take_rate;
+
+    require!(
+        !fees_changed || lending_market.is_fee_authority(&ctx.accounts.authority.key()),
+        LendingError::InvalidFeeManager
+    );
+
+    let old_config = reserve.update_config(config.clone(), lending_market)?;
+
+    emit!(ReserveConfigUpdated {
+        reserve: reserve.key(),
+        lending_market: lending_market.key(),
+        old_config,
+        new_config: config,
+        authority: ctx.accounts.authority.key(),
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+/// Accounts context for updating the configuration of a `Reserve`.
+///
+/// - `reserve` must belong to `lending_market` and be refreshed in this slot.
+/// - Markets with a timelock must use `queue_admin_change` instead.
+/// - `authority` must be the market owner or its risk manager, and sign.
+/// - Changing `fees` or `protocol_take_rate` also requires fee authority.
+/// - Oracle and isolation settings cannot change, an EMA price mode cannot be
+///   switched on, and `emode_category` must be 0 or enabled in `lending_market`.
+#[derive(Accounts)]
+pub struct UpdateReserveConfig<'info> {
+    #[account(
+        mut,
+        has_one = lending_market @ LendingError::InvalidLendingMarket,
+    )]
+    pub reserve: Box<Account<'info, Reserve>>,
+
+    #[account(
+        constraint = lending_market.is_risk_authority(&authority.key())
+            @ LendingError::InvalidRiskManager
+    )]
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[event]
+pub struct ReserveConfigUpdated {
+    pub reserve: Pubkey,
+    pub lending_market: Pubkey,
+    pub old_config: ReserveConfig,
+    pub new_config: ReserveConfig,
+    pub authority: Pubkey,
+    pub slot: u64,
+}
@@ -1,15 +1,74 @@
 use crate::errors::LendingError;
 use crate::states::{LendingMarket, Obligation, Reserve};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 
 pub fn handler(ctx: Context<WithdrawObligationCollateral>, collateral_amount: u64) -> Result<()> {
+    let withdraw_amount = withdraw(
+        &mut ctx.accounts.obligation,
+        &ctx.accounts.withdraw_reserve,
+        &ctx.accounts.lending_market,
+        collateral_amount,
+    )?;
+    let obligation = &ctx.accounts.obligation;
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let (expected_authority, _) =
+        Pubkey::find_program_address(&[b"authority", lending_market_key.as_ref()], ctx.program_id);
+
+    require!(
+        ctx.accounts.lending_market_authority.key() == expected_authority,
+        LendingError::InvalidMarketAuthority
+    );
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let authority_bump = ctx.bumps.lending_market_authority;
+    let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
+    let signer_seeds = &[&authority_seeds[..]];
+
+    let cpi_accounts = TransferChecked {
+        from: ctx.accounts.source_collateral.to_account_info(),
+        mint: ctx.accounts.collateral_mint.to_account_info(),
+        to: ctx.accounts.destination_collateral.to_account_info(),
+        authority: ctx.accounts.lending_market_authority.to_account_info(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+        signer_seeds,
+    );
+
+    token_interface::transfer_checked(cpi_ctx, withdraw_amount, ctx.accounts.collateral_mint.decimals)?;
+
+    emit!(CollateralWithdrawn {
+        obligation: obligation.key(),
+        reserve: ctx.accounts.withdraw_reserve.key(),
+        collateral_amount: withdraw_amount,
+        owner: ctx.accounts.obligation_owner.key(),
+    });
+
+    Ok(())
+}
+
+/// Withdraws up to `collateral_amount` (or the most that keeps the obligation
+/// healthy, for `u64::MAX`) from the obligation's deposit in `reserve`.
+///
+/// Shared by both withdraw instructions; returns the amount removed from the
+/// obligation, which is still held in the reserve's collateral supply.
+pub(crate) fn withdraw(
+    obligation: &mut Obligation,
+    reserve: &Account<Reserve>,
+    lending_market: &LendingMarket,
+    collateral_amount: u64,
+) -> Result<u64> {
     require!(collateral_amount > 0, LendingError::InvalidAmount);
 
-    let obligation = &mut ctx.accounts.obligation;
-    let reserve = &ctx.accounts.withdraw_reserve;
     let clock = Clock::get()?;
 
+    lending_market
+        .require_not_paused(reserve.paused_operations, LendingMarket::PAUSE_WITHDRAW)?;
+
     #[cfg(not(feature = "testing"))]
     {
         const MAX_SLOT_AGE: u64 = 3600;
@@ -25,8 +84,8 @@ pub fn handler(ctx: Context<WithdrawObligationCollateral>, collateral_amount: u6
         );
     }
 
-    let (mut collateral, collateral_index) = obligation
-        .find_collateral(ctx.accounts.withdraw_reserve.key())
+    let (collateral, collateral_index) = obligation
+        .find_collateral(reserve.key())
         .map_err(|_| LendingError::InvalidObligationCollateral)?;
 
     require!(
@@ -99,42 +158,7 @@ pub fn handler(ctx: Context<WithdrawObligationCollateral>, collateral_amount: u6
     obligation.verify_healthy()?;
     obligation.last_update_slot = clock.slot;
 
-    let lending_market_key = ctx.accounts.lending_market.key();
-    let (expected_authority, expected_bump) =
-        Pubkey::find_program_address(&[b"authority", lending_market_key.as_ref()], ctx.program_id);
-
-    require!(
-        ctx.accounts.lending_market_authority.key() == expected_authority,
-        LendingError::InvalidMarketAuthority
-    );
-
-    let lending_market_key = ctx.accounts.lending_market.key();
-    let authority_bump = ctx.bumps.lending_market_authority;
-    let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
-    let signer_seeds = &[&authority_seeds[..]];
-
-    let cpi_accounts = Transfer {
-        from: ctx.accounts.source_collateral.to_account_info(),
-        to: ctx.accounts.destination_collateral.to_account_info(),
-        authority: ctx.accounts.lending_market_authority.to_account_info(),
-    };
-
-    let cpi_ctx = CpiContext::new_with_signer(
-        ctx.accounts.token_program.to_account_info(),
-        cpi_accounts,
-        signer_seeds,
-    );
-
-    token::transfer(cpi_ctx, withdraw_amount)?;
-
-    emit!(CollateralWithdrawn {
-        obligation: obligation.key(),
-        reserve: ctx.accounts.withdraw_reserve.key(),
-        collateral_amount: withdraw_amount,
-        owner: ctx.accounts.obligation_owner.key(),
-    });
-
-    Ok(())
+    Ok(withdraw_amount)
 }
 
 /// Accounts required for withdrawing collateral from an obligation.
@@ -148,17 +172,20 @@ pub fn handler(ctx: Context<WithdrawObligationCollateral>, collateral_amount: u6
 #[derive(Accounts)]
 pub struct WithdrawObligationCollateral<'info> {
     #[account(mut)]
-    pub source_collateral: Box<Account<'info, TokenAccount>>,
+    pub source_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut)]
-    pub destination_collateral: Box<Account<'info, TokenAccount>>,
+    pub destination_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         constraint = withdraw_reserve.lending_market == lending_market.key() @ LendingError::InvalidLendingMarket,
         constraint = withdraw_reserve.collateral_supply == source_collateral.key() @ LendingError::InvalidCollateralSupply,
+        has_one = collateral_mint @ LendingError::InvalidCollateralMint,
     )]
     pub withdraw_reserve: Box<Account<'info, Reserve>>,
 
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
     #[account(
         mut,
         constraint = obligation.lending_market == lending_market.key() @ LendingError::InvalidLendingMarket,
@@ -167,6 +194,7 @@ pub struct WithdrawObligationCollateral<'info> {
             Obligation::SEED_PREFIX,
             lending_market.key().as_ref(),
             obligation_owner.key().as_ref(),
+            obligation.id.to_le_bytes().as_ref(),
         ],
         bump
     )]
@@ -183,7 +211,7 @@ pub struct WithdrawObligationCollateral<'info> {
 
     pub obligation_owner: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[event]
@@ -0,0 +1,164 @@
+use crate::errors::LendingError;
+use crate::instructions::redeem_reserve_collateral::CollateralRedeemed;
+use crate::instructions::withdraw_obligation_collateral::{withdraw, CollateralWithdrawn};
+use crate::states::{LendingMarket, Obligation, Reserve};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Burn, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+pub fn handler(
+    ctx: Context<WithdrawObligationCollateralAndRedeemReserveCollateral>,
+    collateral_amount: u64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+
+    // Redemption uses the exchange rate, so interest must be accrued up to now.
+    require!(
+        ctx.accounts.withdraw_reserve.last_update_slot == clock.slot,
+        LendingError::ReserveStale
+    );
+
+    let withdraw_amount = withdraw(
+        &mut ctx.accounts.obligation,
+        &ctx.accounts.withdraw_reserve,
+        &ctx.accounts.lending_market,
+        collateral_amount,
+    )?;
+
+    let reserve = &mut ctx.accounts.withdraw_reserve;
+
+    ctx.accounts
+        .lending_market
+        .require_not_paused(reserve.paused_operations, LendingMarket::PAUSE_REDEEM)?;
+
+    let liquidity_amount = reserve.withdraw_liquidity(withdraw_amount)?;
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let authority_bump = ctx.bumps.lending_market_authority;
+    let authority_seeds = &[b"authority", lending_market_key.as_ref(), &[authority_bump]];
+    let signer_seeds = &[&authority_seeds[..]];
+
+    // The collateral is burned straight out of the reserve's collateral supply.
+    token_interface::burn(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                from: ctx.accounts.source_collateral.to_account_info(),
+                authority: ctx.accounts.lending_market_authority.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        withdraw_amount,
+    )?;
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.reserve_liquidity_supply.to_account_info(),
+                mint: ctx.accounts.liquidity_mint.to_account_info(),
+                to: ctx.accounts.destination_liquidity.to_account_info(),
+                authority: ctx.accounts.lending_market_authority.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        liquidity_amount,
+        ctx.accounts.liquidity_mint.decimals,
+    )?;
+
+    emit!(CollateralWithdrawn {
+        obligation: ctx.accounts.obligation.key(),
+        reserve: ctx.accounts.withdraw_reserve.key(),
+        collateral_amount: withdraw_amount,
+        owner: ctx.accounts.obligation_owner.key(),
+    });
+
+    emit!(CollateralRedeemed {
+        reserve: ctx.accounts.withdraw_reserve.key(),
+        collateral_amount: withdraw_amount,
+        liquidity_amount,
+        redeemer: ctx.accounts.obligation_owner.key(),
+        slot: clock.slot,
+    });
+
+    msg!(
+        "Withdrew and redeemed {} collateral for {} liquidity",
+        withdraw_amount,
+        liquidity_amount
+    );
+
+    Ok(())
+}
+
+/// Accounts context for withdrawing obligation collateral and redeeming it to
+/// liquidity in one step.
+///
+/// - Runs the same health check as `withdraw_obligation_collateral`.
+/// - `withdraw_reserve` must be refreshed in the same slot.
+/// - The collateral is burned from `source_collateral`, the reserve's
+///   collateral supply, and its liquidity sent to `destination_liquidity`.
+#[derive(Accounts)]
+pub struct WithdrawObligationCollateralAndRedeemReserveCollateral<'info> {
+    #[account(mut)]
+    pub source_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = withdraw_reserve.lending_market == lending_market.key() @ LendingError::InvalidLendingMarket,
+        constraint = withdraw_reserve.collateral_supply == source_collateral.key() @ LendingError::InvalidCollateralSupply,
+        has_one = collateral_mint @ LendingError::InvalidCollateralMint,
+    )]
+    pub withdraw_reserve: Box<Account<'info, Reserve>>,
+
+    #[account(mut)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = reserve_liquidity_supply.key() == withdraw_reserve.liquidity_supply
+            @ LendingError::InvalidLiquiditySupply,
+    )]
+    pub reserve_liquidity_supply: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = liquidity_mint.key() == withdraw_reserve.liquidity_mint
+            @ LendingError::InvalidLiquidityMint,
+    )]
+    pub liquidity_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = destination_liquidity.key() != withdraw_reserve.liquidity_supply
+            @ LendingError::InvalidAccountInput,
+        constraint = destination_liquidity.mint == withdraw_reserve.liquidity_mint
+            @ LendingError::InvalidMint,
+    )]
+    pub destination_liquidity: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = obligation.lending_market == lending_market.key() @ LendingError::InvalidLendingMarket,
+        constraint = obligation.owner == obligation_owner.key() @ LendingError::InvalidObligationOwner,
+        seeds = [
+            Obligation::SEED_PREFIX,
+            lending_market.key().as_ref(),
+            obligation_owner.key().as_ref(),
+            obligation.id.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub obligation: Box<Account<'info, Obligation>>,
+
+    pub lending_market: Box<Account<'info, LendingMarket>>,
+
+    /// CHECK: Lending market authority PDA (for CPI signing)
+    #[account(
+        seeds = [b"authority", lending_market.key().as_ref()],
+        bump
+    )]
+    pub lending_market_authority: UncheckedAccount<'info>,
+
+    pub obligation_owner: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
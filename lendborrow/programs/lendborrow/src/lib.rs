@@ -19,16 +19,61 @@ pub mod lendborrow {
 
     pub fn init_lending_market(
         ctx: Context<InitLendingMarket>,
-        quote_currency: [u8; 32]
+        market_id: u16,
+        quote_currency: [u8; 32],
+        name: [u8; 32]
     ) -> Result<()> {
-        instructions::lending_market_init(ctx, quote_currency)
+        instructions::lending_market_init(ctx, market_id, quote_currency, name)
     }
 
-    pub fn set_lending_market_owner(
-        ctx: Context<SetLendingMarketOwner>,
+    pub fn propose_lending_market_owner(
+        ctx: Context<ProposeLendingMarketOwner>,
         new_owner: Pubkey
     ) -> Result<()> {
-        instructions::set_lending_market_owner::handler(ctx, new_owner)
+        instructions::propose_lending_market_owner::handler(ctx, new_owner)
+    }
+
+    pub fn accept_lending_market_owner(ctx: Context<AcceptLendingMarketOwner>) -> Result<()> {
+        instructions::accept_lending_market_owner::handler(ctx)
+    }
+
+    pub fn cancel_lending_market_owner(ctx: Context<CancelLendingMarketOwner>) -> Result<()> {
+        instructions::cancel_lending_market_owner::handler(ctx)
+    }
+
+    pub fn set_lending_market_guardian(
+        ctx: Context<SetLendingMarketGuardian>,
+        new_guardian: Pubkey
+    ) -> Result<()> {
+        instructions::set_lending_market_guardian::handler(ctx, new_guardian)
+    }
+
+    pub fn set_lending_market_risk_manager(
+        ctx: Context<SetLendingMarketRiskManager>,
+        new_risk_manager: Pubkey
+    ) -> Result<()> {
+        instructions::set_lending_market_risk_manager::handler(ctx, new_risk_manager)
+    }
+
+    pub fn set_lending_market_fee_manager(
+        ctx: Context<SetLendingMarketFeeManager>,
+        new_fee_manager: Pubkey
+    ) -> Result<()> {
+        instructions::set_lending_market_fee_manager::handler(ctx, new_fee_manager)
+    }
+
+    pub fn set_lending_market_pause_flags(
+        ctx: Context<SetLendingMarketPauseFlags>,
+        paused_operations: u8
+    ) -> Result<()> {
+        instructions::set_lending_market_pause_flags::handler(ctx, paused_operations)
+    }
+
+    pub fn set_reserve_pause_flags(
+        ctx: Context<SetReservePauseFlags>,
+        paused_operations: u8
+    ) -> Result<()> {
+        instructions::set_reserve_pause_flags::handler(ctx, paused_operations)
     }
 
     pub fn init_reserve(
@@ -39,14 +84,79 @@ pub mod lendborrow {
         instructions::reserve_init::handler(ctx, liquidity_amount, config)
     }
 
-    pub fn init_obligation(ctx: Context<InitObligation>) -> Result<()> {
-        instructions::obligation_init::handler(ctx)
+    pub fn update_reserve_config(
+        ctx: Context<UpdateReserveConfig>,
+        config: ReserveConfig
+    ) -> Result<()> {
+        instructions::update_reserve_config::handler(ctx, config)
+    }
+
+    pub fn set_reserve_secondary_oracle(ctx: Context<SetReserveSecondaryOracle>) -> Result<()> {
+        instructions::set_reserve_secondary_oracle::handler(ctx)
+    }
+
+    pub fn set_reserve_fee_receiver(ctx: Context<SetReserveFeeReceiver>) -> Result<()> {
+        instructions::set_reserve_fee_receiver::handler(ctx)
+    }
+
+    pub fn set_max_liquidation_threshold_step(
+        ctx: Context<SetMaxLiquidationThresholdStep>,
+        max_step: u8
+    ) -> Result<()> {
+        instructions::set_max_liquidation_threshold_step::handler(ctx, max_step)
+    }
+
+    pub fn set_emode_category(
+        ctx: Context<SetEModeCategory>,
+        category_id: u8,
+        loan_to_value_ratio: u8,
+        liquidation_threshold: u8
+    ) -> Result<()> {
+        instructions::set_emode_category::handler(
+            ctx,
+            category_id,
+            loan_to_value_ratio,
+            liquidation_threshold,
+        )
+    }
+
+    pub fn set_timelock_delay(
+        ctx: Context<SetTimelockDelay>,
+        delay_slots: u64
+    ) -> Result<()> {
+        instructions::set_timelock_delay::handler(ctx, delay_slots)
+    }
+
+    pub fn queue_admin_change(
+        ctx: Context<QueueAdminChange>,
+        change: AdminChange
+    ) -> Result<()> {
+        instructions::queue_admin_change::handler(ctx, change)
+    }
+
+    pub fn execute_admin_change(ctx: Context<ExecuteAdminChange>) -> Result<()> {
+        instructions::execute_admin_change::handler(ctx)
+    }
+
+    pub fn cancel_admin_change(ctx: Context<CancelAdminChange>) -> Result<()> {
+        instructions::cancel_admin_change::handler(ctx)
+    }
+
+    pub fn init_obligation(ctx: Context<InitObligation>, id: u16) -> Result<()> {
+        instructions::obligation_init::handler(ctx, id)
     }
 
     pub fn refresh_obligation(ctx: Context<RefreshObligation>) -> Result<()> {
         instructions::refresh_obligation::handler(ctx)
     }
 
+    pub fn set_obligation_emode(
+        ctx: Context<SetObligationEMode>,
+        category_id: u8
+    ) -> Result<()> {
+        instructions::set_obligation_emode::handler(ctx, category_id)
+    }
+
     pub fn deposit_obligation_collateral(
         ctx: Context<DepositObligationCollateral>,
         collateral_amount: u64
@@ -61,6 +171,16 @@ pub mod lendborrow {
         instructions::withdraw_obligation_collateral::handler(ctx, collateral_amount)
     }
 
+    pub fn withdraw_obligation_collateral_and_redeem_reserve_collateral(
+        ctx: Context<WithdrawObligationCollateralAndRedeemReserveCollateral>,
+        collateral_amount: u64
+    ) -> Result<()> {
+        instructions::withdraw_obligation_collateral_and_redeem_reserve_collateral::handler(
+            ctx,
+            collateral_amount
+        )
+    }
+
     pub fn borrow_obligation_liquidity(
         ctx: Context<BorrowObligationLiquidity>,
         liquidity_amount: u64
@@ -86,6 +206,38 @@ pub mod lendborrow {
         instructions::liquidate_obligation::handler(ctx, liquidity_amount)
     }
 
+    pub fn liquidate_obligation_and_redeem_reserve_collateral(
+        ctx: Context<LiquidateObligationAndRedeemReserveCollateral>,
+        liquidity_amount: u64
+    ) -> Result<()> {
+        instructions::liquidate_obligation_and_redeem_reserve_collateral::handler(
+            ctx,
+            liquidity_amount
+        )
+    }
+
+    pub fn socialize_bad_debt(ctx: Context<SocializeBadDebt>) -> Result<()> {
+        instructions::socialize_bad_debt::handler(ctx)
+    }
+
+    pub fn init_insurance_vault(ctx: Context<InitInsuranceVault>) -> Result<()> {
+        instructions::init_insurance_vault::handler(ctx)
+    }
+
+    pub fn set_insurance_fee_percentage(
+        ctx: Context<SetInsuranceFeePercentage>,
+        insurance_fee_percentage: u8
+    ) -> Result<()> {
+        instructions::set_insurance_fee_percentage::handler(ctx, insurance_fee_percentage)
+    }
+
+    pub fn draw_insurance(
+        ctx: Context<DrawInsurance>,
+        amount: u64
+    ) -> Result<()> {
+        instructions::draw_insurance::handler(ctx, amount)
+    }
+
     pub fn deposit_reserve_liquidity(
         ctx: Context<DepositReserveLiquidity>,
         liquidity_amount: u64
@@ -93,12 +245,45 @@ pub mod lendborrow {
         instructions::deposit_reserve_liquidity::handler(ctx, liquidity_amount)
     }
 
+    pub fn deposit_reserve_liquidity_and_obligation_collateral(
+        ctx: Context<DepositReserveLiquidityAndObligationCollateral>,
+        liquidity_amount: u64
+    ) -> Result<()> {
+        instructions::deposit_reserve_liquidity_and_obligation_collateral::handler(
+            ctx,
+            liquidity_amount
+        )
+    }
+
     pub fn redeem_reserve_collateral(
         ctx: Context<RedeemReserveCollateral>,
         collateral_amount: u64
     ) -> Result<()> {
         instructions::redeem_reserve_collateral::handler(ctx, collateral_amount)
     }
+
+    pub fn claim_protocol_fees(ctx: Context<ClaimProtocolFees>) -> Result<()> {
+        instructions::claim_protocol_fees::handler(ctx)
+    }
+
+    pub fn flash_borrow_reserve_liquidity(
+        ctx: Context<FlashBorrowReserveLiquidity>,
+        liquidity_amount: u64
+    ) -> Result<()> {
+        instructions::flash_borrow_reserve_liquidity::handler(ctx, liquidity_amount)
+    }
+
+    pub fn flash_repay_reserve_liquidity(
+        ctx: Context<FlashRepayReserveLiquidity>,
+        liquidity_amount: u64,
+        borrow_instruction_index: u8
+    ) -> Result<()> {
+        instructions::flash_repay_reserve_liquidity::handler(
+            ctx,
+            liquidity_amount,
+            borrow_instruction_index
+        )
+    }
 }
 
 #[derive(Accounts)]
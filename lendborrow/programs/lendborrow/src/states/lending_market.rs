@@ -5,19 +5,91 @@ use anchor_lang::prelude::*;
 /// Global configuration account for a lending market.
 ///
 /// `LendingMarket` represents a single lending protocol instance on Solana:
-/// - defines who controls the market (`owner`),
+/// - defines who controls the market (`owner`) and its `market_id` among that
+///   owner's markets,
+/// - holds the `pending_owner` proposed by the owner until it accepts the transfer,
 /// - defines the PDA authority that owns all vaults and mints (`authority`),
 /// - stores the program/version metadata,
-/// - defines the quote currency (e.g. "USD", "USDC") used for pricing,
-/// - stores which token program is used (classic SPL or Token-2022).
+/// - defines the quote currency (e.g. "USD", "USDC") used for pricing and a
+///   display `name`,
+/// - stores which token program is used (classic SPL or Token-2022),
+/// - bounds how far a reserve's liquidation threshold may drop in one config update,
+/// - holds the emergency `guardian` and the market-wide `paused_operations` bitmask,
+/// - holds the `risk_manager` allowed to change reserve risk parameters and the
+///   `fee_manager` allowed to change fee rates and receivers, next to the owner,
+/// - sets the `timelock_delay_slots` that queued admin changes wait for,
+/// - sets the `insurance_fee_percentage` of borrow fees and liquidation bonuses
+///   paid into the market's insurance vaults,
+/// - defines the e-mode categories reserves can be tagged with.
 pub struct LendingMarket {
     pub owner: Pubkey,
+    pub pending_owner: Pubkey,
     pub authority: Pubkey,      
     pub authority_bump: u8,      
     pub version: u64,           
     pub bump_seed: u8,
+    pub market_id: u16,
     pub quote_currency: [u8; 32],
+    pub name: [u8; 32],
     pub token_program_id: Pubkey,
+    pub max_liquidation_threshold_step: u8,
+    pub guardian: Pubkey,
+    pub risk_manager: Pubkey,
+    pub fee_manager: Pubkey,
+    pub timelock_delay_slots: u64,
+    pub queued_change_count: u64,
+    pub paused_operations: u8,
+    pub insurance_fee_percentage: u8,
+    pub emode_categories: [EModeCategory; LendingMarket::MAX_EMODE_CATEGORIES],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, InitSpace)]
+/// Risk parameters shared by reserves of correlated assets.
+///
+/// A category with a zero `loan_to_value_ratio` is disabled.
+pub struct EModeCategory {
+    pub loan_to_value_ratio: u8,
+    pub liquidation_threshold: u8,
+}
+
+impl EModeCategory {
+    pub fn is_enabled(&self) -> bool {
+        self.loan_to_value_ratio > 0
+    }
+
+    /// Liquidation threshold obligations in this category are held to, or 0
+    /// if the category is disabled.
+    pub fn effective_liquidation_threshold(&self) -> u8 {
+        if self.is_enabled() {
+            self.liquidation_threshold
+        } else {
+            0
+        }
+    }
+
+    /// Returns the allowed and unhealthy borrow values for `deposited_value` of
+    /// collateral under this category, never lower than the per-reserve values.
+    pub fn borrow_values(
+        &self,
+        deposited_value: u128,
+        allowed_borrow_value: u128,
+        unhealthy_borrow_value: u128,
+    ) -> Result<(u128, u128)> {
+        let emode_allowed_borrow_value = deposited_value
+            .checked_mul(self.loan_to_value_ratio as u128)
+            .and_then(|v| v.checked_div(100))
+            .ok_or(crate::errors::LendingError::MathOverflow)?;
+
+        let emode_unhealthy_borrow_value = deposited_value
+            .checked_mul(self.liquidation_threshold as u128)
+            .and_then(|v| v.checked_div(100))
+            .ok_or(crate::errors::LendingError::MathOverflow)?;
+
+        Ok((
+            allowed_borrow_value.max(emode_allowed_borrow_value),
+            unhealthy_borrow_value.max(emode_unhealthy_borrow_value),
+        ))
+    }
 }
 
 impl LendingMarket {
@@ -26,6 +98,165 @@ impl LendingMarket {
     pub const PROGRAM_VERSION: u8 = 1;
 
     pub const SEED_PREFIX: &'static [u8] = b"lending-market";
+
+    /// Default maximum decrease (in percentage points) of a reserve's
+    /// liquidation threshold allowed by a single `update_reserve_config`.
+    pub const DEFAULT_MAX_LIQUIDATION_THRESHOLD_STEP: u8 = 5;
+
+    /// Pausable operations, used in `paused_operations` on both the market
+    /// and on each `Reserve`.
+    pub const PAUSE_DEPOSIT: u8 = 1 << 0;
+    pub const PAUSE_REDEEM: u8 = 1 << 1;
+    pub const PAUSE_BORROW: u8 = 1 << 2;
+    pub const PAUSE_REPAY: u8 = 1 << 3;
+    pub const PAUSE_WITHDRAW: u8 = 1 << 4;
+    pub const PAUSE_LIQUIDATE: u8 = 1 << 5;
+    pub const PAUSE_FLASH_LOAN: u8 = 1 << 6;
+    pub const PAUSE_ALL: u8 = (1 << 7) - 1;
+
+    /// PDA seed prefix for `QueuedAdminChange` accounts.
+    pub const QUEUED_CHANGE_SEED: &'static [u8] = b"queued-change";
+
+    /// Longest allowed `timelock_delay_slots`, about 30 days of slots.
+    pub const MAX_TIMELOCK_DELAY_SLOTS: u64 = 6_480_000;
+
+    /// PDA seed prefix for insurance vaults, one per `(market, mint)`.
+    pub const INSURANCE_VAULT_SEED: &'static [u8] = b"insurance-vault";
+
+    /// Number of e-mode categories. Category ids run from 1 to this value;
+    /// 0 means no category.
+    pub const MAX_EMODE_CATEGORIES: usize = 8;
+
+    /// Returns the enabled e-mode category with the given id, if any.
+    pub fn emode_category(&self, category_id: u8) -> Option<&EModeCategory> {
+        if category_id == 0 {
+            return None;
+        }
+
+        self.emode_categories
+            .get(category_id as usize - 1)
+            .filter(|category| category.is_enabled())
+    }
+
+    /// Checks that `category_id` exists and `loan_to_value_ratio <=
+    /// liquidation_threshold <= 100`.
+    pub fn validate_emode_category(
+        category_id: u8,
+        loan_to_value_ratio: u8,
+        liquidation_threshold: u8,
+    ) -> Result<()> {
+        require!(
+            category_id >= 1 && category_id as usize <= Self::MAX_EMODE_CATEGORIES,
+            crate::errors::LendingError::InvalidEModeCategory
+        );
+
+        require!(
+            loan_to_value_ratio <= liquidation_threshold && liquidation_threshold <= 100,
+            crate::errors::LendingError::InvalidConfig
+        );
+        Ok(())
+    }
+
+    /// Validates and stores the parameters of e-mode category `category_id`.
+    ///
+    /// Like reserve config updates, the effective liquidation threshold may
+    /// drop by at most `max_liquidation_threshold_step` per call; disabling a
+    /// category counts as dropping it to 0.
+    pub fn set_emode_category(
+        &mut self,
+        category_id: u8,
+        loan_to_value_ratio: u8,
+        liquidation_threshold: u8,
+    ) -> Result<()> {
+        Self::validate_emode_category(category_id, loan_to_value_ratio, liquidation_threshold)?;
+
+        let category = EModeCategory {
+            loan_to_value_ratio,
+            liquidation_threshold,
+        };
+
+        let threshold_decrease = self.emode_categories[category_id as usize - 1]
+            .effective_liquidation_threshold()
+            .saturating_sub(category.effective_liquidation_threshold());
+
+        require!(
+            threshold_decrease <= self.max_liquidation_threshold_step,
+            crate::errors::LendingError::LiquidationThresholdStepTooLarge
+        );
+
+        self.emode_categories[category_id as usize - 1] = category;
+        Ok(())
+    }
+
+    /// Validates and stores the per-update liquidation threshold step.
+    pub fn set_max_liquidation_threshold_step(&mut self, max_step: u8) -> Result<()> {
+        require!(max_step <= 100, crate::errors::LendingError::InvalidConfig);
+
+        self.max_liquidation_threshold_step = max_step;
+        Ok(())
+    }
+
+    /// Validates and stores the share of fees paid into insurance vaults.
+    pub fn set_insurance_fee_percentage(&mut self, insurance_fee_percentage: u8) -> Result<()> {
+        require!(
+            insurance_fee_percentage <= 100,
+            crate::errors::LendingError::InvalidConfig
+        );
+
+        self.insurance_fee_percentage = insurance_fee_percentage;
+        Ok(())
+    }
+
+    /// Validates and stores the delay queued admin changes wait for.
+    pub fn set_timelock_delay_slots(&mut self, delay_slots: u64) -> Result<()> {
+        require!(
+            delay_slots <= Self::MAX_TIMELOCK_DELAY_SLOTS,
+            crate::errors::LendingError::InvalidConfig
+        );
+
+        self.timelock_delay_slots = delay_slots;
+        Ok(())
+    }
+
+    /// Fails with `TimelockRequired` if admin changes on this market must go
+    /// through `queue_admin_change`.
+    pub fn require_no_timelock(&self) -> Result<()> {
+        require!(
+            self.timelock_delay_slots == 0,
+            crate::errors::LendingError::TimelockRequired
+        );
+        Ok(())
+    }
+
+    /// Returns true if `key` may change reserve risk parameters.
+    pub fn is_risk_authority(&self, key: &Pubkey) -> bool {
+        *key == self.owner || *key == self.risk_manager
+    }
+
+    /// Returns true if `key` may change reserve fee rates and receivers.
+    pub fn is_fee_authority(&self, key: &Pubkey) -> bool {
+        *key == self.owner || *key == self.fee_manager
+    }
+
+    /// Returns the part of `amount` owed to the insurance vault.
+    pub fn insurance_share(&self, amount: u64) -> Result<u64> {
+        let share = (amount as u128)
+            .checked_mul(self.insurance_fee_percentage as u128)
+            .and_then(|v| v.checked_div(100))
+            .ok_or(crate::errors::LendingError::MathOverflow)?;
+
+        Ok(share as u64)
+    }
+
+    /// Fails with `OperationPaused` if `operation` is paused either market-wide
+    /// or on the reserve whose flags are passed in.
+    pub fn require_not_paused(&self, reserve_paused_operations: u8, operation: u8) -> Result<()> {
+        require!(
+            (self.paused_operations | reserve_paused_operations) & operation == 0,
+            crate::errors::LendingError::OperationPaused
+        );
+        Ok(())
+    }
     
     /// Validates the `quote_currency` field.
     ///
@@ -64,4 +295,12 @@ impl LendingMarket {
 
         non_zero_bytes >= 20
     }
+
+    /// A market name is a non-empty, zero-padded UTF-8 string.
+    pub fn validate_name(name: &[u8; 32]) -> bool {
+        match std::str::from_utf8(name) {
+            Ok(s) => !s.trim_end_matches('\0').is_empty(),
+            Err(_) => false,
+        }
+    }
 }
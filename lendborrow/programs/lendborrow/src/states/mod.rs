@@ -1,9 +1,11 @@
 pub mod lending_market;
 pub mod obligation;
+pub mod queued_admin_change;
 pub mod reserve;
 pub mod reserve_config;
 
 pub use lending_market::*;
 pub use obligation::*;
+pub use queued_admin_change::*;
 pub use reserve::*;
 pub use reserve_config::*;
@@ -1,7 +1,6 @@
 use anchor_lang::prelude::*;
 
 pub const MAX_OBLIGATION_RESERVES: usize = 10;
-pub const LIQUIDATION_CLOSE_FACTOR: u8 = 50;
 
 #[account]
 #[derive(InitSpace)]
@@ -13,17 +12,30 @@ pub const LIQUIDATION_CLOSE_FACTOR: u8 = 50;
 /// - how much the user is **allowed to borrow**,
 /// - when the position becomes **unhealthy** (liquidatable),
 /// - per-reserve collateral and borrow positions stored in a compact, flat buffer.
+///
+/// An owner can hold several obligations per market, told apart by `id`, which
+/// is part of the PDA seeds. Each one is valued and liquidated on its own.
+///
+/// While `isolated_collateral_reserve` is set, that reserve is the obligation's
+/// only collateral and borrowing is restricted to isolation-borrowable reserves.
+///
+/// `emode_category` is the e-mode category the owner opted into (0 for none).
+/// When every deposit and borrow belongs to it, the category's risk parameters
+/// apply on refresh.
 pub struct Obligation {
     pub version: u8,
     pub last_update_slot: u64,
     pub lending_market: Pubkey,
     pub owner: Pubkey,
+    pub id: u16,
     pub deposited_value: u128,
     pub borrowed_value: u128,
     pub allowed_borrow_value: u128,
     pub unhealthy_borrow_value: u128,
     pub deposits_len: u8,
     pub borrows_len: u8,
+    pub isolated_collateral_reserve: Pubkey,
+    pub emode_category: u8,
     #[max_len(896)]
     pub data_flat: Vec<u8>,
 }
@@ -32,6 +44,16 @@ impl Obligation {
     pub const PROGRAM_VERSION: u8 = 1;
     pub const SEED_PREFIX: &'static [u8] = b"obligation";
 
+    /// Requires a `refresh_obligation` before the obligation is used again,
+    /// even later in the same slot.
+    pub fn mark_stale(&mut self) {
+        self.last_update_slot = self.last_update_slot.saturating_sub(1);
+    }
+
+    /// Returns true if the obligation's collateral is an isolation-mode reserve.
+    pub fn is_isolated(&self) -> bool {
+        self.isolated_collateral_reserve != Pubkey::default()
+    }
 
     /// Returns the maximum value (in quote currency) that can be safely withdrawn.
     ///
@@ -291,12 +313,25 @@ impl Obligation {
 
     /// Finds existing collateral for a given reserve or creates a new one if missing.
     ///
+    /// `isolated` is the reserve's `isolation_mode`. Isolated collateral can only be
+    /// added to an obligation with no other collateral and no open borrows, and no
+    /// other collateral can be added next to it. An isolated obligation whose
+    /// collateral was withdrawn or seized may still top it back up.
+    ///
     /// Returns the index of the collateral entry.
-    pub fn find_or_add_collateral(&mut self, deposit_reserve: Pubkey) -> Result<usize> {
+    pub fn find_or_add_collateral(&mut self, deposit_reserve: Pubkey, isolated: bool) -> Result<usize> {
         if let Ok((_, index)) = self.find_collateral(deposit_reserve) {
             return Ok(index);
         }
 
+        let allowed = if self.is_isolated() {
+            deposit_reserve == self.isolated_collateral_reserve
+        } else {
+            !isolated || (self.deposits_len == 0 && self.borrows_len == 0)
+        };
+
+        require!(allowed, crate::errors::LendingError::IsolatedCollateralConflict);
+
         require!(
             (self.deposits_len as usize + self.borrows_len as usize) < MAX_OBLIGATION_RESERVES,
             crate::errors::LendingError::ObligationReserveLimit
@@ -306,6 +341,10 @@ impl Obligation {
         let index = self.deposits_len as usize;
         self.add_collateral(collateral)?;
 
+        if isolated {
+            self.isolated_collateral_reserve = deposit_reserve;
+        }
+
         Ok(index)
     }
 
@@ -410,12 +449,20 @@ impl Obligation {
     /// Removes a liquidity entry at `index` from the flat buffer.
     ///
     /// This compacts `data_flat` and decrements `borrows_len`.
+    ///
+    /// The isolated collateral reserve stays recorded while borrows remain, so
+    /// their debt can still be released from its ceiling.
     pub fn remove_collateral(&mut self, index: usize) -> Result<()> {
         require!(
             index < self.deposits_len as usize,
             crate::errors::LendingError::InvalidObligationIndex
         );
 
+        let (collateral, _) = self.find_collateral_by_index(index)?;
+        if collateral.deposit_reserve == self.isolated_collateral_reserve && self.borrows_len == 0 {
+            self.isolated_collateral_reserve = Pubkey::default();
+        }
+
         let offset = index * ObligationCollateral::LEN;
         self.data_flat
             .drain(offset..offset + ObligationCollateral::LEN);
@@ -434,6 +481,10 @@ impl Obligation {
         self.data_flat
             .drain(offset..offset + ObligationLiquidity::LEN);
         self.borrows_len = self.borrows_len.checked_sub(1).unwrap();
+
+        if self.borrows_len == 0 && self.deposits_len == 0 {
+            self.isolated_collateral_reserve = Pubkey::default();
+        }
         Ok(())
     }
 }
@@ -564,4 +615,73 @@ impl ObligationLiquidity {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const WAD: u128 = 1_000_000_000_000_000_000;
+
+    pub(crate) fn obligation() -> Obligation {
+        Obligation {
+            version: Obligation::PROGRAM_VERSION,
+            last_update_slot: 0,
+            lending_market: Pubkey::default(),
+            owner: Pubkey::default(),
+            id: 0,
+            deposited_value: 0,
+            borrowed_value: 0,
+            allowed_borrow_value: 0,
+            unhealthy_borrow_value: 0,
+            deposits_len: 0,
+            borrows_len: 0,
+            isolated_collateral_reserve: Pubkey::default(),
+            emode_category: 0,
+            data_flat: Vec::new(),
+        }
+    }
+
+    /// Isolated obligation with 100 collateral tokens in `isolated_reserve`
+    /// and 50 tokens borrowed from `borrow_reserve`.
+    fn isolated_obligation(isolated_reserve: Pubkey, borrow_reserve: Pubkey) -> Obligation {
+        let mut obligation = obligation();
+
+        let index = obligation.find_or_add_collateral(isolated_reserve, true).unwrap();
+        let (mut collateral, _) = obligation.find_collateral_by_index(index).unwrap();
+        collateral.deposit(100).unwrap();
+        obligation.update_collateral(index, collateral).unwrap();
+
+        let index = obligation.find_or_add_liquidity(borrow_reserve).unwrap();
+        let (mut liquidity, _) = obligation.find_liquidity_by_index(index).unwrap();
+        liquidity.borrow(50 * WAD).unwrap();
+        obligation.update_liquidity(index, liquidity).unwrap();
+
+        obligation
+    }
+
+    #[test]
+    fn keeps_isolated_reserve_while_borrows_remain() {
+        let isolated_reserve = Pubkey::new_unique();
+        let mut obligation = isolated_obligation(isolated_reserve, Pubkey::new_unique());
+
+        obligation.withdraw(0, 100).unwrap();
+
+        assert_eq!(obligation.deposits_len, 0);
+        assert_eq!(obligation.isolated_collateral_reserve, isolated_reserve);
+
+        assert!(obligation.find_or_add_collateral(Pubkey::new_unique(), false).is_err());
+        assert_eq!(obligation.find_or_add_collateral(isolated_reserve, true).unwrap(), 0);
+    }
+
+    #[test]
+    fn releases_isolated_reserve_once_debt_is_gone() {
+        let mut obligation = isolated_obligation(Pubkey::new_unique(), Pubkey::new_unique());
+
+        obligation.withdraw(0, 100).unwrap();
+        obligation.repay(0, 50 * WAD).unwrap();
+
+        assert_eq!(obligation.borrows_len, 0);
+        assert!(!obligation.is_isolated());
+    }
+}
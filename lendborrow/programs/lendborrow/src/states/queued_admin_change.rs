@@ -0,0 +1,202 @@
+use super::lending_market::LendingMarket;
+use super::reserve::Reserve;
+use super::reserve_config::ReserveConfig;
+use crate::errors::LendingError;
+use anchor_lang::prelude::*;
+
+#[account]
+#[derive(InitSpace)]
+/// An admin change waiting out the market's timelock.
+///
+/// Created by `queue_admin_change` with `eta_slot = queued_slot +
+/// timelock_delay_slots`. Anyone can apply it with `execute_admin_change` once
+/// `eta_slot` is reached and the market's current delay has also passed since
+/// `queued_slot`, as long as the proposer still holds the role the change
+/// needs. The proposer or the market owner can withdraw it with
+/// `cancel_admin_change`. Either way the account is closed to the proposer.
+pub struct QueuedAdminChange {
+    pub lending_market: Pubkey,
+    /// Target reserve for `AdminChange::ReserveConfig`,
+    /// `AdminChange::SecondaryOracle` and `AdminChange::FeeReceiver`, default
+    /// otherwise.
+    pub reserve: Pubkey,
+    pub proposer: Pubkey,
+    pub id: u64,
+    pub change: AdminChange,
+    pub queued_slot: u64,
+    pub eta_slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+/// Admin changes that are subject to the market timelock.
+pub enum AdminChange {
+    /// Replace a reserve's config, as `update_reserve_config` would.
+    ReserveConfig(ReserveConfig),
+    /// Set an e-mode category, as `set_emode_category` would.
+    EModeCategory {
+        category_id: u8,
+        loan_to_value_ratio: u8,
+        liquidation_threshold: u8,
+    },
+    /// Set the market's `max_liquidation_threshold_step`.
+    MaxLiquidationThresholdStep(u8),
+    /// Set the market's `timelock_delay_slots`.
+    TimelockDelay(u64),
+    /// Set or clear (default key) a reserve's secondary oracle, as
+    /// `set_reserve_secondary_oracle` would.
+    SecondaryOracle(Pubkey),
+    /// Propose a new market owner, as `propose_lending_market_owner` would.
+    PendingOwner(Pubkey),
+    /// Set the market's `insurance_fee_percentage`, as
+    /// `set_insurance_fee_percentage` would.
+    InsuranceFeePercentage(u8),
+    /// Redirect a reserve's fees, as `set_reserve_fee_receiver` would.
+    FeeReceiver(Pubkey),
+    /// Set or clear (default key) the guardian, as
+    /// `set_lending_market_guardian` would.
+    Guardian(Pubkey),
+    /// Set or clear (default key) the risk manager, as
+    /// `set_lending_market_risk_manager` would.
+    RiskManager(Pubkey),
+    /// Set or clear (default key) the fee manager, as
+    /// `set_lending_market_fee_manager` would.
+    FeeManager(Pubkey),
+}
+
+impl QueuedAdminChange {
+    /// First slot the change may execute in.
+    ///
+    /// A delay raised after the change was queued applies to it as well.
+    pub fn ready_slot(&self, lending_market: &LendingMarket) -> Result<u64> {
+        let ready_slot = self
+            .queued_slot
+            .checked_add(lending_market.timelock_delay_slots)
+            .ok_or(LendingError::MathOverflow)?;
+
+        Ok(ready_slot.max(self.eta_slot))
+    }
+}
+
+impl AdminChange {
+    /// Fails unless `authority` may make this change on `lending_market`.
+    ///
+    /// `reserve` is the change's target reserve; a `ReserveConfig` that
+    /// touches its fees also needs fee authority.
+    pub fn require_authority(
+        &self,
+        lending_market: &LendingMarket,
+        reserve: Option<&Reserve>,
+        authority: &Pubkey,
+    ) -> Result<()> {
+        match self {
+            AdminChange::ReserveConfig(config) => {
+                let reserve = reserve.ok_or(LendingError::InvalidQueuedChangeReserve)?;
+
+                require!(
+                    lending_market.is_risk_authority(authority),
+                    LendingError::InvalidRiskManager
+                );
+
+                let fees_changed = config.fees != reserve.config.fees
+                    || config.protocol_take_rate != reserve.config.protocol_take_rate;
+
+                require!(
+                    !fees_changed || lending_market.is_fee_authority(authority),
+                    LendingError::InvalidFeeManager
+                );
+            }
+            AdminChange::EModeCategory { .. } | AdminChange::SecondaryOracle(_) => {
+                require!(
+                    lending_market.is_risk_authority(authority),
+                    LendingError::InvalidRiskManager
+                );
+            }
+            AdminChange::InsuranceFeePercentage(_) | AdminChange::FeeReceiver(_) => {
+                require!(
+                    lending_market.is_fee_authority(authority),
+                    LendingError::InvalidFeeManager
+                );
+            }
+            AdminChange::MaxLiquidationThresholdStep(_)
+            | AdminChange::TimelockDelay(_)
+            | AdminChange::PendingOwner(_)
+            | AdminChange::Guardian(_)
+            | AdminChange::RiskManager(_)
+            | AdminChange::FeeManager(_) => {
+                require_keys_eq!(*authority, lending_market.owner, LendingError::InvalidOwner);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::states::reserve::tests::{market, reserve};
+
+    fn queued_change(change: AdminChange, proposer: Pubkey) -> QueuedAdminChange {
+        QueuedAdminChange {
+            lending_market: Pubkey::default(),
+            reserve: Pubkey::default(),
+            proposer,
+            id: 0,
+            change,
+            queued_slot: 100,
+            eta_slot: 104,
+        }
+    }
+
+    #[test]
+    fn applies_a_delay_raised_after_queueing() {
+        let mut lending_market = market();
+        let queued_change = queued_change(AdminChange::TimelockDelay(0), Pubkey::default());
+
+        lending_market.timelock_delay_slots = 50;
+        assert_eq!(queued_change.ready_slot(&lending_market).unwrap(), 150);
+
+        lending_market.timelock_delay_slots = 0;
+        assert_eq!(queued_change.ready_slot(&lending_market).unwrap(), 104);
+    }
+
+    #[test]
+    fn rejects_a_proposer_who_lost_their_role() {
+        let mut lending_market = market();
+        let risk_manager = Pubkey::new_unique();
+        lending_market.risk_manager = risk_manager;
+
+        let reserve = reserve(0, 0);
+        let mut config = reserve.config.clone();
+        config.max_borrow_rate = 40;
+        let change = AdminChange::ReserveConfig(config);
+
+        change
+            .require_authority(&lending_market, Some(&reserve), &risk_manager)
+            .unwrap();
+
+        lending_market.risk_manager = Pubkey::new_unique();
+        assert!(change
+            .require_authority(&lending_market, Some(&reserve), &risk_manager)
+            .is_err());
+        assert!(AdminChange::TimelockDelay(10)
+            .require_authority(&lending_market, None, &risk_manager)
+            .is_err());
+    }
+
+    #[test]
+    fn caps_the_timelock_delay() {
+        let mut lending_market = market();
+
+        lending_market
+            .set_timelock_delay_slots(LendingMarket::MAX_TIMELOCK_DELAY_SLOTS)
+            .unwrap();
+        assert!(lending_market
+            .set_timelock_delay_slots(LendingMarket::MAX_TIMELOCK_DELAY_SLOTS + 1)
+            .is_err());
+        assert_eq!(
+            lending_market.timelock_delay_slots,
+            LendingMarket::MAX_TIMELOCK_DELAY_SLOTS
+        );
+    }
+}
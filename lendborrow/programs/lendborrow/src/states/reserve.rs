@@ -1,6 +1,8 @@
+use super::lending_market::LendingMarket;
 use super::reserve_config::*;
 use anchor_lang::prelude::*;
 use crate::errors::LendingError;
+use crate::utils::math::mul_div;
 
 /// High-precision fixed-point WAD (10^18) used throughout rate calculations.
 const WAD: u128 = 1_000_000_000_000_000_000;
@@ -43,6 +45,9 @@ pub struct Reserve {
     /// Oracle account providing market price for liquidity token.
     pub liquidity_oracle: Pubkey,
 
+    /// Optional secondary oracle cross-checked on refresh (`Pubkey::default()` if unset).
+    pub secondary_oracle: Pubkey,
+
     /// Amount of liquidity currently available for withdrawal/borrowing.
     pub liquidity_available_amount: u64,
 
@@ -55,6 +60,17 @@ pub struct Reserve {
     /// Current oracle market price for the liquidity token (WAD precision).
     pub liquidity_market_price: u128,
 
+    /// Oracle EMA price for the liquidity token, or 0 if the oracle has none
+    /// or `config.price_mode` does not use it.
+    pub liquidity_ema_price: u128,
+
+    /// Latest secondary oracle price, or 0 if no secondary oracle is set.
+    pub liquidity_secondary_market_price: u128,
+
+    /// Set when a `PriceMode::Pegged` reserve trades outside its peg band;
+    /// borrowing is halted until the price returns.
+    pub depegged: bool,
+
     /// Mint of the collateral token representing deposit shares.
     pub collateral_mint: Pubkey,
 
@@ -64,6 +80,26 @@ pub struct Reserve {
     /// Total supply of collateral tokens (scaled by exchange rate).
     pub collateral_mint_total_supply: u64,
 
+    /// Protocol share of accrued interest still owed by borrowers (WAD precision).
+    pub accumulated_protocol_fees_wads: u128,
+
+    /// Protocol fees repaid into the liquidity supply and not yet claimed.
+    /// Held outside `liquidity_available_amount`.
+    pub claimable_protocol_fees: u64,
+
+    /// Bitmask of operations paused on this reserve (see `LendingMarket::PAUSE_*`).
+    pub paused_operations: u8,
+
+    /// Debt (1e6 quote units) currently backed by this reserve as isolated collateral.
+    pub isolated_debt: u128,
+
+    /// Flash loan principal lent out and not yet repaid in the current transaction.
+    pub flash_borrowed_amount: u64,
+
+    /// Bad debt socialized to depositors and not yet covered by the insurance
+    /// vault (WAD precision).
+    pub socialized_bad_debt_wads: u128,
+
     /// Reserve configuration (rates, LTV, liquidation, fees).
     pub config: ReserveConfig,
 }
@@ -103,6 +139,200 @@ impl Reserve {
         self.last_update_slot = self.last_update_slot.saturating_sub(Self::MAX_STALE_SLOTS + 1);
     }
 
+    /// Checks that `config` may replace the current config in `lending_market`.
+    ///
+    /// The oracle and isolation settings are fixed at initialization, since
+    /// prices and isolated obligations already depend on them. Nor can a reserve
+    /// move into an EMA price mode: refresh only stores the EMA while the mode
+    /// reads it, so until the next refresh it would be valued at 0. The e-mode
+    /// category must be 0 or enabled in the market. Lowering the liquidation
+    /// threshold can push healthy obligations straight into liquidation, so it
+    /// may only drop by `max_liquidation_threshold_step` per update.
+    pub fn validate_config_update(
+        &self,
+        config: &ReserveConfig,
+        lending_market: &LendingMarket,
+    ) -> Result<()> {
+        config.validate()?;
+
+        require!(
+            config.oracle_type == self.config.oracle_type
+                && config.pyth_price_feed_id == self.config.pyth_price_feed_id
+                && (!config.price_mode.uses_ema() || self.config.price_mode.uses_ema())
+                && config.isolation_mode == self.config.isolation_mode
+                && config.isolation_borrowable == self.config.isolation_borrowable,
+            LendingError::ImmutableReserveConfig
+        );
+
+        require!(
+            config.emode_category == 0
+                || lending_market.emode_category(config.emode_category).is_some(),
+            LendingError::InvalidEModeCategory
+        );
+
+        let threshold_decrease = self
+            .config
+            .liquidation_threshold
+            .saturating_sub(config.liquidation_threshold);
+
+        require!(
+            threshold_decrease <= lending_market.max_liquidation_threshold_step,
+            LendingError::LiquidationThresholdStepTooLarge
+        );
+
+        Ok(())
+    }
+
+    /// Validates and installs a new config, returning the one it replaces.
+    pub fn update_config(
+        &mut self,
+        config: ReserveConfig,
+        lending_market: &LendingMarket,
+    ) -> Result<ReserveConfig> {
+        self.validate_config_update(&config, lending_market)?;
+
+        Ok(std::mem::replace(&mut self.config, config))
+    }
+
+    /// Returns `true` if a secondary oracle is configured for this reserve.
+    pub fn has_secondary_oracle(&self) -> bool {
+        self.secondary_oracle != Pubkey::default()
+    }
+
+    /// Divergence between two prices in basis points, relative to `primary_price`.
+    pub fn price_divergence_bps(primary_price: u128, secondary_price: u128) -> u128 {
+        if primary_price == 0 {
+            return u128::MAX;
+        }
+
+        primary_price
+            .abs_diff(secondary_price)
+            .saturating_mul(10_000)
+            / primary_price
+    }
+
+    /// Returns `true` if the primary and secondary prices are further apart
+    /// than `config.max_oracle_divergence_bps`.
+    pub fn oracle_prices_diverged(&self) -> bool {
+        self.liquidity_secondary_market_price > 0
+            && Self::price_divergence_bps(
+                self.liquidity_market_price,
+                self.liquidity_secondary_market_price,
+            ) > self.config.max_oracle_divergence_bps as u128
+    }
+
+    /// Applies `PriceMode::Pegged` to a freshly read `liquidity_market_price`.
+    ///
+    /// Inside the peg band the live price is replaced by `config.pegged_price`.
+    /// Outside it the live price is kept and the reserve is marked `depegged`.
+    pub fn apply_peg(&mut self) {
+        if self.config.price_mode != PriceMode::Pegged {
+            self.depegged = false;
+            return;
+        }
+
+        let pegged_price = self.config.pegged_price as u128;
+        let deviation_bps = Self::price_divergence_bps(pegged_price, self.liquidity_market_price);
+
+        self.depegged = deviation_bps > self.config.max_peg_deviation_bps as u128;
+
+        if self.depegged {
+            msg!(
+                "WARNING: Live price {} is {} bps away from peg {}",
+                self.liquidity_market_price,
+                deviation_bps,
+                pegged_price
+            );
+        } else {
+            self.liquidity_market_price = pegged_price;
+        }
+    }
+
+    /// Price used to value deposits of this reserve as collateral.
+    ///
+    /// Picks spot, EMA, or the lower of the two according to `config.price_mode`.
+    /// When the oracles diverge, the secondary price is used if it is lower.
+    pub fn collateral_price(&self) -> u128 {
+        let price = match self.config.price_mode {
+            PriceMode::Spot | PriceMode::Pegged => self.liquidity_market_price,
+            PriceMode::Ema => self.liquidity_ema_price,
+            PriceMode::SpotEmaMinMax => self.liquidity_market_price.min(self.liquidity_ema_price),
+        };
+
+        if self.oracle_prices_diverged() {
+            price.min(self.liquidity_secondary_market_price)
+        } else {
+            price
+        }
+    }
+
+    /// Price used to value borrows of this reserve.
+    ///
+    /// Picks spot, EMA, or the higher of the two according to `config.price_mode`.
+    /// When the oracles diverge, the secondary price is used if it is higher.
+    pub fn debt_price(&self) -> u128 {
+        let price = match self.config.price_mode {
+            PriceMode::Spot | PriceMode::Pegged => self.liquidity_market_price,
+            PriceMode::Ema => self.liquidity_ema_price,
+            PriceMode::SpotEmaMinMax => self.liquidity_market_price.max(self.liquidity_ema_price),
+        };
+
+        if self.oracle_prices_diverged() {
+            price.max(self.liquidity_secondary_market_price)
+        } else {
+            price
+        }
+    }
+
+    /// Values `amount_wads` of this reserve's liquidity at the debt price, in
+    /// 1e6 quote units.
+    pub fn debt_value(&self, amount_wads: u128) -> Result<u128> {
+        let decimals = 10u128
+            .checked_pow(self.liquidity_mint_decimals as u32)
+            .ok_or(LendingError::MathOverflow)?;
+
+        amount_wads
+            .checked_mul(self.debt_price())
+            .and_then(|v| v.checked_div(WAD))
+            .and_then(|v| v.checked_div(decimals))
+            .ok_or(LendingError::MathOverflow.into())
+    }
+
+    /// Records `debt_value` of new borrowing against this isolated collateral
+    /// reserve, failing if it would exceed `config.isolated_debt_ceiling`.
+    pub fn add_isolated_debt(&mut self, debt_value: u128) -> Result<()> {
+        let new_isolated_debt = self.isolated_debt
+            .checked_add(debt_value)
+            .ok_or(LendingError::MathOverflow)?;
+
+        require!(
+            new_isolated_debt <= self.config.isolated_debt_ceiling as u128,
+            LendingError::IsolatedDebtCeilingExceeded
+        );
+
+        self.isolated_debt = new_isolated_debt;
+        Ok(())
+    }
+
+    /// Releases `debt_value` of repaid debt from this isolated collateral reserve.
+    ///
+    /// Debt is valued at current prices on both sides, so the tally saturates at
+    /// zero rather than failing when prices have moved.
+    pub fn remove_isolated_debt(&mut self, debt_value: u128) {
+        self.isolated_debt = self.isolated_debt.saturating_sub(debt_value);
+    }
+
+    /// Borrowed liquidity owed to depositors, in tokens.
+    ///
+    /// The protocol fees accrued on borrows are owed to the protocol, not to
+    /// depositors, so they are left out.
+    fn depositor_borrowed_liquidity(&self) -> Result<u128> {
+        self.liquidity_borrowed_amount_wads
+            .saturating_sub(self.accumulated_protocol_fees_wads)
+            .checked_div(Self::INITIAL_BORROW_RATE)
+            .ok_or(LendingError::MathOverflow.into())
+    }
+
     /// Computes the exchange rate between deposit collateral tokens and underlying liquidity.
     ///
     /// exchange_rate = total_liquidity / collateral_supply
@@ -113,9 +343,7 @@ impl Reserve {
             return Ok(Self::INITIAL_BORROW_RATE);
         }
 
-        let borrowed_liquidity = self.liquidity_borrowed_amount_wads
-            .checked_div(Self::INITIAL_BORROW_RATE)
-            .ok_or(LendingError::MathOverflow)?;
+        let borrowed_liquidity = self.depositor_borrowed_liquidity()?;
 
         let total_liquidity = (self.liquidity_available_amount as u128)
             .checked_add(borrowed_liquidity)
@@ -153,12 +381,67 @@ impl Reserve {
         Ok(collateral_amount as u64)
     }
 
+    /// Total liquidity supplied to the reserve (available + borrowed), in tokens.
+    pub fn total_supply(&self) -> Result<u64> {
+        let borrowed_liquidity = self.depositor_borrowed_liquidity()?;
+
+        let total_supply = (self.liquidity_available_amount as u128)
+            .checked_add(borrowed_liquidity)
+            .ok_or(LendingError::MathOverflow)?;
+
+        u64::try_from(total_supply).map_err(|_| LendingError::MathOverflow.into())
+    }
+
+    /// Fails if depositing `liquidity_amount` would push total supply past
+    /// `config.deposit_limit`.
+    pub fn check_deposit_limit(&self, liquidity_amount: u64) -> Result<()> {
+        let new_total_supply = self.total_supply()?
+            .checked_add(liquidity_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        require!(
+            new_total_supply <= self.config.deposit_limit,
+            LendingError::DepositLimitExceeded
+        );
+
+        Ok(())
+    }
+
+    /// Fails if borrowing `borrow_amount_wads` would push
+    /// `liquidity_borrowed_amount_wads` past `config.borrow_limit`.
+    ///
+    /// Flash loans are repaid within the same transaction and are not capped,
+    /// so an open flash principal does not count towards the limit.
+    pub fn check_borrow_limit(&self, borrow_amount_wads: u128) -> Result<()> {
+        let flash_borrowed_wads = (self.flash_borrowed_amount as u128)
+            .checked_mul(Self::INITIAL_BORROW_RATE)
+            .ok_or(LendingError::MathOverflow)?;
+
+        let new_borrowed_amount_wads = self.liquidity_borrowed_amount_wads
+            .checked_sub(flash_borrowed_wads)
+            .and_then(|v| v.checked_add(borrow_amount_wads))
+            .ok_or(LendingError::MathOverflow)?;
+
+        let borrow_limit_wads = (self.config.borrow_limit as u128)
+            .checked_mul(Self::INITIAL_BORROW_RATE)
+            .ok_or(LendingError::MathOverflow)?;
+
+        require!(
+            new_borrowed_amount_wads <= borrow_limit_wads,
+            LendingError::BorrowLimitExceeded
+        );
+
+        Ok(())
+    }
+
     /// Deposits liquidity into the reserve and returns the amount of collateral minted.
     ///
     /// Also increases liquidity_available_amount and collateral_mint_total_supply.
     pub fn deposit_liquidity(&mut self, liquidity_amount: u64) -> Result<u64> {
         require!(liquidity_amount > 0, LendingError::InvalidAmount);
 
+        self.check_deposit_limit(liquidity_amount)?;
+
         let collateral_amount = self.liquidity_to_collateral(liquidity_amount)?;
 
         self.liquidity_available_amount = self.liquidity_available_amount
@@ -176,9 +459,7 @@ impl Reserve {
     ///
     /// Higher utilization → higher borrow interest rate.
     pub fn calculate_utilization_rate(&self) -> Result<u128> {
-        let borrowed = self.liquidity_borrowed_amount_wads
-            .checked_div(Self::INITIAL_BORROW_RATE)
-            .ok_or(LendingError::MathOverflow)?;
+        let borrowed = self.depositor_borrowed_liquidity()?;
 
         let total_liquidity = (self.liquidity_available_amount as u128)
             .checked_add(borrowed)
@@ -290,7 +571,9 @@ impl Reserve {
     /// 3. compute compound rate for N slots
     /// 4. update cumulative borrow rate
     /// 5. increase borrowed amount
-    /// 6. mint interest into available liquidity
+    /// 6. set aside `protocol_take_rate` of the interest as protocol fees
+    ///
+    /// Interest only becomes available liquidity once it is repaid.
     pub fn accrue_interest(&mut self, current_slot: u64) -> Result<()> {
         let slots_elapsed = current_slot
             .checked_sub(self.last_update_slot)
@@ -300,6 +583,9 @@ impl Reserve {
             return Ok(());
         }
 
+        // The flash principal sits in borrowed wads and must not earn interest.
+        require!(self.flash_borrowed_amount == 0, LendingError::FlashLoanActive);
+
         if self.liquidity_borrowed_amount_wads == 0 {
             msg!("No borrows, skipping accrue_interest");
             return Ok(());
@@ -354,22 +640,23 @@ impl Reserve {
             .checked_sub(self.liquidity_borrowed_amount_wads)
             .ok_or(LendingError::MathOverflow)?;
 
-        let interest_tokens = interest_earned
-            .checked_div(Self::INITIAL_BORROW_RATE)
-            .ok_or(LendingError::MathOverflow)? as u64;
+        let protocol_fee_wads = interest_earned
+            .checked_mul(self.config.protocol_take_rate as u128)
+            .and_then(|v| v.checked_div(100))
+            .ok_or(LendingError::MathOverflow)?;
 
-        self.liquidity_available_amount = self
-            .liquidity_available_amount
-            .checked_add(interest_tokens)
+        self.accumulated_protocol_fees_wads = self.accumulated_protocol_fees_wads
+            .checked_add(protocol_fee_wads)
             .ok_or(LendingError::MathOverflow)?;
 
         self.liquidity_borrowed_amount_wads = new_borrowed_amount;
 
         msg!(
-            "Accrued interest: {} tokens over {} slots (rate: {})",
-            interest_tokens,
+            "Accrued interest: {} wads over {} slots (rate: {}, protocol fee wads: {})",
+            interest_earned,
             capped_slots,
-            current_borrow_rate
+            current_borrow_rate,
+            protocol_fee_wads
         );
 
         Ok(())
@@ -408,6 +695,8 @@ impl Reserve {
             .checked_mul(Self::INITIAL_BORROW_RATE)
             .ok_or(LendingError::MathOverflow)?;
 
+        self.check_borrow_limit(borrow_wads)?;
+
         self.liquidity_borrowed_amount_wads = self.liquidity_borrowed_amount_wads
             .checked_add(borrow_wads)
             .ok_or(LendingError::MathOverflow)?;
@@ -415,6 +704,52 @@ impl Reserve {
         Ok((receive_amount, borrow_fee, host_fee))
     }
 
+    /// Lends liquidity out of the reserve for the duration of a flash loan.
+    ///
+    /// The amount is moved from available to borrowed liquidity so the
+    /// collateral exchange rate is unaffected until it is repaid via
+    /// `flash_repay`. It is also recorded in `flash_borrowed_amount`, which
+    /// blocks interest accrual until then.
+    pub fn flash_borrow(&mut self, liquidity_amount: u64) -> Result<()> {
+        require!(liquidity_amount > 0, LendingError::InvalidAmount);
+
+        require!(self.flash_borrowed_amount == 0, LendingError::FlashLoanActive);
+
+        require!(
+            self.liquidity_available_amount >= liquidity_amount,
+            LendingError::InsufficientLiquidity
+        );
+
+        self.liquidity_available_amount = self.liquidity_available_amount
+            .checked_sub(liquidity_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        let borrow_wads = (liquidity_amount as u128)
+            .checked_mul(Self::INITIAL_BORROW_RATE)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.liquidity_borrowed_amount_wads = self.liquidity_borrowed_amount_wads
+            .checked_add(borrow_wads)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.flash_borrowed_amount = liquidity_amount;
+
+        Ok(())
+    }
+
+    /// Settles the flash loan opened by `flash_borrow`.
+    pub fn flash_repay(&mut self, liquidity_amount: u64) -> Result<()> {
+        require!(
+            self.flash_borrowed_amount == liquidity_amount,
+            LendingError::InvalidFlashRepay
+        );
+
+        self.flash_borrowed_amount = 0;
+        self.repay(liquidity_amount)?;
+
+        Ok(())
+    }
+
     /// Repays liquidity back into the reserve.
     ///
     /// Returns the actual number of tokens settled after WAD rounding.
@@ -444,6 +779,145 @@ impl Reserve {
         Ok(settled_tokens)
     }
 
+    /// Settles `settle_amount_wads` of obligation debt with the
+    /// `received_amount` tokens that reached the liquidity supply.
+    ///
+    /// The protocol's pro-rata share of the settled debt moves from
+    /// `accumulated_protocol_fees_wads` to `claimable_protocol_fees`; only the
+    /// rest of the repayment becomes available liquidity. An open flash loan
+    /// carries no protocol fees, so its principal is left out of the ratio.
+    pub fn settle_repayment(&mut self, received_amount: u64, settle_amount_wads: u128) -> Result<()> {
+        let flash_borrowed_wads = (self.flash_borrowed_amount as u128)
+            .checked_mul(Self::INITIAL_BORROW_RATE)
+            .ok_or(LendingError::MathOverflow)?;
+
+        let debt_wads = self.liquidity_borrowed_amount_wads
+            .checked_sub(flash_borrowed_wads)
+            .ok_or(LendingError::MathOverflow)?;
+
+        let fee_wads = if debt_wads == 0 {
+            0
+        } else {
+            mul_div(settle_amount_wads, self.accumulated_protocol_fees_wads, debt_wads)
+                .ok_or(LendingError::MathOverflow)?
+        };
+
+        let fee_amount = (fee_wads / Self::INITIAL_BORROW_RATE).min(received_amount as u128) as u64;
+
+        self.accumulated_protocol_fees_wads = self.accumulated_protocol_fees_wads
+            .checked_sub(fee_amount as u128 * Self::INITIAL_BORROW_RATE)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.claimable_protocol_fees = self.claimable_protocol_fees
+            .checked_add(fee_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.liquidity_borrowed_amount_wads = self.liquidity_borrowed_amount_wads
+            .checked_sub(settle_amount_wads)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.liquidity_available_amount = self.liquidity_available_amount
+            .checked_add(received_amount - fee_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        Ok(())
+    }
+
+    /// Removes repaid protocol fees from the reserve, given the current
+    /// balance of the liquidity supply.
+    ///
+    /// Capped at the part of `supply_amount` above `liquidity_available_amount`,
+    /// so a claim can never be paid out of depositors' liquidity.
+    ///
+    /// Returns the number of tokens that can be transferred to the fee receiver.
+    pub fn claim_protocol_fees(&mut self, supply_amount: u64) -> Result<u64> {
+        let max_amount = supply_amount.saturating_sub(self.liquidity_available_amount);
+        let claim_amount = self.claimable_protocol_fees.min(max_amount);
+
+        self.claimable_protocol_fees = self.claimable_protocol_fees
+            .checked_sub(claim_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        Ok(claim_amount)
+    }
+
+    /// Writes `bad_debt_wads` of unrecoverable debt off the reserve.
+    ///
+    /// The protocol bears its own share first: the fees accrued on the
+    /// written-off debt are forgiven, and `claimable_protocol_fees`, which are
+    /// already held in the liquidity supply, are released to depositors to
+    /// cover their loss. Whatever remains is socialized: the debt simply leaves
+    /// `liquidity_borrowed_amount_wads`, lowering the collateral exchange rate,
+    /// and is added to `socialized_bad_debt_wads` for the insurance vault to cover.
+    ///
+    /// Returns `(covered_wads, socialized_wads)`.
+    pub fn write_off_bad_debt(&mut self, bad_debt_wads: u128) -> Result<(u128, u128)> {
+        let write_off_wads = bad_debt_wads.min(self.liquidity_borrowed_amount_wads);
+
+        let borrowed_tokens = self.liquidity_borrowed_amount_wads / Self::INITIAL_BORROW_RATE;
+
+        let forgiven_fee_wads = if borrowed_tokens == 0 {
+            self.accumulated_protocol_fees_wads
+        } else {
+            (write_off_wads / Self::INITIAL_BORROW_RATE)
+                .checked_mul(self.accumulated_protocol_fees_wads / borrowed_tokens)
+                .ok_or(LendingError::MathOverflow)?
+        }
+        .min(write_off_wads);
+
+        let depositor_loss_wads = write_off_wads
+            .checked_sub(forgiven_fee_wads)
+            .ok_or(LendingError::MathOverflow)?;
+
+        let covered_amount = (depositor_loss_wads / Self::INITIAL_BORROW_RATE)
+            .min(self.claimable_protocol_fees as u128) as u64;
+
+        self.accumulated_protocol_fees_wads = self.accumulated_protocol_fees_wads
+            .checked_sub(forgiven_fee_wads)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.claimable_protocol_fees = self.claimable_protocol_fees
+            .checked_sub(covered_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.liquidity_available_amount = self.liquidity_available_amount
+            .checked_add(covered_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.liquidity_borrowed_amount_wads = self.liquidity_borrowed_amount_wads
+            .checked_sub(write_off_wads)
+            .ok_or(LendingError::MathOverflow)?;
+
+        let covered_wads = (covered_amount as u128)
+            .checked_mul(Self::INITIAL_BORROW_RATE)
+            .and_then(|v| v.checked_add(forgiven_fee_wads))
+            .ok_or(LendingError::MathOverflow)?;
+
+        let socialized_wads = write_off_wads
+            .checked_sub(covered_wads)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.socialized_bad_debt_wads = self.socialized_bad_debt_wads
+            .checked_add(socialized_wads)
+            .ok_or(LendingError::MathOverflow)?;
+
+        Ok((covered_wads, socialized_wads))
+    }
+
+    /// Records `liquidity_amount` of insurance paid back to depositors against
+    /// `socialized_bad_debt_wads`, failing if it exceeds the uncovered loss.
+    pub fn cover_bad_debt(&mut self, liquidity_amount: u64) -> Result<()> {
+        let cover_wads = (liquidity_amount as u128)
+            .checked_mul(Self::INITIAL_BORROW_RATE)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.socialized_bad_debt_wads = self.socialized_bad_debt_wads
+            .checked_sub(cover_wads)
+            .ok_or(LendingError::InsuranceDrawExceedsBadDebt)?;
+
+        Ok(())
+    }
+
     /// Calculates borrow fee and host fee for a borrow transaction.
     ///
     /// Fees are:
@@ -495,9 +969,7 @@ impl Reserve {
             ::try_from(liquidity_amount)
             .map_err(|_| LendingError::MathOverflow)?;
 
-        let borrowed_liquidity = self.liquidity_borrowed_amount_wads
-            .checked_div(Self::INITIAL_BORROW_RATE)
-            .ok_or(LendingError::MathOverflow)? as u64;
+        let borrowed_liquidity = self.depositor_borrowed_liquidity()? as u64;
 
         let total_liquidity = self.liquidity_available_amount
             .checked_add(borrowed_liquidity)
@@ -507,4 +979,433 @@ impl Reserve {
 
         Ok(liquidity_amount_u64)
     }
+
+    /// Redeems collateral from the reserve and returns the amount of liquidity withdrawn.
+    ///
+    /// Also decreases liquidity_available_amount and collateral_mint_total_supply.
+    pub fn withdraw_liquidity(&mut self, collateral_amount: u64) -> Result<u64> {
+        let liquidity_amount = self.redeem_collateral(collateral_amount)?;
+
+        require!(
+            self.liquidity_available_amount >= liquidity_amount,
+            LendingError::InsufficientLiquidity
+        );
+
+        self.liquidity_available_amount = self.liquidity_available_amount
+            .checked_sub(liquidity_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.collateral_mint_total_supply = self.collateral_mint_total_supply
+            .checked_sub(collateral_amount)
+            .ok_or(LendingError::MathOverflow)?;
+
+        Ok(liquidity_amount)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::states::EModeCategory;
+
+    pub(crate) fn config() -> ReserveConfig {
+        ReserveConfig {
+            optimal_utilization_rate: 80,
+            loan_to_value_ratio: 50,
+            liquidation_bonus: 5,
+            max_liquidation_bonus: 5,
+            liquidation_threshold: 55,
+            liquidation_close_factor: 50,
+            liquidation_dust_value: 0,
+            min_borrow_rate: 0,
+            optimal_borrow_rate: 4,
+            max_borrow_rate: 30,
+            fees: ReserveFees {
+                borrow_fee_wad: 0,
+                flash_loan_fee_wad: 0,
+                host_fee_percentage: 0,
+            },
+            protocol_take_rate: 10,
+            deposit_limit: u64::MAX,
+            borrow_limit: u64::MAX,
+            isolation_mode: false,
+            isolated_debt_ceiling: 0,
+            isolation_borrowable: false,
+            emode_category: 0,
+            oracle_type: OracleType::Pyth,
+            pyth_price_feed_id: [1u8; 32],
+            max_price_age_secs: 60,
+            max_confidence_bps: 500,
+            price_mode: PriceMode::Spot,
+            pegged_price: 0,
+            max_peg_deviation_bps: 0,
+            secondary_oracle_type: OracleType::Pyth,
+            secondary_pyth_price_feed_id: [1u8; 32],
+            max_oracle_divergence_bps: 0,
+            oracle_divergence_fallback: false,
+        }
+    }
+
+    /// 1,000 tokens deposited, `borrowed` of them lent out, and `fees` tokens
+    /// of protocol fees accrued on top and still owed by the borrowers.
+    pub(crate) fn reserve(borrowed: u64, fees: u64) -> Reserve {
+        Reserve {
+            version: Reserve::PROGRAM_VERSION,
+            last_update_slot: 0,
+            lending_market: Pubkey::default(),
+            liquidity_mint: Pubkey::default(),
+            liquidity_mint_decimals: 6,
+            liquidity_supply: Pubkey::default(),
+            liquidity_fee_receiver: Pubkey::default(),
+            liquidity_oracle: Pubkey::default(),
+            secondary_oracle: Pubkey::default(),
+            liquidity_available_amount: 1_000 - borrowed,
+            liquidity_borrowed_amount_wads: (borrowed + fees) as u128 * WAD,
+            liquidity_cumulative_borrow_rate_wads: WAD,
+            liquidity_market_price: 1_000_000,
+            liquidity_ema_price: 1_000_000,
+            liquidity_secondary_market_price: 0,
+            depegged: false,
+            collateral_mint: Pubkey::default(),
+            collateral_supply: Pubkey::default(),
+            collateral_mint_total_supply: 1_000,
+            accumulated_protocol_fees_wads: fees as u128 * WAD,
+            claimable_protocol_fees: 0,
+            paused_operations: 0,
+            isolated_debt: 0,
+            flash_borrowed_amount: 0,
+            socialized_bad_debt_wads: 0,
+            config: config(),
+        }
+    }
+
+    /// A market with e-mode category 1 enabled and a 5 point threshold step.
+    pub(crate) fn market() -> LendingMarket {
+        let mut emode_categories = [EModeCategory::default(); LendingMarket::MAX_EMODE_CATEGORIES];
+        emode_categories[0] = EModeCategory {
+            loan_to_value_ratio: 90,
+            liquidation_threshold: 93,
+        };
+
+        LendingMarket {
+            owner: Pubkey::default(),
+            pending_owner: Pubkey::default(),
+            authority: Pubkey::default(),
+            authority_bump: 0,
+            version: 1,
+            bump_seed: 0,
+            market_id: 0,
+            quote_currency: [0u8; 32],
+            name: [0u8; 32],
+            token_program_id: Pubkey::default(),
+            max_liquidation_threshold_step: 5,
+            guardian: Pubkey::default(),
+            risk_manager: Pubkey::default(),
+            fee_manager: Pubkey::default(),
+            timelock_delay_slots: 0,
+            queued_change_count: 0,
+            paused_operations: 0,
+            insurance_fee_percentage: 0,
+            emode_categories,
+        }
+    }
+
+    /// Accrues half a year of 20% interest on 300 borrowed tokens, half of
+    /// it taken by the protocol.
+    fn accrued_reserve() -> Reserve {
+        let mut reserve = reserve(300, 0);
+        reserve.config.min_borrow_rate = 20;
+        reserve.config.optimal_borrow_rate = 20;
+        reserve.config.protocol_take_rate = 50;
+
+        for step in 1..=(Reserve::SLOTS_PER_YEAR as u64 / Reserve::MAX_ACCRUE_SLOTS / 2) {
+            let slot = step * Reserve::MAX_ACCRUE_SLOTS;
+            reserve.accrue_interest(slot).unwrap();
+            reserve.last_update_slot = slot;
+        }
+
+        reserve
+    }
+
+    #[test]
+    fn accrues_protocol_fees_without_minting_liquidity() {
+        let reserve = accrued_reserve();
+
+        let interest_wads = reserve.liquidity_borrowed_amount_wads - 300 * WAD;
+        assert!(interest_wads > 30 * WAD);
+        assert!(interest_wads / 2 - reserve.accumulated_protocol_fees_wads < WAD);
+        assert_eq!(reserve.liquidity_available_amount, 700);
+
+        // Depositors earn the interest net of the protocol's share.
+        let depositor_liquidity = 700
+            + (reserve.liquidity_borrowed_amount_wads - reserve.accumulated_protocol_fees_wads) / WAD;
+        assert_eq!(
+            reserve.collateral_exchange_rate().unwrap(),
+            depositor_liquidity * WAD / 1_000
+        );
+    }
+
+    #[test]
+    fn claims_fees_once_interest_is_repaid() {
+        let mut reserve = accrued_reserve();
+        let mut supply_amount = reserve.liquidity_available_amount;
+        let fees_wads = reserve.accumulated_protocol_fees_wads;
+
+        assert_eq!(reserve.claim_protocol_fees(supply_amount).unwrap(), 0);
+
+        let borrowed_wads = reserve.liquidity_borrowed_amount_wads;
+        let repay_amount = borrowed_wads.div_ceil(WAD) as u64;
+        reserve.settle_repayment(repay_amount, borrowed_wads).unwrap();
+        supply_amount += repay_amount;
+
+        assert_eq!(reserve.claimable_protocol_fees as u128, fees_wads / WAD);
+        assert!(reserve.accumulated_protocol_fees_wads < WAD);
+
+        let claimed = reserve.claim_protocol_fees(supply_amount).unwrap();
+        supply_amount -= claimed;
+
+        assert_eq!(claimed as u128, fees_wads / WAD);
+        assert_eq!(reserve.claimable_protocol_fees, 0);
+        assert_eq!(reserve.liquidity_available_amount, supply_amount);
+    }
+
+    #[test]
+    fn counts_sub_token_settled_debt_towards_protocol_fees() {
+        // 100 tokens owed, 80 of them protocol fees. The quarter token settled
+        // on top of the first one is what lifts the share to a whole token.
+        let mut reserve = reserve(20, 80);
+
+        reserve.settle_repayment(2, 5 * WAD / 4).unwrap();
+
+        assert_eq!(reserve.claimable_protocol_fees, 1);
+        assert_eq!(reserve.accumulated_protocol_fees_wads, 79 * WAD);
+        assert_eq!(reserve.liquidity_available_amount, 981);
+    }
+
+    #[test]
+    fn leaves_flash_principal_out_of_the_protocol_fee_share() {
+        let mut reserve = reserve(20, 80);
+        reserve.flash_borrow(100).unwrap();
+
+        reserve.settle_repayment(3, 5 * WAD / 2).unwrap();
+
+        assert_eq!(reserve.claimable_protocol_fees, 2);
+        assert_eq!(reserve.accumulated_protocol_fees_wads, 78 * WAD);
+    }
+
+    #[test]
+    fn caps_claim_at_supply_above_available_liquidity() {
+        let mut reserve = accrued_reserve();
+        let mut supply_amount = reserve.liquidity_available_amount;
+
+        let borrowed_wads = reserve.liquidity_borrowed_amount_wads;
+        let repay_amount = borrowed_wads.div_ceil(WAD) as u64;
+        reserve.settle_repayment(repay_amount, borrowed_wads).unwrap();
+        let fee_amount = reserve.claimable_protocol_fees;
+
+        // A transfer fee kept 5 tokens of the repayment from reaching the supply.
+        supply_amount += repay_amount - 5;
+
+        assert_eq!(reserve.claim_protocol_fees(supply_amount).unwrap(), fee_amount - 5);
+        assert_eq!(reserve.claimable_protocol_fees, 5);
+    }
+
+    /// `reserve(500, 0)` after 20 tokens of protocol fees were repaid into
+    /// the supply, returned with the supply's token balance.
+    fn reserve_with_claimable_fees() -> (Reserve, u64) {
+        let mut reserve = reserve(500, 0);
+        reserve.claimable_protocol_fees = 20;
+        let supply_amount = reserve.liquidity_available_amount + reserve.claimable_protocol_fees;
+        (reserve, supply_amount)
+    }
+
+    #[test]
+    fn claimable_protocol_fees_absorb_bad_debt_first() {
+        let (mut reserve, supply_amount) = reserve_with_claimable_fees();
+        let exchange_rate_before = reserve.collateral_exchange_rate().unwrap();
+
+        let (covered_wads, socialized_wads) = reserve.write_off_bad_debt(10 * WAD).unwrap();
+
+        assert_eq!(covered_wads, 10 * WAD);
+        assert_eq!(socialized_wads, 0);
+        assert_eq!(reserve.claimable_protocol_fees, 10);
+        assert_eq!(reserve.liquidity_available_amount, 510);
+        assert!(reserve.liquidity_available_amount <= supply_amount);
+        assert_eq!(reserve.collateral_exchange_rate().unwrap(), exchange_rate_before);
+    }
+
+    #[test]
+    fn socializes_bad_debt_beyond_protocol_fees() {
+        let (mut reserve, supply_amount) = reserve_with_claimable_fees();
+        assert_eq!(reserve.collateral_exchange_rate().unwrap(), WAD);
+
+        let (covered_wads, socialized_wads) = reserve.write_off_bad_debt(120 * WAD).unwrap();
+
+        // The 20 fee tokens cover part of the loss; depositors take the other 100.
+        assert_eq!(covered_wads, 20 * WAD);
+        assert_eq!(socialized_wads, 100 * WAD);
+        assert_eq!(reserve.claimable_protocol_fees, 0);
+        assert_eq!(reserve.liquidity_available_amount, 520);
+        assert_eq!(reserve.liquidity_available_amount, supply_amount);
+        assert_eq!(reserve.liquidity_borrowed_amount_wads, 380 * WAD);
+        assert_eq!(reserve.collateral_exchange_rate().unwrap(), 900_000_000_000_000_000);
+        assert_eq!(reserve.socialized_bad_debt_wads, 100 * WAD);
+    }
+
+    #[test]
+    fn rejects_cover_without_socialized_bad_debt() {
+        let mut reserve = reserve(0, 0);
+
+        assert!(reserve.cover_bad_debt(1).is_err());
+        assert_eq!(reserve.socialized_bad_debt_wads, 0);
+    }
+
+    #[test]
+    fn caps_cover_at_socialized_bad_debt() {
+        let (mut reserve, _) = reserve_with_claimable_fees();
+        reserve.write_off_bad_debt(120 * WAD).unwrap();
+
+        reserve.cover_bad_debt(60).unwrap();
+        assert_eq!(reserve.socialized_bad_debt_wads, 40 * WAD);
+
+        assert!(reserve.cover_bad_debt(41).is_err());
+        assert_eq!(reserve.socialized_bad_debt_wads, 40 * WAD);
+
+        reserve.cover_bad_debt(40).unwrap();
+        assert_eq!(reserve.socialized_bad_debt_wads, 0);
+    }
+
+    #[test]
+    fn forgives_fees_accrued_on_written_off_debt() {
+        // 480 tokens lent out, which have since grown to 500 with 20 owed to
+        // the protocol; nothing repaid yet.
+        let mut reserve = reserve(480, 20);
+        let supply_amount = reserve.liquidity_available_amount;
+
+        let (covered_wads, socialized_wads) = reserve.write_off_bad_debt(500 * WAD).unwrap();
+
+        // Depositors only lose the 480 tokens they lent.
+        assert_eq!(covered_wads, 20 * WAD);
+        assert_eq!(socialized_wads, 480 * WAD);
+        assert_eq!(reserve.accumulated_protocol_fees_wads, 0);
+        assert_eq!(reserve.liquidity_available_amount, supply_amount);
+        assert_eq!(reserve.collateral_exchange_rate().unwrap(), 520_000_000_000_000_000);
+    }
+
+    #[test]
+    fn caps_write_off_at_borrowed_liquidity() {
+        let (mut reserve, supply_amount) = reserve_with_claimable_fees();
+
+        let (covered_wads, socialized_wads) = reserve.write_off_bad_debt(10_000 * WAD).unwrap();
+
+        assert_eq!(covered_wads + socialized_wads, 500 * WAD);
+        assert_eq!(reserve.liquidity_borrowed_amount_wads, 0);
+        assert!(reserve.liquidity_available_amount <= supply_amount);
+    }
+
+    #[test]
+    fn excludes_open_flash_loans_from_the_borrow_limit() {
+        let mut reserve = reserve(300, 0);
+        reserve.config.borrow_limit = 400;
+
+        reserve.flash_borrow(500).unwrap();
+
+        reserve.check_borrow_limit(100 * WAD).unwrap();
+        assert!(reserve.check_borrow_limit(101 * WAD).is_err());
+    }
+
+    #[test]
+    fn redeems_collateral_at_the_exchange_rate() {
+        let mut reserve = reserve(500, 20);
+        let exchange_rate_before = reserve.collateral_exchange_rate().unwrap();
+
+        let liquidity_amount = reserve.withdraw_liquidity(100).unwrap();
+
+        assert_eq!(liquidity_amount, 100);
+        assert_eq!(reserve.liquidity_available_amount, 400);
+        assert_eq!(reserve.collateral_mint_total_supply, 900);
+        assert_eq!(reserve.collateral_exchange_rate().unwrap(), exchange_rate_before);
+    }
+
+    #[test]
+    fn rejects_redemption_beyond_available_liquidity() {
+        let mut reserve = reserve(900, 0);
+
+        assert!(reserve.withdraw_liquidity(101).is_err());
+        assert_eq!(reserve.liquidity_available_amount, 100);
+        assert_eq!(reserve.collateral_mint_total_supply, 1_000);
+    }
+
+    #[test]
+    fn updates_risk_parameters_within_the_threshold_step() {
+        let mut reserve = reserve(0, 0);
+        let mut new_config = config();
+        new_config.loan_to_value_ratio = 45;
+        new_config.liquidation_threshold = 50;
+        new_config.max_borrow_rate = 40;
+
+        let old_config = reserve.update_config(new_config.clone(), &market()).unwrap();
+
+        assert_eq!(old_config, config());
+        assert_eq!(reserve.config, new_config);
+    }
+
+    #[test]
+    fn rejects_threshold_drop_beyond_step() {
+        let mut reserve = reserve(0, 0);
+        let mut new_config = config();
+        new_config.loan_to_value_ratio = 40;
+        new_config.liquidation_threshold = 49;
+
+        assert!(reserve.update_config(new_config, &market()).is_err());
+        assert_eq!(reserve.config, config());
+    }
+
+    #[test]
+    fn rejects_oracle_and_isolation_changes() {
+        let mut reserve = reserve(0, 0);
+        let changes: [fn(&mut ReserveConfig); 5] = [
+            |config| config.oracle_type = OracleType::SwitchboardOnDemand,
+            |config| config.pyth_price_feed_id = [2u8; 32],
+            |config| config.price_mode = PriceMode::Ema,
+            |config| config.isolation_mode = true,
+            |config| config.isolation_borrowable = true,
+        ];
+
+        for change in changes {
+            let mut new_config = config();
+            change(&mut new_config);
+
+            assert!(reserve.update_config(new_config, &market()).is_err());
+            assert_eq!(reserve.config, config());
+        }
+    }
+
+    #[test]
+    fn keeps_collateral_priced_when_switching_to_ema() {
+        // Refresh only stores the EMA for modes that use it.
+        let mut reserve = reserve(0, 0);
+        reserve.liquidity_ema_price = 0;
+
+        for price_mode in [PriceMode::Ema, PriceMode::SpotEmaMinMax] {
+            let mut new_config = config();
+            new_config.price_mode = price_mode;
+
+            assert!(reserve.update_config(new_config, &market()).is_err());
+            assert_eq!(reserve.collateral_price(), 1_000_000);
+        }
+    }
+
+    #[test]
+    fn requires_an_enabled_emode_category() {
+        let mut reserve = reserve(0, 0);
+        let mut new_config = config();
+
+        new_config.emode_category = 2;
+        assert!(reserve.update_config(new_config.clone(), &market()).is_err());
+
+        new_config.emode_category = 1;
+        reserve.update_config(new_config, &market()).unwrap();
+        assert_eq!(reserve.config.emode_category, 1);
+    }
 }
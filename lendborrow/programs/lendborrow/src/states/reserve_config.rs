@@ -4,7 +4,10 @@ use anchor_lang::prelude::*;
 /// Configuration parameters that define how a reserve behaves.
 ///
 /// A `ReserveConfig` represents the full risk, interest-rate, and oracle behavior
-/// for a given Reserve. These values are immutable after initialization and define:
+/// for a given Reserve. These values are set at initialization, can only be changed
+/// by the market owner through `update_reserve_config` (except `oracle_type`,
+/// `pyth_price_feed_id`, `isolation_mode` and `isolation_borrowable`, which are
+/// fixed), and define:
 ///
 /// - **Interest Rate Curve**
 ///   `optimal_utilization_rate`, `min_borrow_rate`, `optimal_borrow_rate`, `max_borrow_rate`
@@ -14,21 +17,111 @@ use anchor_lang::prelude::*;
 ///   `loan_to_value_ratio`, `liquidation_threshold`, and `liquidation_bonus` control
 ///   collateralization and liquidation safety rules.
 ///
+/// - **Liquidation Bonus**
+///   The bonus paid for seizing this reserve as collateral grows linearly from
+///   `liquidation_bonus` for a barely unhealthy obligation to `max_liquidation_bonus`
+///   once its borrowed value reaches its deposited value.
+///
+/// - **Liquidation Close Factor**
+///   `liquidation_close_factor` caps the share (in percent) of an obligation's
+///   borrowed value that one liquidation repaying this reserve may cover. Obligations
+///   whose borrowed value is at most `liquidation_dust_value` (1e6 quote units) can
+///   be closed in full.
+///
 /// - **Protocol Fees**
-///   Stored inside `ReserveFees`, defining borrow fees and fee splits.
+///   Stored inside `ReserveFees`, defining borrow fees and fee splits, plus
+///   `protocol_take_rate`, the share of accrued interest kept by the protocol.
+///
+/// - **Caps**
+///   `deposit_limit` and `borrow_limit` bound total supplied and total borrowed
+///   liquidity, in liquidity token units.
+///
+/// - **Isolation Mode**
+///   An `isolation_mode` reserve can only be used as an obligation's sole collateral,
+///   and such obligations may only borrow from reserves marked `isolation_borrowable`.
+///   `isolated_debt_ceiling` caps the debt (1e6 quote units) backed by this reserve
+///   across all obligations.
+///
+/// - **E-Mode**
+///   `emode_category` tags the reserve with one of the lending market's e-mode
+///   categories (0 for none). Obligations in that category use its higher LTV
+///   and liquidation threshold.
 ///
 /// - **Oracle Configuration**
-///   `pyth_price_feed_id` points to the Pyth price feed used to value this asset.
+///   `oracle_type` selects the price source. For Pyth, `pyth_price_feed_id` points
+///   to the feed used to value this asset; for Switchboard On-Demand the pull feed
+///   account itself is stored as the reserve's `liquidity_oracle`.
+///   `max_price_age_secs` and `max_confidence_bps` bound how old and how uncertain
+///   an accepted price may be. `price_mode` chooses between the spot price, the
+///   oracle EMA, or the more conservative of the two when valuing obligations.
+///   In `Pegged` mode the reserve reports the fixed `pegged_price` (1e6 quote units)
+///   while the live price stays within `max_peg_deviation_bps` of it.
+///
+/// - **Secondary Oracle**
+///   `secondary_oracle_type` and `secondary_pyth_price_feed_id` describe the optional
+///   `Reserve::secondary_oracle`. When the two prices differ by more than
+///   `max_oracle_divergence_bps`, refresh fails with `OracleDivergence`, or, if
+///   `oracle_divergence_fallback` is set, collateral is valued at the lower price and
+///   debt at the higher one.
 pub struct ReserveConfig {
     pub optimal_utilization_rate: u8,
     pub loan_to_value_ratio: u8,
     pub liquidation_bonus: u8,
+    pub max_liquidation_bonus: u8,
     pub liquidation_threshold: u8,
+    pub liquidation_close_factor: u8,
+    pub liquidation_dust_value: u64,
     pub min_borrow_rate: u8,
     pub optimal_borrow_rate: u8,
     pub max_borrow_rate: u8,
     pub fees: ReserveFees,
+    pub protocol_take_rate: u8,
+    pub deposit_limit: u64,
+    pub borrow_limit: u64,
+    pub isolation_mode: bool,
+    pub isolated_debt_ceiling: u64,
+    pub isolation_borrowable: bool,
+    pub emode_category: u8,
+    pub oracle_type: OracleType,
     pub pyth_price_feed_id:[u8; 32],
+    pub max_price_age_secs: u64,
+    pub max_confidence_bps: u16,
+    pub price_mode: PriceMode,
+    pub pegged_price: u64,
+    pub max_peg_deviation_bps: u16,
+    pub secondary_oracle_type: OracleType,
+    pub secondary_pyth_price_feed_id: [u8; 32],
+    pub max_oracle_divergence_bps: u16,
+    pub oracle_divergence_fallback: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+/// Price source used to value a reserve's liquidity.
+pub enum OracleType {
+    /// Pyth pull oracle (`PriceUpdateV2`), selected by `pyth_price_feed_id`.
+    Pyth,
+    /// Switchboard On-Demand pull feed (`PullFeedAccountData`).
+    SwitchboardOnDemand,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+/// Which oracle price is used to value a reserve in obligations.
+pub enum PriceMode {
+    /// Latest spot price.
+    Spot,
+    /// Oracle exponentially-weighted moving average price.
+    Ema,
+    /// Lower of spot and EMA for collateral, higher of the two for debt.
+    SpotEmaMinMax,
+    /// Fixed `pegged_price` while the live spot price stays within the peg band.
+    Pegged,
+}
+
+impl PriceMode {
+    /// Returns `true` if obligations are valued with the oracle EMA.
+    pub fn uses_ema(self) -> bool {
+        matches!(self, PriceMode::Ema | PriceMode::SpotEmaMinMax)
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, InitSpace)]
@@ -42,11 +135,24 @@ pub struct ReserveFees {
 }
 
 impl ReserveConfig {
+    /// Close factor that lets a liquidation repay an obligation's whole debt.
+    pub const FULL_CLOSE_FACTOR: u8 = 100;
+
+    /// Upper bound for `max_price_age_secs`.
+    pub const MAX_PRICE_AGE_SECS: u64 = 3_600;
+
+    /// Upper bound for `max_confidence_bps` (100%).
+    pub const MAX_CONFIDENCE_BPS: u16 = 10_000;
+
+    /// Upper bound for `max_oracle_divergence_bps` (100%).
+    pub const MAX_ORACLE_DIVERGENCE_BPS: u16 = 10_000;
+
     /// Validates the reserve configuration and ensures all parameters fall within
     /// acceptable risk and economic bounds.
     ///
-    /// This must be called during reserve initialization to protect the protocol
-    /// from misconfigured assets that could cause insolvency or poor economic outcomes.
+    /// This must be called during reserve initialization and on every config update
+    /// to protect the protocol from misconfigured assets that could cause insolvency
+    /// or poor economic outcomes.
     pub fn validate(&self) -> Result<()> {
         // Optimal utilization rate must be between 0-100% (e.g., 80 means 80%)
         require!(
@@ -63,6 +169,13 @@ impl ReserveConfig {
         // Liquidation bonus must be between 0-100% (e.g., 5 means liquidator gets 5% discount)
         require!(self.liquidation_bonus <= 100, crate::errors::LendingError::InvalidReserveConfig);
 
+        // Max liquidation bonus must be between the base bonus and 100%
+        require!(
+            self.liquidation_bonus <= self.max_liquidation_bonus
+                && self.max_liquidation_bonus <= 100,
+            crate::errors::LendingError::InvalidReserveConfig
+        );
+
         // Liquidation threshold must be between 0-100% (e.g., 55 means liquidation starts at 55% collateral ratio)
         require!(
             self.liquidation_threshold <= 100,
@@ -75,6 +188,12 @@ impl ReserveConfig {
             crate::errors::LendingError::InvalidReserveConfig
         );
 
+        // Close factor must be between 1-100% (e.g., 50 means half the debt per liquidation)
+        require!(
+            self.liquidation_close_factor > 0 && self.liquidation_close_factor <= 100,
+            crate::errors::LendingError::InvalidReserveConfig
+        );
+
         // Min borrow rate must be less than or equal to optimal rate (interest rate curve starts at min)
         require!(
             self.min_borrow_rate <= self.optimal_borrow_rate,
@@ -93,9 +212,54 @@ impl ReserveConfig {
             crate::errors::LendingError::InvalidReserveConfig
         );
 
-        // Validate that pyth_price_feed_id is not all zeros
+        // Protocol take rate must be between 0-100% (e.g., 10 means protocol keeps 10% of accrued interest)
+        require!(
+            self.protocol_take_rate <= 100,
+            crate::errors::LendingError::InvalidReserveConfig
+        );
+
+        // Pyth reserves need a feed id; it must not be all zeros
+        require!(
+            self.oracle_type != OracleType::Pyth || self.pyth_price_feed_id != [0u8; 32],
+            crate::errors::LendingError::InvalidOracleConfig
+        );
+
+        // Max price age must be between 1 second and 1 hour
+        require!(
+            self.max_price_age_secs > 0 && self.max_price_age_secs <= Self::MAX_PRICE_AGE_SECS,
+            crate::errors::LendingError::InvalidOracleConfig
+        );
+
+        // Max confidence interval must be between 0.01% and 100% of the price (in basis points)
+        require!(
+            self.max_confidence_bps > 0 && self.max_confidence_bps <= Self::MAX_CONFIDENCE_BPS,
+            crate::errors::LendingError::InvalidOracleConfig
+        );
+
+        // Only Pyth publishes an EMA, so other oracles cannot use EMA-based modes
+        require!(
+            !self.price_mode.uses_ema() || self.oracle_type == OracleType::Pyth,
+            crate::errors::LendingError::InvalidOracleConfig
+        );
+
+        // Pegged reserves need a non-zero peg and a peg band between 0.01% and 100%
+        require!(
+            self.price_mode != PriceMode::Pegged
+                || (self.pegged_price > 0
+                    && self.max_peg_deviation_bps > 0
+                    && self.max_peg_deviation_bps <= Self::MAX_ORACLE_DIVERGENCE_BPS),
+            crate::errors::LendingError::InvalidOracleConfig
+        );
+
+        // E-mode category must be 0 (none) or one of the market's categories
+        require!(
+            self.emode_category as usize <= crate::states::LendingMarket::MAX_EMODE_CATEGORIES,
+            crate::errors::LendingError::InvalidEModeCategory
+        );
+
+        // Oracle divergence band must be at most 100% (in basis points)
         require!(
-            self.pyth_price_feed_id != [0u8; 32],
+            self.max_oracle_divergence_bps <= Self::MAX_ORACLE_DIVERGENCE_BPS,
             crate::errors::LendingError::InvalidOracleConfig
         );
 
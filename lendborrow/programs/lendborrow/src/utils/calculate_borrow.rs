@@ -1,5 +1,5 @@
 use crate::errors::LendingError;
-use crate::states::{Obligation, Reserve};
+use crate::states::{LendingMarket, Obligation, Reserve};
 use anchor_lang::prelude::*;
 
 #[derive(Debug)]
@@ -28,7 +28,7 @@ pub fn calculate_borrow(
         .ok_or(LendingError::MathOverflow)?;
 
     let borrow_value = (liquidity_amount as u128)
-        .checked_mul(reserve.liquidity_market_price)
+        .checked_mul(reserve.debt_price())
         .and_then(|v| v.checked_div(decimals))
         .and_then(|v| v.checked_div(decimals))
         .ok_or(LendingError::MathOverflow)?;
@@ -73,6 +73,7 @@ pub fn calculate_borrow(
 
 pub fn refresh_obligation_internal(
     obligation: &mut Obligation,
+    lending_market: &LendingMarket,
     reserve_accounts: &[AccountInfo],
     clock: &Clock,
 ) -> Result<()> {
@@ -87,6 +88,8 @@ pub fn refresh_obligation_internal(
     let mut total_unhealthy_borrow_value: u128 = 0;
     let mut total_borrowed_value: u128 = 0;
 
+    let mut in_emode = obligation.emode_category != 0;
+
     let mut reserve_index = 0;
     const WAD: u128 = 1_000_000_000_000_000_000;
 
@@ -109,6 +112,7 @@ pub fn refresh_obligation_internal(
             deposit_reserve.last_update_slot == clock.slot,
             LendingError::ReserveStale
         );
+        in_emode &= deposit_reserve.config.emode_category == obligation.emode_category;
 
         let liquidity_amount =
             deposit_reserve.collateral_to_liquidity(collateral.deposited_amount)?;
@@ -117,7 +121,7 @@ pub fn refresh_obligation_internal(
             .checked_pow(deposit_reserve.liquidity_mint_decimals as u32)
             .ok_or(LendingError::MathOverflow)?;
 
-        let market_value = (deposit_reserve.liquidity_market_price as u128)
+        let market_value = deposit_reserve.collateral_price()
             .checked_mul(liquidity_amount as u128)
             .and_then(|v| v.checked_div(decimals))
             .and_then(|v| v.checked_div(decimals))
@@ -169,6 +173,7 @@ pub fn refresh_obligation_internal(
             borrow_reserve.last_update_slot == clock.slot,
             LendingError::ReserveStale
         );
+        in_emode &= borrow_reserve.config.emode_category == obligation.emode_category;
 
         liquidity.accrue_interest(borrow_reserve.liquidity_cumulative_borrow_rate_wads)?;
 
@@ -178,7 +183,7 @@ pub fn refresh_obligation_internal(
 
         let market_value = liquidity
             .borrowed_amount_wads
-            .checked_mul(borrow_reserve.liquidity_market_price)
+            .checked_mul(borrow_reserve.debt_price())
             .and_then(|v| v.checked_div(WAD))
             .and_then(|v| v.checked_div(decimals))
             .and_then(|v| v.checked_div(decimals))
@@ -193,6 +198,16 @@ pub fn refresh_obligation_internal(
         obligation.update_liquidity(index, liquidity)?;
     }
 
+    if in_emode {
+        if let Some(category) = lending_market.emode_category(obligation.emode_category) {
+            (total_allowed_borrow_value, total_unhealthy_borrow_value) = category.borrow_values(
+                total_deposited_value,
+                total_allowed_borrow_value,
+                total_unhealthy_borrow_value,
+            )?;
+        }
+    }
+
     obligation.deposited_value = total_deposited_value;
     obligation.borrowed_value = total_borrowed_value;
     obligation.allowed_borrow_value = total_allowed_borrow_value;
@@ -0,0 +1,40 @@
+use crate::errors::LendingError;
+use crate::states::Reserve;
+use anchor_lang::prelude::*;
+
+#[derive(Debug)]
+pub struct CalculateFlashLoanResult {
+    pub flash_loan_fee: u64,
+    pub host_fee: u64,
+    pub owner_fee: u64,
+}
+
+/// Splits the flash loan fee for `liquidity_amount` between the reserve's
+/// `liquidity_fee_receiver` (owner) and an optional host, using the same
+/// `host_fee_percentage` split as `calculate_borrow`.
+pub fn calculate_flash_loan(
+    reserve: &Reserve,
+    liquidity_amount: u64,
+) -> Result<CalculateFlashLoanResult> {
+    const WAD: u128 = 1_000_000_000_000_000_000;
+
+    let flash_loan_fee = (liquidity_amount as u128)
+        .checked_mul(reserve.config.fees.flash_loan_fee_wad as u128)
+        .and_then(|v| v.checked_div(WAD))
+        .ok_or(LendingError::MathOverflow)? as u64;
+
+    let host_fee = (flash_loan_fee as u128)
+        .checked_mul(reserve.config.fees.host_fee_percentage as u128)
+        .and_then(|v| v.checked_div(100))
+        .ok_or(LendingError::MathOverflow)? as u64;
+
+    let owner_fee = flash_loan_fee
+        .checked_sub(host_fee)
+        .ok_or(LendingError::MathOverflow)?;
+
+    Ok(CalculateFlashLoanResult {
+        flash_loan_fee,
+        host_fee,
+        owner_fee,
+    })
+}
@@ -1,5 +1,5 @@
 use crate::errors::LendingError;
-use crate::states::{Obligation, ObligationCollateral, ObligationLiquidity, Reserve};
+use crate::states::{Obligation, ObligationCollateral, ObligationLiquidity, Reserve, ReserveConfig};
 use anchor_lang::prelude::*;
 
 #[derive(Debug)]
@@ -7,6 +7,52 @@ pub struct LiquidationResult {
     pub settle_amount_wads: u128,
     pub repay_amount: u64,
     pub withdraw_collateral: u64,
+    pub close_factor: u8,
+    pub liquidation_bonus: u8,
+}
+
+/// Liquidation bonus (in percent) paid for seizing `withdraw_reserve` collateral.
+///
+/// Scales linearly from the reserve's `liquidation_bonus` when the obligation's
+/// borrowed value just exceeds its unhealthy borrow value, up to
+/// `max_liquidation_bonus` when the borrowed value reaches the deposited value.
+pub fn liquidation_bonus(withdraw_reserve: &Reserve, obligation: &Obligation) -> Result<u8> {
+    let min_bonus = withdraw_reserve.config.liquidation_bonus;
+    let max_bonus = withdraw_reserve.config.max_liquidation_bonus;
+
+    let shortfall = obligation
+        .borrowed_value
+        .saturating_sub(obligation.unhealthy_borrow_value);
+    let range = obligation
+        .deposited_value
+        .saturating_sub(obligation.unhealthy_borrow_value);
+
+    if max_bonus <= min_bonus || shortfall == 0 {
+        return Ok(min_bonus);
+    }
+
+    if shortfall >= range {
+        return Ok(max_bonus);
+    }
+
+    let extra_bonus = ((max_bonus - min_bonus) as u128)
+        .checked_mul(shortfall)
+        .and_then(|v| v.checked_div(range))
+        .ok_or(LendingError::MathOverflow)?;
+
+    Ok(min_bonus + extra_bonus as u8)
+}
+
+/// Close factor that applies when liquidating `obligation` against `repay_reserve`.
+///
+/// Obligations whose borrowed value is at or below the reserve's dust value may
+/// be closed in full, so small underwater positions can be cleaned up.
+pub fn liquidation_close_factor(repay_reserve: &Reserve, obligation: &Obligation) -> u8 {
+    if obligation.borrowed_value <= repay_reserve.config.liquidation_dust_value as u128 {
+        ReserveConfig::FULL_CLOSE_FACTOR
+    } else {
+        repay_reserve.config.liquidation_close_factor
+    }
 }
 
 pub fn calculate_liquidation(
@@ -18,33 +64,58 @@ pub fn calculate_liquidation(
     obligation: &Obligation,
 ) -> Result<LiquidationResult> {
     const WAD: u128 = 1_000_000_000_000_000_000;
-    const LIQUIDATION_CLOSE_FACTOR_PCT: u128 = 50; 
+
+    let close_factor = liquidation_close_factor(repay_reserve, obligation);
 
     let max_liquidation_value = obligation
         .borrowed_value
-        .checked_mul(LIQUIDATION_CLOSE_FACTOR_PCT)
+        .checked_mul(close_factor as u128)
         .and_then(|v| v.checked_div(100))
         .ok_or(LendingError::MathOverflow)?;
 
-    let repay_amount = liquidity_amount.min(
+    let max_repay_amount = if close_factor == ReserveConfig::FULL_CLOSE_FACTOR {
+        // Round up so a full close also settles the fractional remainder.
         liquidity
+            .borrowed_amount_wads
+            .checked_add(WAD - 1)
+            .and_then(|v| v.checked_div(WAD))
+            .ok_or(LendingError::MathOverflow)? as u64
+    } else {
+        let borrowed_amount = liquidity
             .borrowed_amount_wads
             .checked_div(WAD)
-            .ok_or(LendingError::MathOverflow)? as u64,
-    );
+            .ok_or(LendingError::MathOverflow)? as u64;
+
+        // Convert the value cap back into repay tokens at the price debt is valued at.
+        let decimals = 10u128
+            .checked_pow(repay_reserve.liquidity_mint_decimals as u32)
+            .ok_or(LendingError::MathOverflow)?;
+
+        let max_close_amount = max_liquidation_value
+            .checked_mul(decimals)
+            .and_then(|v| v.checked_div(repay_reserve.debt_price()))
+            .ok_or(LendingError::MathOverflow)?
+            .min(u64::MAX as u128) as u64;
+
+        borrowed_amount.min(max_close_amount)
+    };
+
+    let repay_amount = liquidity_amount.min(max_repay_amount);
 
     let settle_amount_wads = (repay_amount as u128)
         .checked_mul(WAD)
         .ok_or(LendingError::MathOverflow)?
         .min(liquidity.borrowed_amount_wads);
 
-    let liquidation_bonus = withdraw_reserve.config.liquidation_bonus as u128;
+    let liquidation_bonus = liquidation_bonus(withdraw_reserve, obligation)?;
     let bonus_rate = 100u128
-        .checked_add(liquidation_bonus)
+        .checked_add(liquidation_bonus as u128)
         .ok_or(LendingError::MathOverflow)?;
 
+    // Debt and collateral are priced the way the obligation was valued on
+    // refresh, matching the close factor cap above.
     let repay_value = (repay_amount as u128)
-        .checked_mul(repay_reserve.liquidity_market_price)
+        .checked_mul(repay_reserve.debt_price())
         .ok_or(LendingError::MathOverflow)?;
 
     let collateral_value_with_bonus = repay_value
@@ -52,9 +123,11 @@ pub fn calculate_liquidation(
         .and_then(|v| v.checked_div(100))
         .ok_or(LendingError::MathOverflow)?;
 
-    let withdraw_collateral = if withdraw_reserve.liquidity_market_price > 0 {
+    let withdraw_price = withdraw_reserve.collateral_price();
+
+    let withdraw_collateral = if withdraw_price > 0 {
         collateral_value_with_bonus
-            .checked_div(withdraw_reserve.liquidity_market_price)
+            .checked_div(withdraw_price)
             .ok_or(LendingError::MathOverflow)? as u64
     } else {
         return Err(LendingError::InvalidOracleConfig.into());
@@ -66,5 +139,243 @@ pub fn calculate_liquidation(
         settle_amount_wads,
         repay_amount,
         withdraw_collateral,
+        close_factor,
+        liquidation_bonus,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::states::obligation::tests::obligation;
+    use crate::states::reserve::tests::reserve;
+    use crate::states::PriceMode;
+
+    const WAD: u128 = 1_000_000_000_000_000_000;
+
+    /// USDC-like repay reserve at $1 and SOL-like withdraw reserve at $100,
+    /// both with 6 decimals.
+    fn reserves() -> (Reserve, Reserve) {
+        let repay_reserve = reserve(0, 0);
+
+        let mut withdraw_reserve = reserve(0, 0);
+        withdraw_reserve.liquidity_market_price = 100_000_000;
+        withdraw_reserve.liquidity_ema_price = 100_000_000;
+
+        (repay_reserve, withdraw_reserve)
+    }
+
+    /// Obligation owing 1,000 USDC against 11 SOL, valued at the reserves' prices.
+    fn position(
+        repay_reserve: &Reserve,
+        withdraw_reserve: &Reserve,
+    ) -> (Obligation, ObligationLiquidity, ObligationCollateral) {
+        let mut liquidity = ObligationLiquidity::new(Pubkey::new_unique());
+        liquidity.borrowed_amount_wads = 1_000_000_000 * WAD;
+        liquidity.market_value = repay_reserve.debt_value(liquidity.borrowed_amount_wads).unwrap();
+
+        let mut collateral = ObligationCollateral::new(Pubkey::new_unique());
+        collateral.deposited_amount = 11_000_000;
+        collateral.market_value = 11 * withdraw_reserve.collateral_price();
+
+        let obligation = Obligation {
+            deposited_value: collateral.market_value,
+            borrowed_value: liquidity.market_value,
+            allowed_borrow_value: collateral.market_value / 2,
+            unhealthy_borrow_value: collateral.market_value * 55 / 100,
+            deposits_len: 1,
+            borrows_len: 1,
+            ..obligation()
+        };
+
+        (obligation, liquidity, collateral)
+    }
+
+    #[test]
+    fn caps_repay_at_close_factor_and_adds_bonus() {
+        let (repay_reserve, withdraw_reserve) = reserves();
+        let (obligation, liquidity, collateral) = position(&repay_reserve, &withdraw_reserve);
+
+        let result = calculate_liquidation(
+            &repay_reserve,
+            &withdraw_reserve,
+            u64::MAX,
+            &liquidity,
+            &collateral,
+            &obligation,
+        )
+        .unwrap();
+
+        // 50% of 1,000 USDC, paid out as $525 of SOL.
+        assert_eq!(result.close_factor, 50);
+        assert_eq!(result.repay_amount, 500_000_000);
+        assert_eq!(result.settle_amount_wads, 500_000_000 * WAD);
+        assert_eq!(result.withdraw_collateral, 5_250_000);
+    }
+
+    #[test]
+    fn values_repay_and_collateral_like_the_obligation() {
+        let (mut repay_reserve, mut withdraw_reserve) = reserves();
+        repay_reserve.config.price_mode = PriceMode::SpotEmaMinMax;
+        repay_reserve.liquidity_ema_price = 1_100_000;
+        withdraw_reserve.config.price_mode = PriceMode::SpotEmaMinMax;
+        withdraw_reserve.liquidity_ema_price = 90_000_000;
+        let (obligation, liquidity, collateral) = position(&repay_reserve, &withdraw_reserve);
+
+        let result = calculate_liquidation(
+            &repay_reserve,
+            &withdraw_reserve,
+            u64::MAX,
+            &liquidity,
+            &collateral,
+            &obligation,
+        )
+        .unwrap();
+
+        // The close factor cap ($550 of debt at $1.10) and the payout agree:
+        // 500 USDC repaid is worth $550, plus 5% is $577.50 of SOL at $90.
+        assert_eq!(result.repay_amount, 500_000_000);
+        assert_eq!(result.withdraw_collateral, 6_416_666);
+    }
+
+    #[test]
+    fn closes_dust_positions_in_full() {
+        let (mut repay_reserve, withdraw_reserve) = reserves();
+        repay_reserve.config.liquidation_dust_value = 1_000_000_000;
+        let (obligation, liquidity, collateral) = position(&repay_reserve, &withdraw_reserve);
+
+        let result = calculate_liquidation(
+            &repay_reserve,
+            &withdraw_reserve,
+            u64::MAX,
+            &liquidity,
+            &collateral,
+            &obligation,
+        )
+        .unwrap();
+
+        assert_eq!(result.close_factor, ReserveConfig::FULL_CLOSE_FACTOR);
+        assert_eq!(result.repay_amount, 1_000_000_000);
+        assert_eq!(result.withdraw_collateral, 10_500_000);
+    }
+
+    #[test]
+    fn caps_seized_collateral_at_the_deposit() {
+        let (repay_reserve, mut withdraw_reserve) = reserves();
+        withdraw_reserve.liquidity_market_price = 50_000_000;
+        withdraw_reserve.liquidity_ema_price = 50_000_000;
+        let (obligation, liquidity, collateral) = position(&repay_reserve, &withdraw_reserve);
+
+        let result = calculate_liquidation(
+            &repay_reserve,
+            &withdraw_reserve,
+            u64::MAX,
+            &liquidity,
+            &collateral,
+            &obligation,
+        )
+        .unwrap();
+
+        // $525 of SOL at $50 would be 10.5 SOL; it is still within 11.
+        assert_eq!(result.withdraw_collateral, 10_500_000);
+
+        let result = calculate_liquidation(
+            &repay_reserve,
+            &withdraw_reserve,
+            u64::MAX,
+            &liquidity,
+            &ObligationCollateral {
+                deposited_amount: 10_000_000,
+                ..collateral
+            },
+            &obligation,
+        )
+        .unwrap();
+
+        assert_eq!(result.withdraw_collateral, 10_000_000);
+    }
+
+    /// Obligation with $1,000 of unhealthy borrow value and $1,100 deposited.
+    fn obligation_at(borrowed_value: u128) -> Obligation {
+        let (repay_reserve, withdraw_reserve) = reserves();
+        let (mut obligation, _, _) = position(&repay_reserve, &withdraw_reserve);
+        obligation.deposited_value = 1_100_000_000;
+        obligation.unhealthy_borrow_value = 1_000_000_000;
+        obligation.borrowed_value = borrowed_value;
+        obligation
+    }
+
+    fn ramped_reserve() -> Reserve {
+        let (_, mut withdraw_reserve) = reserves();
+        withdraw_reserve.config.liquidation_bonus = 5;
+        withdraw_reserve.config.max_liquidation_bonus = 15;
+        withdraw_reserve
+    }
+
+    #[test]
+    fn bonus_starts_at_liquidation_bonus_when_just_unhealthy() {
+        let withdraw_reserve = ramped_reserve();
+
+        assert_eq!(liquidation_bonus(&withdraw_reserve, &obligation_at(1_000_000_000)).unwrap(), 5);
+        assert_eq!(liquidation_bonus(&withdraw_reserve, &obligation_at(1_000_000_001)).unwrap(), 5);
+    }
+
+    #[test]
+    fn bonus_ramps_linearly_with_shortfall() {
+        let withdraw_reserve = ramped_reserve();
+
+        assert_eq!(liquidation_bonus(&withdraw_reserve, &obligation_at(1_050_000_000)).unwrap(), 10);
+        assert_eq!(liquidation_bonus(&withdraw_reserve, &obligation_at(1_099_999_999)).unwrap(), 14);
+    }
+
+    #[test]
+    fn bonus_clamps_at_max_liquidation_bonus() {
+        let withdraw_reserve = ramped_reserve();
+
+        assert_eq!(liquidation_bonus(&withdraw_reserve, &obligation_at(1_100_000_000)).unwrap(), 15);
+        assert_eq!(liquidation_bonus(&withdraw_reserve, &obligation_at(2_000_000_000)).unwrap(), 15);
+    }
+
+    #[test]
+    fn bonus_is_flat_without_a_higher_max() {
+        let (_, withdraw_reserve) = reserves();
+
+        assert_eq!(liquidation_bonus(&withdraw_reserve, &obligation_at(2_000_000_000)).unwrap(), 5);
+    }
+
+    #[test]
+    fn seizes_collateral_at_the_ramped_bonus() {
+        let (repay_reserve, _) = reserves();
+        let withdraw_reserve = ramped_reserve();
+        let (_, liquidity, collateral) = position(&repay_reserve, &withdraw_reserve);
+
+        // Borrowed value at the deposited value: the full 15% bonus applies.
+        let result = calculate_liquidation(
+            &repay_reserve,
+            &withdraw_reserve,
+            100_000_000,
+            &liquidity,
+            &collateral,
+            &obligation_at(1_100_000_000),
+        )
+        .unwrap();
+
+        assert_eq!(result.liquidation_bonus, 15);
+        assert_eq!(result.repay_amount, 100_000_000);
+        assert_eq!(result.withdraw_collateral, 1_150_000);
+
+        // Halfway along the ramp: 10% on the same repayment.
+        let result = calculate_liquidation(
+            &repay_reserve,
+            &withdraw_reserve,
+            100_000_000,
+            &liquidity,
+            &collateral,
+            &obligation_at(1_050_000_000),
+        )
+        .unwrap();
+
+        assert_eq!(result.liquidation_bonus, 10);
+        assert_eq!(result.withdraw_collateral, 1_100_000);
+    }
+}
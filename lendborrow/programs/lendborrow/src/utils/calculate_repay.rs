@@ -9,7 +9,7 @@ pub struct CalculateRepayResult {
 }
 
 pub fn calculate_repay(
-    reserve: &Reserve,
+    _reserve: &Reserve,
     liquidity_amount: u64,
     borrowed_amount_wads: u128,
 ) -> Result<CalculateRepayResult> {
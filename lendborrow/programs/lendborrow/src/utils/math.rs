@@ -0,0 +1,78 @@
+/// Computes `a * b / c`, rounded down, with a 256-bit intermediate product.
+///
+/// Returns `None` if `c` is zero or the quotient does not fit in a `u128`.
+pub fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+
+    let (hi, lo) = full_mul(a, b);
+
+    if hi >= c {
+        return None;
+    }
+
+    // Shift-subtract long division of the 256-bit product by `c`.
+    let mut quotient = 0u128;
+    let mut remainder = hi;
+
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> bit) & 1);
+        quotient <<= 1;
+
+        if carry == 1 || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+
+    Some(quotient)
+}
+
+/// Returns the 256-bit product of `a` and `b` as `(high, low)` halves.
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+
+    let lo = a_lo * b_lo;
+    let (mid, mid_carry) = (a_lo * b_hi).overflowing_add(a_hi * b_lo);
+    let mut hi = a_hi * b_hi + ((mid_carry as u128) << 64) + (mid >> 64);
+
+    let (lo, lo_carry) = lo.overflowing_add(mid << 64);
+    hi += lo_carry as u128;
+
+    (hi, lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAD: u128 = 1_000_000_000_000_000_000;
+
+    #[test]
+    fn matches_u128_arithmetic_when_it_fits() {
+        assert_eq!(mul_div(7, 9, 4), Some(15));
+        assert_eq!(mul_div(u128::MAX, 1, 1), Some(u128::MAX));
+        assert_eq!(mul_div(0, u128::MAX, 3), Some(0));
+    }
+
+    #[test]
+    fn keeps_precision_past_u128_products() {
+        let a = 1_000_000_000_000 * WAD;
+        let b = 800_000_000_000 * WAD;
+        let c = 2_000_000_000_000 * WAD;
+
+        assert_eq!(mul_div(a, b, c), Some(400_000_000_000 * WAD));
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+    }
+
+    #[test]
+    fn rejects_zero_divisor_and_overflowing_quotient() {
+        assert_eq!(mul_div(1, 1, 0), None);
+        assert_eq!(mul_div(u128::MAX, 2, 1), None);
+    }
+}
@@ -1,11 +1,19 @@
 pub mod calculate_borrow;
+pub mod calculate_flash_loan;
 pub mod calculate_liquidation;
 pub mod calculate_repay;
+pub mod math;
 pub mod oracle;
 pub mod refresh_helpers;
+pub mod switchboard;
+pub mod token;
 
 pub use calculate_borrow::*;
+pub use calculate_flash_loan::*;
 pub use calculate_liquidation::*;
 pub use calculate_repay::*;
+pub use math::*;
 pub use oracle::*;
 pub use refresh_helpers::*;
+pub use switchboard::*;
+pub use token::*;
@@ -1,39 +1,115 @@
 use anchor_lang::prelude::*;
 use crate::errors::LendingError;
 use crate::states::LendingMarket;
-use pyth_solana_receiver_sdk::price_update::{get_feed_id_from_hex,PriceUpdateV2};
+
+#[cfg(not(feature = "testing"))]
+use crate::states::{OracleType, ReserveConfig};
+#[cfg(not(feature = "testing"))]
+use pyth_solana_receiver_sdk::price_update::{
+    get_feed_id_from_hex, Price, PriceFeedMessage, PriceUpdateV2,
+};
+#[cfg(not(feature = "testing"))]
+use crate::utils::switchboard::validate_switchboard_price;
+
+/// Prices read from an oracle, normalized to 1e6 quote units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OraclePrice {
+    /// Latest spot price.
+    pub price: u128,
+    /// Exponentially-weighted moving average price, or 0 if the oracle has none.
+    pub ema_price: u128,
+}
+
+/// Reads the current price from `price_account` using the source selected by
+/// `oracle_type`.
+///
+/// `pyth_feed_id` is only used for Pyth; staleness and confidence bounds come
+/// from `config`. Switchboard feeds do not publish an EMA.
+#[cfg(not(feature = "testing"))]
+pub fn get_oracle_price(
+    price_account: &UncheckedAccount,
+    lending_market: &LendingMarket,
+    oracle_type: OracleType,
+    pyth_feed_id: [u8; 32],
+    config: &ReserveConfig,
+) -> Result<OraclePrice> {
+    match oracle_type {
+        OracleType::Pyth => {
+            validate_pyth_price(price_account, lending_market, pyth_feed_id, config)
+        }
+        OracleType::SwitchboardOnDemand => Ok(OraclePrice {
+            price: validate_switchboard_price(price_account, config)?,
+            ema_price: 0,
+        }),
+    }
+}
 
 #[cfg(not(feature = "testing"))]
 pub fn validate_pyth_price(
     pyth_price_account: &UncheckedAccount,
     _lending_market: &LendingMarket,
     feed_hex: [u8; 32],
-) -> Result<u128> {
+    config: &ReserveConfig,
+) -> Result<OraclePrice> {
     let price_data = pyth_price_account.try_borrow_data()?;
     
     let price_update = PriceUpdateV2::try_deserialize(&mut &price_data[..])
         .map_err(|_| LendingError::InvalidOracleConfig)?;
 
     let clock = Clock::get()?;
-    const MAX_STALENESS: u64 = 60;
-    let feed_id = get_feed_id_from_hex(&hex::encode(&feed_hex))?;
+    let feed_id = get_feed_id_from_hex(&hex::encode(feed_hex))?;
     let price = price_update
-        .get_price_no_older_than(&clock, MAX_STALENESS, &feed_id)
+        .get_price_no_older_than(&clock, config.max_price_age_secs, &feed_id)
         .map_err(|_| LendingError::OraclePriceStale)?;
 
-    require!(price.price > 0, LendingError::OraclePriceInvalid);
+    pyth_oracle_price(&price, &price_update.price_message, config)
+}
+
+/// Normalizes a fresh Pyth price, and the EMA published with it when
+/// `config.price_mode` values obligations with the EMA.
+///
+/// The EMA shares the spot price's exponent and has already passed the
+/// staleness check. Other modes report an `ema_price` of 0, as for oracles
+/// without an EMA, so a wide EMA confidence interval cannot block them.
+#[cfg(not(feature = "testing"))]
+fn pyth_oracle_price(
+    price: &Price,
+    message: &PriceFeedMessage,
+    config: &ReserveConfig,
+) -> Result<OraclePrice> {
+    let ema_price = if config.price_mode.uses_ema() {
+        normalize_pyth_price(message.ema_price, message.ema_conf, price.exponent, config)?
+    } else {
+        0
+    };
+
+    Ok(OraclePrice {
+        price: normalize_pyth_price(price.price, price.conf, price.exponent, config)?,
+        ema_price,
+    })
+}
 
-    let confidence_pct = (price.conf as u128)
-        .checked_mul(100)
-        .and_then(|v| v.checked_div(price.price.abs() as u128))
+/// Checks a Pyth price against `max_confidence_bps` and scales it to 1e6 quote units.
+#[cfg(not(feature = "testing"))]
+fn normalize_pyth_price(
+    price: i64,
+    conf: u64,
+    exponent: i32,
+    config: &ReserveConfig,
+) -> Result<u128> {
+    require!(price > 0, LendingError::OraclePriceInvalid);
+
+    let confidence_bps = (conf as u128)
+        .checked_mul(10_000)
+        .and_then(|v| v.checked_div(price.unsigned_abs() as u128))
         .ok_or(LendingError::MathOverflow)?;
     require!(
-        confidence_pct < 5,
+        confidence_bps <= config.max_confidence_bps as u128,
         LendingError::OraclePriceConfidenceTooWide
     );
 
-    let price_abs = price.price.abs() as u128;
-    let exponent_abs = price.exponent.abs() as u32;
+    let price_abs = price.unsigned_abs() as u128;
+    let exponent_abs = exponent.unsigned_abs();
     let pyth_decimals = (10u128)
         .checked_pow(exponent_abs)
         .ok_or(LendingError::MathOverflow)?;
@@ -58,4 +134,69 @@ pub fn validate_quote_currency_compatibility(
     );
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, not(feature = "testing")))]
+mod tests {
+    use super::*;
+    use crate::states::reserve::tests::config;
+    use crate::states::PriceMode;
+
+    fn price() -> Price {
+        Price {
+            price: 150_000_000,
+            conf: 50_000,
+            exponent: -8,
+            publish_time: 0,
+        }
+    }
+
+    /// A message whose EMA confidence is half the EMA price.
+    fn message_with_wide_ema() -> PriceFeedMessage {
+        PriceFeedMessage {
+            feed_id: [1u8; 32],
+            price: 150_000_000,
+            conf: 50_000,
+            exponent: -8,
+            publish_time: 0,
+            prev_publish_time: 0,
+            ema_price: 140_000_000,
+            ema_conf: 70_000_000,
+        }
+    }
+
+    #[test]
+    fn spot_mode_ignores_the_ema() {
+        let price = pyth_oracle_price(&price(), &message_with_wide_ema(), &config()).unwrap();
+
+        assert_eq!(
+            price,
+            OraclePrice {
+                price: 1_500_000,
+                ema_price: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn ema_modes_reject_a_wide_ema_confidence() {
+        for price_mode in [PriceMode::Ema, PriceMode::SpotEmaMinMax] {
+            let mut config = config();
+            config.price_mode = price_mode;
+
+            assert!(pyth_oracle_price(&price(), &message_with_wide_ema(), &config).is_err());
+        }
+    }
+
+    #[test]
+    fn ema_modes_normalize_the_ema() {
+        let mut message = message_with_wide_ema();
+        message.ema_conf = 10_000;
+        let mut config = config();
+        config.price_mode = PriceMode::Ema;
+
+        let price = pyth_oracle_price(&price(), &message, &config).unwrap();
+
+        assert_eq!(price.ema_price, 1_400_000);
+    }
+}
@@ -31,7 +31,7 @@ pub fn refresh_collateral(
         .ok_or(LendingError::MathOverflow)?;
 
     let market_value = deposit_reserve
-        .liquidity_market_price
+        .collateral_price()
         .checked_mul(liquidity_amount as u128)
         .and_then(|v| v.checked_div(decimals))
         .ok_or(LendingError::MathOverflow)?;
@@ -110,7 +110,7 @@ pub fn refresh_liquidity(
 
     let market_value = liquidity
         .borrowed_amount_wads
-        .checked_mul(borrow_reserve.liquidity_market_price)
+        .checked_mul(borrow_reserve.debt_price())
         .and_then(|v| v.checked_div(WAD))
         .and_then(|v| v.checked_div(decimals))
         .ok_or(LendingError::MathOverflow)?;
@@ -157,8 +157,7 @@ pub fn refresh_all_collaterals(
     let mut total_allowed_borrow = 0u128;
     let mut total_unhealthy_threshold = 0u128;
 
-    for index in 0..obligation.deposits_len as usize {
-        let reserve = &deposit_reserves[index];
+    for (index, reserve) in deposit_reserves.iter().enumerate() {
         let result = refresh_collateral(obligation, index, reserve)?;
         
         total_market_value = total_market_value
@@ -196,8 +195,7 @@ pub fn refresh_all_borrows(
 
     let mut total_borrowed_value = 0u128;
 
-    for index in 0..obligation.borrows_len as usize {
-        let reserve = &borrow_reserves[index];
+    for (index, reserve) in borrow_reserves.iter().enumerate() {
         let market_value = refresh_liquidity(obligation, index, reserve)?;
         
         total_borrowed_value = total_borrowed_value
@@ -0,0 +1,219 @@
+use crate::errors::LendingError;
+use crate::states::ReserveConfig;
+use anchor_lang::prelude::*;
+
+/// Switchboard On-Demand program that owns pull feed accounts.
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey =
+    pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+
+/// Anchor discriminator of `PullFeedAccountData`.
+pub const PULL_FEED_DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+
+/// Switchboard results are fixed-point with 18 decimals.
+const SWITCHBOARD_DECIMALS: u128 = 1_000_000_000_000_000_000;
+
+/// Prices are normalized to the same 1e6 scale as `validate_pyth_price`.
+const BASE_DECIMALS: u128 = 1_000_000;
+
+// Byte offsets into `PullFeedAccountData`, including the 8-byte discriminator.
+// The account is `repr(C)`, so these follow the field order of the
+// `switchboard-on-demand` crate: 32 oracle submissions of 64 bytes, feed
+// metadata, then the aggregated `CurrentResult`.
+const LAST_UPDATE_TIMESTAMP_OFFSET: usize = 8 + 2208;
+const RESULT_VALUE_OFFSET: usize = 8 + 2256;
+const RESULT_STD_DEV_OFFSET: usize = 8 + 2272;
+const RESULT_NUM_SAMPLES_OFFSET: usize = 8 + 2352;
+
+/// Minimum account length needed to read every field used here.
+pub const PULL_FEED_MIN_LEN: usize = RESULT_NUM_SAMPLES_OFFSET + 1;
+
+/// Reads and validates the latest result of a Switchboard On-Demand pull feed.
+#[cfg(not(feature = "testing"))]
+pub fn validate_switchboard_price(
+    feed_account: &UncheckedAccount,
+    config: &ReserveConfig,
+) -> Result<u128> {
+    require_keys_eq!(
+        *feed_account.owner,
+        SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+        LendingError::InvalidOracleConfig
+    );
+
+    let feed_data = feed_account.try_borrow_data()?;
+    let clock = Clock::get()?;
+
+    decode_switchboard_price(&feed_data, clock.unix_timestamp, config)
+}
+
+/// Decodes a `PullFeedAccountData` account and returns its price in 1e6 quote units.
+///
+/// The feed is rejected if it was last updated more than `max_price_age_secs`
+/// before `now`, or if its standard deviation exceeds `max_confidence_bps` of
+/// the price.
+#[cfg_attr(feature = "testing", allow(dead_code))]
+pub fn decode_switchboard_price(
+    data: &[u8],
+    now: i64,
+    config: &ReserveConfig,
+) -> Result<u128> {
+    require!(
+        data.len() >= PULL_FEED_MIN_LEN && data[..8] == PULL_FEED_DISCRIMINATOR,
+        LendingError::InvalidOracleConfig
+    );
+
+    let last_update_timestamp = read_i64(data, LAST_UPDATE_TIMESTAMP_OFFSET);
+    let value = read_i128(data, RESULT_VALUE_OFFSET);
+    let std_dev = read_i128(data, RESULT_STD_DEV_OFFSET);
+    let num_samples = data[RESULT_NUM_SAMPLES_OFFSET];
+
+    require!(num_samples > 0, LendingError::OraclePriceInvalid);
+
+    let age = now.saturating_sub(last_update_timestamp).max(0) as u64;
+    require!(
+        age <= config.max_price_age_secs,
+        LendingError::OraclePriceStale
+    );
+
+    require!(value > 0, LendingError::OraclePriceInvalid);
+
+    let price = value as u128;
+
+    let confidence_bps = std_dev
+        .unsigned_abs()
+        .checked_mul(10_000)
+        .and_then(|v| v.checked_div(price))
+        .ok_or(LendingError::MathOverflow)?;
+    require!(
+        confidence_bps <= config.max_confidence_bps as u128,
+        LendingError::OraclePriceConfidenceTooWide
+    );
+
+    let normalized_price = price
+        .checked_mul(BASE_DECIMALS)
+        .and_then(|v| v.checked_div(SWITCHBOARD_DECIMALS))
+        .ok_or(LendingError::MathOverflow)?;
+
+    Ok(normalized_price)
+}
+
+fn read_i64(data: &[u8], offset: usize) -> i64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    i64::from_le_bytes(bytes)
+}
+
+fn read_i128(data: &[u8], offset: usize) -> i128 {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&data[offset..offset + 16]);
+    i128::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::states::OracleType;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn config() -> ReserveConfig {
+        ReserveConfig {
+            oracle_type: OracleType::SwitchboardOnDemand,
+            ..crate::states::reserve::tests::config()
+        }
+    }
+
+    fn feed(value: i128, std_dev: i128, last_update_timestamp: i64) -> Vec<u8> {
+        let mut data = vec![0u8; 8 + 3712];
+        data[..8].copy_from_slice(&PULL_FEED_DISCRIMINATOR);
+        data[LAST_UPDATE_TIMESTAMP_OFFSET..LAST_UPDATE_TIMESTAMP_OFFSET + 8]
+            .copy_from_slice(&last_update_timestamp.to_le_bytes());
+        data[RESULT_VALUE_OFFSET..RESULT_VALUE_OFFSET + 16].copy_from_slice(&value.to_le_bytes());
+        data[RESULT_STD_DEV_OFFSET..RESULT_STD_DEV_OFFSET + 16]
+            .copy_from_slice(&std_dev.to_le_bytes());
+        data[RESULT_NUM_SAMPLES_OFFSET] = 3;
+        data
+    }
+
+    fn wad(units: i128, thousandths: i128) -> i128 {
+        units * 1_000_000_000_000_000_000 + thousandths * 1_000_000_000_000_000
+    }
+
+    #[test]
+    fn normalizes_price_to_base_decimals() {
+        let data = feed(wad(1, 500), wad(0, 1), NOW - 10);
+
+        let price = decode_switchboard_price(&data, NOW, &config()).unwrap();
+
+        assert_eq!(price, 1_500_000);
+    }
+
+    #[test]
+    fn accepts_price_at_max_age() {
+        let data = feed(wad(150, 0), 0, NOW - 60);
+
+        assert_eq!(decode_switchboard_price(&data, NOW, &config()).unwrap(), 150_000_000);
+    }
+
+    #[test]
+    fn rejects_stale_price() {
+        let data = feed(wad(1, 0), 0, NOW - 61);
+
+        assert_eq!(
+            decode_switchboard_price(&data, NOW, &config()).unwrap_err(),
+            LendingError::OraclePriceStale.into()
+        );
+    }
+
+    #[test]
+    fn rejects_wide_confidence() {
+        // 6% standard deviation against a 5% limit
+        let data = feed(wad(1, 0), wad(0, 60), NOW);
+
+        assert_eq!(
+            decode_switchboard_price(&data, NOW, &config()).unwrap_err(),
+            LendingError::OraclePriceConfidenceTooWide.into()
+        );
+    }
+
+    #[test]
+    fn rejects_non_positive_price() {
+        let data = feed(-wad(1, 0), 0, NOW);
+
+        assert_eq!(
+            decode_switchboard_price(&data, NOW, &config()).unwrap_err(),
+            LendingError::OraclePriceInvalid.into()
+        );
+    }
+
+    #[test]
+    fn rejects_feed_without_samples() {
+        let mut data = feed(wad(1, 0), 0, NOW);
+        data[RESULT_NUM_SAMPLES_OFFSET] = 0;
+
+        assert_eq!(
+            decode_switchboard_price(&data, NOW, &config()).unwrap_err(),
+            LendingError::OraclePriceInvalid.into()
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_discriminator() {
+        let mut data = feed(wad(1, 0), 0, NOW);
+        data[0] ^= 0xff;
+
+        assert_eq!(
+            decode_switchboard_price(&data, NOW, &config()).unwrap_err(),
+            LendingError::InvalidOracleConfig.into()
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_account() {
+        let data = feed(wad(1, 0), 0, NOW);
+
+        assert_eq!(
+            decode_switchboard_price(&data[..PULL_FEED_MIN_LEN - 1], NOW, &config()).unwrap_err(),
+            LendingError::InvalidOracleConfig.into()
+        );
+    }
+}
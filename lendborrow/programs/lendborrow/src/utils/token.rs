@@ -0,0 +1,71 @@
+use crate::errors::LendingError;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::spl_token_2022::extension::{
+    transfer_hook::TransferHook, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use anchor_spl::token_interface::{
+    self, spl_token_2022, Mint, TokenAccount, TransferChecked,
+};
+
+/// Token-2022 mint extensions that let a third party move, freeze in place, or
+/// block transfers of tokens held in reserve vaults.
+const DISALLOWED_MINT_EXTENSIONS: [ExtensionType; 2] = [
+    ExtensionType::PermanentDelegate,
+    ExtensionType::NonTransferable,
+];
+
+/// Rejects Token-2022 liquidity mints whose extensions are unsafe for a reserve.
+///
+/// Transfer hooks are only allowed when no hook program is set, since vault
+/// transfers do not forward the hook's extra accounts.
+pub fn validate_mint_extensions(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+
+    for extension in mint_state.get_extension_types()? {
+        require!(
+            !DISALLOWED_MINT_EXTENSIONS.contains(&extension),
+            LendingError::UnsupportedMintExtension
+        );
+    }
+
+    if let Ok(transfer_hook) = mint_state.get_extension::<TransferHook>() {
+        require!(
+            Option::<Pubkey>::from(transfer_hook.program_id).is_none(),
+            LendingError::UnsupportedMintExtension
+        );
+    }
+
+    Ok(())
+}
+
+/// Transfers `amount` into `destination` with `transfer_checked` and returns how
+/// much it actually received.
+///
+/// For Token-2022 mints with a transfer fee the received amount is lower than
+/// `amount`; reserves must only credit what landed in their vaults.
+pub fn transfer_checked_received<'info>(
+    cpi_ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    destination: &mut InterfaceAccount<'info, TokenAccount>,
+    amount: u64,
+    decimals: u8,
+) -> Result<u64> {
+    let balance_before = destination.amount;
+
+    token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+
+    destination.reload()?;
+
+    let received = destination
+        .amount
+        .checked_sub(balance_before)
+        .ok_or(LendingError::MathOverflow)?;
+
+    Ok(received)
+}
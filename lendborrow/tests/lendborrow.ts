@@ -7,11 +7,25 @@ import {
   getOrCreateAssociatedTokenAccount,
   mintTo,
   TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
+  getMintLen,
+  createInitializeMintInstruction,
+  createInitializePermanentDelegateInstruction,
+  createInitializeTransferFeeConfigInstruction,
   getAssociatedTokenAddress,
   createAssociatedTokenAccountInstruction,
   transfer
 } from "@solana/spl-token";
-import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+  sendAndConfirmTransaction,
+} from "@solana/web3.js";
 import { assert } from "chai";
 
 
@@ -42,6 +56,12 @@ describe("Lending Protocol", () => {
     return Array.from(buffer);
   }
 
+  function createMarketName(name: string): number[] {
+    const buffer = Buffer.alloc(32);
+    buffer.write(name);
+    return Array.from(buffer);
+  }
+
   it("Setup: Create test accounts and fund them", async () => {
     console.log("\n  Setting up test environment...");
 
@@ -72,7 +92,11 @@ describe("Lending Protocol", () => {
 
     [lendingMarketPDA, lendingMarketBump] =
       PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -94,7 +118,11 @@ describe("Lending Protocol", () => {
       await confirmTx(airdropSig);
 
       const [testMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), testOwner.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          testOwner.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -102,7 +130,7 @@ describe("Lending Protocol", () => {
 
       try {
         await program.methods
-          .initLendingMarket(invalidCurrency)
+          .initLendingMarket(0, invalidCurrency, createMarketName("Main"))
           .accounts({
             owner: testOwner.publicKey,
             //@ts-ignore
@@ -135,7 +163,11 @@ describe("Lending Protocol", () => {
       await confirmTx(airdropSig);
 
       const [testMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), testOwner.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          testOwner.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -144,7 +176,7 @@ describe("Lending Protocol", () => {
 
       try {
         await program.methods
-          .initLendingMarket(invalidCurrency)
+          .initLendingMarket(0, invalidCurrency, createMarketName("Main"))
           .accounts({
             owner: testOwner.publicKey,
             //@ts-ignore
@@ -172,7 +204,7 @@ describe("Lending Protocol", () => {
       const quoteCurrency = createQuoteCurrency("USD");
 
       const tx = await program.methods
-        .initLendingMarket(quoteCurrency)
+        .initLendingMarket(0, quoteCurrency, createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -221,7 +253,7 @@ describe("Lending Protocol", () => {
 
       try {
         await program.methods
-          .initLendingMarket(quoteCurrency)
+          .initLendingMarket(0, quoteCurrency, createMarketName("Main"))
           .accounts({
             owner: admin.publicKey,
             //@ts-ignore
@@ -258,14 +290,18 @@ describe("Lending Protocol", () => {
         await confirmTx(airdropSig);
 
         const [newMarketPDA] = PublicKey.findProgramAddressSync(
-          [Buffer.from("lending-market"), newOwner.publicKey.toBuffer()],
+          [
+            Buffer.from("lending-market"),
+            newOwner.publicKey.toBuffer(),
+            new BN(0).toArrayLike(Buffer, "le", 2),
+          ],
           program.programId
         );
 
         const quoteCurrency = createQuoteCurrency(currency);
 
         const tx = await program.methods
-          .initLendingMarket(quoteCurrency)
+          .initLendingMarket(0, quoteCurrency, createMarketName("Main"))
           .accounts({
             owner: newOwner.publicKey,
             //@ts-ignore
@@ -287,6 +323,76 @@ describe("Lending Protocol", () => {
         console.log(` ${currency} market initialized`);
       }
     });
+
+    it("Should initialize a second market for the same owner", async () => {
+      console.log("\n Testing second market with a new market id...");
+
+      const [experimentalMarketPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(1).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      const tx = await program.methods
+        .initLendingMarket(1, createQuoteCurrency("USD"), createMarketName("Experimental"))
+        .accounts({
+          owner: admin.publicKey,
+          //@ts-ignore
+          lendingMarket: experimentalMarketPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      await confirmTx(tx);
+
+      const market = await program.account.lendingMarket.fetch(
+        experimentalMarketPDA
+      );
+      const name = Buffer.from(market.name).toString().replace(/\0/g, "");
+
+      assert.equal(market.marketId, 1);
+      assert.equal(name, "Experimental");
+      assert.equal(market.owner.toBase58(), admin.publicKey.toBase58());
+      assert.notEqual(experimentalMarketPDA.toBase58(), lendingMarketPDA.toBase58());
+      console.log(" Second market initialized");
+    });
+
+    it("Should reject an empty market name", async () => {
+      console.log("\n Testing empty market name...");
+
+      const [emptyNameMarketPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(2).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .initLendingMarket(2, createQuoteCurrency("USD"), new Array(32).fill(0))
+          .accounts({
+            owner: admin.publicKey,
+            //@ts-ignore
+            lendingMarket: emptyNameMarketPDA,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([admin])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (err: any) {
+        assert.include(err.toString(), "InvalidMarketName");
+        console.log(" Correctly rejected empty name");
+      }
+    });
   });
 
   describe("Security & Edge Cases", () => {
@@ -305,7 +411,7 @@ describe("Lending Protocol", () => {
 
       try {
         await program.methods
-          .initLendingMarket(quoteCurrency)
+          .initLendingMarket(0, quoteCurrency, createMarketName("Main"))
           .accounts({
             owner: unauthorizedUser.publicKey,
             //@ts-ignore
@@ -340,7 +446,11 @@ describe("Lending Protocol", () => {
       await confirmTx(airdropSig);
 
       const [newMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), newOwner.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          newOwner.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -348,7 +458,7 @@ describe("Lending Protocol", () => {
       const quoteCurrency = createQuoteCurrency(longCurrency);
 
       const tx = await program.methods
-        .initLendingMarket(quoteCurrency)
+        .initLendingMarket(0, quoteCurrency, createMarketName("Main"))
         .accounts({
           owner: newOwner.publicKey,
           //@ts-ignore
@@ -381,7 +491,11 @@ describe("Lending Protocol", () => {
       await confirmTx(airdropSig);
 
       const [newMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), newOwner.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          newOwner.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -391,7 +505,7 @@ describe("Lending Protocol", () => {
       const quoteCurrency = Array.from(usdcMint.toBuffer());
 
       const tx = await program.methods
-        .initLendingMarket(quoteCurrency)
+        .initLendingMarket(0, quoteCurrency, createMarketName("Main"))
         .accounts({
           owner: newOwner.publicKey,
           //@ts-ignore
@@ -468,11 +582,23 @@ describe("Lending Protocol", () => {
         8 +
         32 +
         32 +
+        32 +
         1 +
         8 +
         1 +
+        2 +
+        32 +
+        32 +
+        32 +
+        1 +
+        32 +
         32 +
-        32;
+        32 +
+        8 +
+        8 +
+        1 +
+        1 +
+        16;
 
       assert.equal(
         accountInfo!.data.length,
@@ -538,14 +664,18 @@ describe("Lending Protocol", () => {
         await confirmTx(airdropSig);
 
         const [newMarketPDA] = PublicKey.findProgramAddressSync(
-          [Buffer.from("lending-market"), newOwner.publicKey.toBuffer()],
+          [
+            Buffer.from("lending-market"),
+            newOwner.publicKey.toBuffer(),
+            new BN(0).toArrayLike(Buffer, "le", 2),
+          ],
           program.programId
         );
 
         const quoteCurrency = createQuoteCurrency(`CURR${i}`);
 
         const tx = await program.methods
-          .initLendingMarket(quoteCurrency)
+          .initLendingMarket(0, quoteCurrency, createMarketName("Main"))
           .accounts({
             owner: newOwner.publicKey,
             //@ts-ignore
@@ -572,8 +702,36 @@ describe("Lending Protocol", () => {
   describe("Set Lending Market Owner", () => {
     let newOwner: Keypair;
 
-    it("Should set new lending market owner", async () => {
-      console.log("\n Testing ownership transfer...");
+    async function proposeOwner(owner: Keypair, proposed: PublicKey) {
+      const tx = await program.methods
+        .proposeLendingMarketOwner(proposed)
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          //@ts-ignore
+          owner: owner.publicKey,
+        })
+        .signers([owner])
+        .rpc();
+      await confirmTx(tx);
+      return tx;
+    }
+
+    async function acceptOwner(pendingOwner: Keypair) {
+      const tx = await program.methods
+        .acceptLendingMarketOwner()
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          //@ts-ignore
+          pendingOwner: pendingOwner.publicKey,
+        })
+        .signers([pendingOwner])
+        .rpc();
+      await confirmTx(tx);
+      return tx;
+    }
+
+    it("Should propose a new lending market owner", async () => {
+      console.log("\n Testing ownership proposal...");
 
       newOwner = Keypair.generate();
 
@@ -584,19 +742,50 @@ describe("Lending Protocol", () => {
       await confirmTx(airdropSig);
 
       console.log("Current owner:", admin.publicKey.toBase58());
-      console.log("New owner:", newOwner.publicKey.toBase58());
+      console.log("Proposed owner:", newOwner.publicKey.toBase58());
 
-      const tx = await program.methods
-        .setLendingMarketOwner(newOwner.publicKey)
-        .accounts({
-          lendingMarket: lendingMarketPDA,
-          //@ts-ignore
-          owner: admin.publicKey,
-        })
-        .signers([admin])
-        .rpc();
+      const tx = await proposeOwner(admin, newOwner.publicKey);
+      console.log(" Tx:", tx);
 
-      await confirmTx(tx);
+      const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+
+      assert.equal(
+        market.owner.toBase58(),
+        admin.publicKey.toBase58(),
+        "Owner should not change before acceptance"
+      );
+      assert.equal(
+        market.pendingOwner.toBase58(),
+        newOwner.publicKey.toBase58(),
+        "Pending owner should be stored"
+      );
+
+      console.log("   Pending owner stored");
+    });
+
+    it("Should reject acceptance from a key that was not proposed", async () => {
+      console.log("\n Testing acceptance by wrong key...");
+
+      const impostor = Keypair.generate();
+      const airdropSig = await connection.requestAirdrop(
+        impostor.publicKey,
+        2 * LAMPORTS_PER_SOL
+      );
+      await confirmTx(airdropSig);
+
+      try {
+        await acceptOwner(impostor);
+        assert.fail("Should have thrown error");
+      } catch (err: any) {
+        assert.include(err.toString(), "InvalidPendingOwner");
+        console.log(" Correctly rejected wrong key");
+      }
+    });
+
+    it("Should transfer ownership once the pending owner accepts", async () => {
+      console.log("\n Testing ownership acceptance...");
+
+      const tx = await acceptOwner(newOwner);
       console.log(" Tx:", tx);
 
       const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
@@ -606,14 +795,19 @@ describe("Lending Protocol", () => {
         newOwner.publicKey.toBase58(),
         "Owner should be updated"
       );
+      assert.equal(
+        market.pendingOwner.toBase58(),
+        PublicKey.default.toBase58(),
+        "Pending owner should be cleared"
+      );
 
       console.log("   Owner successfully changed");
       console.log("   Old owner:", admin.publicKey.toBase58());
       console.log("   New owner:", market.owner.toBase58());
     });
 
-    it("Should reject transfer from non-owner", async () => {
-      console.log("\n Testing unauthorized transfer...");
+    it("Should reject proposal from non-owner", async () => {
+      console.log("\n Testing unauthorized proposal...");
 
       const unauthorizedUser = Keypair.generate();
       const airdropSig = await connection.requestAirdrop(
@@ -625,48 +819,22 @@ describe("Lending Protocol", () => {
       const anotherOwner = Keypair.generate();
 
       try {
-        await program.methods
-          .setLendingMarketOwner(anotherOwner.publicKey)
-          .accounts({
-            lendingMarket: lendingMarketPDA,
-            //@ts-ignore
-            owner: unauthorizedUser.publicKey,
-          })
-          .signers([unauthorizedUser])
-          .rpc();
-
+        await proposeOwner(unauthorizedUser, anotherOwner.publicKey);
         assert.fail("Should have thrown error");
       } catch (err: any) {
-        const errMsg = err.toString();
-        assert.isTrue(
-          errMsg.includes("InvalidOwner") || errMsg.includes("6001"),
-          "Should fail with InvalidOwner error"
-        );
-        console.log(" Correctly rejected unauthorized transfer");
+        assert.include(err.toString(), "InvalidOwner");
+        console.log(" Correctly rejected unauthorized proposal");
       }
     });
 
-    it("Should reject setting same owner", async () => {
+    it("Should reject proposing same owner", async () => {
       console.log("\n Testing same owner rejection...");
 
       try {
-        await program.methods
-          .setLendingMarketOwner(newOwner.publicKey)
-          .accounts({
-            lendingMarket: lendingMarketPDA,
-            //@ts-ignore
-            owner: newOwner.publicKey,
-          })
-          .signers([newOwner])
-          .rpc();
-
+        await proposeOwner(newOwner, newOwner.publicKey);
         assert.fail("Should have thrown error");
       } catch (err: any) {
-        const errMsg = err.toString();
-        assert.isTrue(
-          errMsg.includes("SameOwner") || errMsg.includes("6004"),
-          "Should fail with SameOwner error"
-        );
+        assert.include(err.toString(), "SameOwner");
         console.log(" Correctly rejected same owner");
       }
     });
@@ -674,11 +842,51 @@ describe("Lending Protocol", () => {
     it("Should reject default pubkey as new owner", async () => {
       console.log("\n Testing default pubkey rejection...");
 
-      const defaultPubkey = PublicKey.default;
+      try {
+        await proposeOwner(newOwner, PublicKey.default);
+        assert.fail("Should have thrown error");
+      } catch (err: any) {
+        assert.include(err.toString(), "InvalidNewOwner");
+        console.log(" Correctly rejected default pubkey");
+      }
+    });
+
+    it("Should cancel a pending proposal", async () => {
+      console.log("\n Testing proposal cancellation...");
+
+      const mistyped = Keypair.generate();
+      await proposeOwner(newOwner, mistyped.publicKey);
+
+      const tx = await program.methods
+        .cancelLendingMarketOwner()
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          //@ts-ignore
+          owner: newOwner.publicKey,
+        })
+        .signers([newOwner])
+        .rpc();
+      await confirmTx(tx);
+
+      const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.equal(market.owner.toBase58(), newOwner.publicKey.toBase58());
+      assert.equal(market.pendingOwner.toBase58(), PublicKey.default.toBase58());
+
+      try {
+        await acceptOwner(mistyped);
+        assert.fail("Should have thrown error");
+      } catch (err: any) {
+        assert.include(err.toString(), "InvalidPendingOwner");
+        console.log(" Cancelled proposal can no longer be accepted");
+      }
+    });
+
+    it("Should reject cancel without a pending proposal", async () => {
+      console.log("\n Testing cancel with nothing pending...");
 
       try {
         await program.methods
-          .setLendingMarketOwner(defaultPubkey)
+          .cancelLendingMarketOwner()
           .accounts({
             lendingMarket: lendingMarketPDA,
             //@ts-ignore
@@ -686,15 +894,10 @@ describe("Lending Protocol", () => {
           })
           .signers([newOwner])
           .rpc();
-
         assert.fail("Should have thrown error");
       } catch (err: any) {
-        const errMsg = err.toString();
-        assert.isTrue(
-          errMsg.includes("InvalidNewOwner") || errMsg.includes("6005"),
-          "Should fail with InvalidNewOwner error"
-        );
-        console.log(" Correctly rejected default pubkey");
+        assert.include(err.toString(), "NoPendingOwner");
+        console.log(" Correctly rejected cancel");
       }
     });
 
@@ -716,31 +919,15 @@ describe("Lending Protocol", () => {
       );
       await confirmTx(sig3);
 
-      const tx1 = await program.methods
-        .setLendingMarketOwner(owner2.publicKey)
-        .accounts({
-          lendingMarket: lendingMarketPDA,
-          //@ts-ignore
-          owner: newOwner.publicKey,
-        })
-        .signers([newOwner])
-        .rpc();
-      await confirmTx(tx1);
+      await proposeOwner(newOwner, owner2.publicKey);
+      await acceptOwner(owner2);
 
       let market = await program.account.lendingMarket.fetch(lendingMarketPDA);
       assert.equal(market.owner.toBase58(), owner2.publicKey.toBase58());
       console.log(" Transfer 1: newOwner -> owner2");
 
-      const tx2 = await program.methods
-        .setLendingMarketOwner(owner3.publicKey)
-        .accounts({
-          lendingMarket: lendingMarketPDA,
-          //@ts-ignore
-          owner: owner2.publicKey,
-        })
-        .signers([owner2])
-        .rpc();
-      await confirmTx(tx2);
+      await proposeOwner(owner2, owner3.publicKey);
+      await acceptOwner(owner3);
 
       market = await program.account.lendingMarket.fetch(lendingMarketPDA);
       assert.equal(market.owner.toBase58(), owner3.publicKey.toBase58());
@@ -755,23 +942,10 @@ describe("Lending Protocol", () => {
       const newUser = Keypair.generate();
 
       try {
-        await program.methods
-          .setLendingMarketOwner(newUser.publicKey)
-          .accounts({
-            lendingMarket: lendingMarketPDA,
-            //@ts-ignore
-            owner: admin.publicKey,
-          })
-          .signers([admin])
-          .rpc();
-
+        await proposeOwner(admin, newUser.publicKey);
         assert.fail("Should have thrown error");
       } catch (err: any) {
-        const errMsg = err.toString();
-        assert.isTrue(
-          errMsg.includes("InvalidOwner") || errMsg.includes("6001"),
-          "Should fail because admin is no longer owner"
-        );
+        assert.include(err.toString(), "InvalidOwner");
         console.log(" Old owner correctly lost access");
       }
     });
@@ -898,14 +1072,18 @@ describe("Lending Protocol", () => {
 
       console.log(" Initializing lending market...");
       [lendingMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
       const quoteCurrency = createQuoteCurrency("USD");
 
       await program.methods
-        .initLendingMarket(quoteCurrency)
+        .initLendingMarket(0, quoteCurrency, createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -963,7 +1141,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 50,
         liquidationBonus: 5,
+        maxLiquidationBonus: 5,
         liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 4,
         maxBorrowRate: 30,
@@ -972,7 +1153,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: createPythFeedId('USDC'),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       console.log(" Sending config:", config);
@@ -1121,13 +1319,17 @@ describe("Lending Protocol", () => {
 
       console.log(" Initializing lending market...");
       [lendingMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
       const quoteCurrency = createQuoteCurrency("USD");
       await program.methods
-        .initLendingMarket(quoteCurrency)
+        .initLendingMarket(0, quoteCurrency, createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -1145,6 +1347,7 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           user1.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
@@ -1154,6 +1357,7 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           user2.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
@@ -1164,7 +1368,7 @@ describe("Lending Protocol", () => {
     it("Should initialize obligation successfully", async () => {
 
       const tx = await program.methods
-        .initObligation()
+        .initObligation(0)
         .accounts({
           //@ts-ignore
           obligation: obligation1PDA,
@@ -1197,7 +1401,7 @@ describe("Lending Protocol", () => {
 
       try {
         await program.methods
-          .initObligation()
+          .initObligation(0)
           .accounts({
             //@ts-ignore
             obligation: obligation1PDA,
@@ -1219,7 +1423,7 @@ describe("Lending Protocol", () => {
     it("Should allow multiple users to create obligations", async () => {
 
       const tx = await program.methods
-        .initObligation()
+        .initObligation(0)
         .accounts({
           //@ts-ignore
           obligation: obligation2PDA,
@@ -1239,6 +1443,41 @@ describe("Lending Protocol", () => {
       console.log(" User2 obligation created");
     });
 
+    it("Should allow one owner to hold obligations with different ids", async () => {
+
+      const [subAccountPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("obligation"),
+          lendingMarketPDA.toBuffer(),
+          user1.publicKey.toBuffer(),
+          new BN(1).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      const tx = await program.methods
+        .initObligation(1)
+        .accounts({
+          //@ts-ignore
+          obligation: subAccountPDA,
+          lendingMarket: lendingMarketPDA,
+          owner: user1.publicKey,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([user1])
+        .rpc();
+
+      await confirmTx(tx);
+
+      const obligation = await program.account.obligation.fetch(subAccountPDA);
+      assert.equal(obligation.owner.toBase58(), user1.publicKey.toBase58());
+      assert.equal(obligation.id, 1);
+      assert.notEqual(subAccountPDA.toBase58(), obligation1PDA.toBase58());
+
+      console.log(" User1 sub-account obligation created");
+    });
+
     it("Should verify PDA derivation", async () => {
 
       const [derivedPDA] = PublicKey.findProgramAddressSync(
@@ -1246,6 +1485,7 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           user1.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
@@ -1394,13 +1634,17 @@ describe("Lending Protocol", () => {
 
       console.log(" Initializing lending market...");
       [lendingMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
       const quoteCurrency = createQuoteCurrency("USD");
       await program.methods
-        .initLendingMarket(quoteCurrency)
+        .initLendingMarket(0, quoteCurrency, createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -1419,12 +1663,13 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           user1.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
 
       await program.methods
-        .initObligation()
+        .initObligation(0)
         .accounts({
           //@ts-ignore
           obligation: obligation1PDA,
@@ -1441,12 +1686,13 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           user2.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
 
       await program.methods
-        .initObligation()
+        .initObligation(0)
         .accounts({
           //@ts-ignore
           obligation: obligation2PDA,
@@ -1641,6 +1887,7 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           user1.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
@@ -1737,7 +1984,7 @@ describe("Lending Protocol", () => {
 
       try {
         await program.methods
-          .initObligation()
+          .initObligation(0)
           .accounts({
             //@ts-ignore
             obligation: obligation1PDA,
@@ -1859,7 +2106,11 @@ describe("Lending Protocol", () => {
 
       console.log(" Initializing lending market...");
       [lendingMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -1869,7 +2120,7 @@ describe("Lending Protocol", () => {
       );
 
       await program.methods
-        .initLendingMarket(createQuoteCurrency("USD"))
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -1910,7 +2161,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 50,
         liquidationBonus: 5,
+        maxLiquidationBonus: 5,
         liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 4,
         maxBorrowRate: 30,
@@ -1919,7 +2173,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: createPythFeedId(),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       const adminCollateralAddress = await getAssociatedTokenAddress(
@@ -1964,12 +2235,13 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           user.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
 
       await program.methods
-        .initObligation()
+        .initObligation(0)
         .accounts({
           //@ts-ignore
           obligation: obligationPDA,
@@ -2255,23 +2527,111 @@ describe("Lending Protocol", () => {
       console.log(" Multiple deposits work!");
     });
 
-    it("Should verify obligation state", async () => {
-      console.log("\n Test: Verify obligation state");
-
-      const obligation = await program.account.obligation.fetch(obligationPDA);
+    it("Should deposit liquidity straight into obligation collateral", async () => {
+      const depositAmount = new BN(100 * 1e6);
 
-      console.log("   Obligation State:");
-      console.log("   Owner:", obligation.owner.toBase58());
-      console.log("   Market:", obligation.lendingMarket.toBase58());
-      console.log("   Deposits length:", obligation.depositsLen);
-      console.log("   Borrows length:", obligation.borrowsLen);
-      console.log("   Deposited value:", obligation.depositedValue.toString());
-      console.log("   Data flat length:", obligation.dataFlat.length);
+      const obligationBefore = await program.account.obligation.fetch(obligationPDA);
+      const supplyBefore = await getAccount(connection, collateralSupplyPDA);
+      const userCollateralBefore = await getAccount(connection, userCollateralAccount);
 
-      assert.equal(
-        obligation.owner.toBase58(),
-        user.publicKey.toBase58(),
-        "Owner should match"
+      const refreshReserveIx = await program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMock.publicKey,
+        })
+        .instruction();
+
+      const depositIx = await program.methods
+        .depositReserveLiquidityAndObligationCollateral(depositAmount)
+        .accounts({
+          sourceLiquidity: adminUsdcAccount,
+          reserve: reservePDA,
+          reserveLiquiditySupply: liquiditySupplyPDA,
+          //@ts-ignore
+          liquidityMint: usdcMint,
+          collateralMint: collateralMintPDA,
+          collateralSupply: collateralSupplyPDA,
+          obligation: obligationPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          obligationOwner: user.publicKey,
+          userTransferAuthority: admin.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .instruction();
+
+      const tx = new anchor.web3.Transaction();
+      tx.add(refreshReserveIx);
+      tx.add(depositIx);
+
+      await provider.sendAndConfirm(tx, [user, admin]);
+
+      const obligationAfter = await program.account.obligation.fetch(obligationPDA);
+      const supplyAfter = await getAccount(connection, collateralSupplyPDA);
+      const userCollateralAfter = await getAccount(connection, userCollateralAccount);
+
+      assert.equal(obligationAfter.depositsLen, obligationBefore.depositsLen);
+      assert.isAbove(Number(supplyAfter.amount), Number(supplyBefore.amount));
+      assert.equal(
+        Number(userCollateralAfter.amount),
+        Number(userCollateralBefore.amount),
+        "Collateral should not pass through the user's account"
+      );
+    });
+
+    it("Should fail: deposit liquidity as collateral without a same-slot refresh", async () => {
+      const reserve = await program.account.reserve.fetch(reservePDA);
+      while ((await connection.getSlot()) <= reserve.lastUpdateSlot.toNumber()) {
+        await new Promise((resolve) => setTimeout(resolve, 200));
+      }
+
+      try {
+        await program.methods
+          .depositReserveLiquidityAndObligationCollateral(new BN(100 * 1e6))
+          .accounts({
+            sourceLiquidity: adminUsdcAccount,
+            reserve: reservePDA,
+            reserveLiquiditySupply: liquiditySupplyPDA,
+            //@ts-ignore
+            liquidityMint: usdcMint,
+            collateralMint: collateralMintPDA,
+            collateralSupply: collateralSupplyPDA,
+            obligation: obligationPDA,
+            lendingMarket: lendingMarketPDA,
+            lendingMarketAuthority: lendingMarketAuthorityPDA,
+            obligationOwner: user.publicKey,
+            userTransferAuthority: admin.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user, admin])
+          .rpc();
+
+        assert.fail("Should have failed with ReserveStale");
+      } catch (error: any) {
+        assert.include(error.toString(), "ReserveStale");
+      }
+    });
+
+    it("Should verify obligation state", async () => {
+      console.log("\n Test: Verify obligation state");
+
+      const obligation = await program.account.obligation.fetch(obligationPDA);
+
+      console.log("   Obligation State:");
+      console.log("   Owner:", obligation.owner.toBase58());
+      console.log("   Market:", obligation.lendingMarket.toBase58());
+      console.log("   Deposits length:", obligation.depositsLen);
+      console.log("   Borrows length:", obligation.borrowsLen);
+      console.log("   Deposited value:", obligation.depositedValue.toString());
+      console.log("   Data flat length:", obligation.dataFlat.length);
+
+      assert.equal(
+        obligation.owner.toBase58(),
+        user.publicKey.toBase58(),
+        "Owner should match"
       );
       assert.equal(
         obligation.lendingMarket.toBase58(),
@@ -2460,7 +2820,11 @@ describe("Lending Protocol", () => {
 
       console.log("Initializing lending market...");
       [lendingMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -2470,7 +2834,7 @@ describe("Lending Protocol", () => {
       );
 
       await program.methods
-        .initLendingMarket(createQuoteCurrency("USD"))
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -2511,7 +2875,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 50,
         liquidationBonus: 5,
+        maxLiquidationBonus: 5,
         liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 4,
         maxBorrowRate: 30,
@@ -2520,7 +2887,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: createPythFeedId('USDC'),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       const adminCollateralAddress = await getAssociatedTokenAddress(
@@ -2562,12 +2946,13 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           user.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
 
       await program.methods
-        .initObligation()
+        .initObligation(0)
         .accounts({
           //@ts-ignore
           obligation: obligationPDA,
@@ -2734,6 +3119,142 @@ describe("Lending Protocol", () => {
       }
     });
 
+    it("Should reject zero amount when withdrawing and redeeming", async () => {
+      const refreshReserveIx = await program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMock.publicKey,
+        })
+        .instruction();
+
+      try {
+        await program.methods
+          .withdrawObligationCollateralAndRedeemReserveCollateral(new BN(0))
+          .accounts({
+            sourceCollateral: collateralSupplyPDA,
+            withdrawReserve: reservePDA,
+            collateralMint: collateralMintPDA,
+            reserveLiquiditySupply: liquiditySupplyPDA,
+            liquidityMint: usdcMint,
+            destinationLiquidity: adminUsdcAccount,
+            //@ts-ignore
+            obligation: obligationPDA,
+            lendingMarket: lendingMarketPDA,
+            lendingMarketAuthority: lendingMarketAuthorityPDA,
+            obligationOwner: user.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .preInstructions([refreshReserveIx])
+          .signers([user])
+          .rpc();
+
+        assert.fail("Should have failed with InvalidAmount");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidAmount");
+      }
+    });
+
+    it("Should withdraw collateral and redeem it to liquidity", async () => {
+      const withdrawAmount = new BN(100 * 1e6);
+
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+      const supplyBefore = await getAccount(connection, collateralSupplyPDA);
+      const userCollateralBefore = await getAccount(connection, userCollateralAccount);
+      const liquidityBefore = await getAccount(connection, adminUsdcAccount);
+
+      const refreshReserveIx = await program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMock.publicKey,
+        })
+        .instruction();
+
+      await program.methods
+        .withdrawObligationCollateralAndRedeemReserveCollateral(withdrawAmount)
+        .accounts({
+          sourceCollateral: collateralSupplyPDA,
+          withdrawReserve: reservePDA,
+          collateralMint: collateralMintPDA,
+          reserveLiquiditySupply: liquiditySupplyPDA,
+          liquidityMint: usdcMint,
+          destinationLiquidity: adminUsdcAccount,
+          //@ts-ignore
+          obligation: obligationPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          obligationOwner: user.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .preInstructions([refreshReserveIx])
+        .signers([user])
+        .rpc();
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      const supplyAfter = await getAccount(connection, collateralSupplyPDA);
+      const userCollateralAfter = await getAccount(connection, userCollateralAccount);
+      const liquidityAfter = await getAccount(connection, adminUsdcAccount);
+
+      const liquidityReceived = Number(liquidityAfter.amount) - Number(liquidityBefore.amount);
+
+      assert.isAtLeast(liquidityReceived, 100 * 1e6);
+      assert.equal(
+        Number(supplyBefore.amount) - Number(supplyAfter.amount),
+        100 * 1e6,
+        "Collateral should be burned from the reserve's supply"
+      );
+      assert.equal(
+        Number(userCollateralAfter.amount),
+        Number(userCollateralBefore.amount),
+        "Collateral should not pass through the user's account"
+      );
+      assert.equal(
+        reserveBefore.collateralMintTotalSupply.sub(reserveAfter.collateralMintTotalSupply).toNumber(),
+        100 * 1e6
+      );
+      assert.equal(
+        reserveBefore.liquidityAvailableAmount.sub(reserveAfter.liquidityAvailableAmount).toNumber(),
+        liquidityReceived
+      );
+    });
+
+    it("Should fail: withdraw and redeem against a reserve not refreshed this slot", async () => {
+      const reserve = await program.account.reserve.fetch(reservePDA);
+      while ((await connection.getSlot()) <= reserve.lastUpdateSlot.toNumber()) {
+        await new Promise((resolve) => setTimeout(resolve, 200));
+      }
+
+      try {
+        await program.methods
+          .withdrawObligationCollateralAndRedeemReserveCollateral(new BN(100 * 1e6))
+          .accounts({
+            sourceCollateral: collateralSupplyPDA,
+            withdrawReserve: reservePDA,
+            collateralMint: collateralMintPDA,
+            reserveLiquiditySupply: liquiditySupplyPDA,
+            liquidityMint: usdcMint,
+            destinationLiquidity: adminUsdcAccount,
+            //@ts-ignore
+            obligation: obligationPDA,
+            lendingMarket: lendingMarketPDA,
+            lendingMarketAuthority: lendingMarketAuthorityPDA,
+            obligationOwner: user.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user])
+          .rpc();
+
+        assert.fail("Should have failed with ReserveStale");
+      } catch (error: any) {
+        assert.include(error.toString(), "ReserveStale");
+      }
+    });
+
     it("Should reject non-owner withdrawal", async () => {
       console.log("\n Testing non-owner withdrawal...");
 
@@ -3142,7 +3663,11 @@ describe("Lending Protocol", () => {
 
       console.log("Initializing lending market...");
       [lendingMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -3152,7 +3677,7 @@ describe("Lending Protocol", () => {
       );
 
       await program.methods
-        .initLendingMarket(createQuoteCurrency("USD"))
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -3193,7 +3718,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 80,
         liquidationBonus: 5,
+        maxLiquidationBonus: 5,
         liquidationThreshold: 85,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 4,
         maxBorrowRate: 30,
@@ -3202,7 +3730,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: createPythFeedId('USDC'),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       const adminUsdcCollateralAddress = await getAssociatedTokenAddress(
@@ -3268,7 +3813,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 50,
         liquidationBonus: 10,
+        maxLiquidationBonus: 10,
         liquidationThreshold: 65,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 8,
         maxBorrowRate: 50,
@@ -3277,7 +3825,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: createPythFeedId('SOL'),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       const adminSolCollateralAddress = await getAssociatedTokenAddress(
@@ -3319,12 +3884,13 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           user.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
 
       await program.methods
-        .initObligation()
+        .initObligation(0)
         .accounts({
           //@ts-ignore
           obligation: obligationPDA,
@@ -3913,7 +4479,11 @@ describe("Lending Protocol", () => {
       );
 
       [lendingMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -3926,7 +4496,7 @@ describe("Lending Protocol", () => {
       quoteCurrency.write("USD");
 
       await program.methods
-        .initLendingMarket(Array.from(quoteCurrency))
+        .initLendingMarket(0, Array.from(quoteCurrency), createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -3966,7 +4536,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 80,
         liquidationBonus: 5,
+        maxLiquidationBonus: 5,
         liquidationThreshold: 85,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 4,
         maxBorrowRate: 30,
@@ -3975,7 +4548,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: Array(32).fill(1),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       const adminUsdcCollateralAddress = await getAssociatedTokenAddress(
@@ -4038,7 +4628,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 50,
         liquidationBonus: 10,
+        maxLiquidationBonus: 10,
         liquidationThreshold: 65,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 8,
         maxBorrowRate: 50,
@@ -4047,7 +4640,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: Array(32).fill(2),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       const adminSolCollateralAddress = await getAssociatedTokenAddress(
@@ -4086,12 +4696,13 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           user.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
 
       await program.methods
-        .initObligation()
+        .initObligation(0)
         .accounts({
           //@ts-ignore
           obligation: obligationPDA,
@@ -4399,6 +5010,66 @@ describe("Lending Protocol", () => {
       assert.equal(obligationAfter.borrowsLen, 0, "Should have 0 borrows");
 
     });
+
+    it("Should claim the protocol share of repaid interest", async () => {
+      const reserveBefore = await program.account.reserve.fetch(usdcReservePDA);
+      const feeReceiverBefore = await getAccount(connection, usdcLiquidityFeeReceiverPDA);
+      const claimable = reserveBefore.claimableProtocolFees.toNumber();
+
+      console.log("   Claimable protocol fees:", claimable);
+
+      const refreshUsdcIx = await program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: usdcReservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMockUsdc.publicKey,
+        })
+        .instruction();
+
+      const claim = program.methods
+        .claimProtocolFees()
+        .accounts({
+          reserve: usdcReservePDA,
+          liquidityMint: usdcMint,
+          //@ts-ignore
+          reserveLiquiditySupply: usdcLiquiditySupplyPDA,
+          reserveLiquidityFeeReceiver: usdcLiquidityFeeReceiverPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .preInstructions([refreshUsdcIx]);
+
+      if (claimable === 0) {
+        try {
+          await claim.rpc();
+          assert.fail("Should have failed with NoProtocolFeesToClaim");
+        } catch (error: any) {
+          assert.include(error.toString(), "NoProtocolFeesToClaim");
+        }
+        return;
+      }
+
+      await claim.rpc();
+
+      const reserveAfter = await program.account.reserve.fetch(usdcReservePDA);
+      const feeReceiverAfter = await getAccount(connection, usdcLiquidityFeeReceiverPDA);
+      const supplyAfter = await getAccount(connection, usdcLiquiditySupplyPDA);
+
+      assert.equal(
+        Number(feeReceiverAfter.amount) - Number(feeReceiverBefore.amount),
+        claimable
+      );
+      assert.equal(reserveAfter.claimableProtocolFees.toNumber(), 0);
+      assert.isAtLeast(
+        Number(supplyAfter.amount),
+        reserveAfter.liquidityAvailableAmount.toNumber(),
+        "Depositors' liquidity should stay in the supply"
+      );
+    });
+
     it("Should fail: repay zero amount", async () => {
       console.log("\nTesting repay zero amount (should fail)...");
 
@@ -4570,7 +5241,11 @@ describe("Lending Protocol", () => {
       );
 
       [lendingMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -4583,7 +5258,7 @@ describe("Lending Protocol", () => {
       quoteCurrency.write("USD");
 
       await program.methods
-        .initLendingMarket(Array.from(quoteCurrency))
+        .initLendingMarket(0, Array.from(quoteCurrency), createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -4623,7 +5298,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 80,
         liquidationBonus: 10,
+        maxLiquidationBonus: 10,
         liquidationThreshold: 85,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 4,
         maxBorrowRate: 30,
@@ -4632,7 +5310,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: Array(32).fill(1),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       const adminUsdcCollateralAddress = await getAssociatedTokenAddress(
@@ -4695,7 +5390,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 50,
         liquidationBonus: 10,
+        maxLiquidationBonus: 10,
         liquidationThreshold: 65,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 8,
         maxBorrowRate: 50,
@@ -4704,7 +5402,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: Array(32).fill(2),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       const adminSolCollateralAddress = await getAssociatedTokenAddress(
@@ -4743,12 +5458,13 @@ describe("Lending Protocol", () => {
           Buffer.from("obligation"),
           lendingMarketPDA.toBuffer(),
           borrower.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
         ],
         program.programId
       );
 
       await program.methods
-        .initObligation()
+        .initObligation(0)
         .accounts({
           //@ts-ignore
           obligation: obligationPDA,
@@ -4964,6 +5680,7 @@ describe("Lending Protocol", () => {
           lendingMarketAuthority: lendingMarketAuthorityPDA,
           userTransferAuthority: liquidator.publicKey,
           tokenProgram: TOKEN_PROGRAM_ID,
+          withdrawTokenProgram: TOKEN_PROGRAM_ID,
         })
         .instruction();
 
@@ -5156,6 +5873,7 @@ describe("Lending Protocol", () => {
           lendingMarketAuthority: lendingMarketAuthorityPDA,
           userTransferAuthority: liquidator.publicKey,
           tokenProgram: TOKEN_PROGRAM_ID,
+          withdrawTokenProgram: TOKEN_PROGRAM_ID,
         })
         .instruction();
 
@@ -5183,35 +5901,234 @@ describe("Lending Protocol", () => {
       console.log("Liquidation successful");
     });
 
-    it("Should fail: liquidate zero amount", async () => {
-      console.log("\n Testing liquidation with zero amount (should fail)...");
+    it("Should fail: liquidate twice in one transaction without a refresh", async () => {
+      const refreshIxs = async () => {
+        const obligation = await program.account.obligation.fetch(obligationPDA);
+        const remainingAccounts = [
+          ...Array(obligation.depositsLen).fill(solReservePDA),
+          ...Array(obligation.borrowsLen).fill(usdcReservePDA),
+        ].map((pubkey) => ({ pubkey, isWritable: false, isSigner: false }));
+
+        return [
+          await program.methods
+            .refreshReserve()
+            .accounts({
+              reserve: usdcReservePDA,
+              //@ts-ignore
+              lendingMarket: lendingMarketPDA,
+              pythPrice: pythPriceMockUsdc.publicKey,
+            })
+            .instruction(),
+          await program.methods
+            .refreshReserve()
+            .accounts({
+              reserve: solReservePDA,
+              //@ts-ignore
+              lendingMarket: lendingMarketPDA,
+              pythPrice: pythPriceMockSol.publicKey,
+            })
+            .instruction(),
+          await program.methods
+            .refreshObligation()
+            .accounts({
+              obligation: obligationPDA,
+            })
+            .remainingAccounts(remainingAccounts)
+            .instruction(),
+        ];
+      };
 
-      try {
-        await program.methods
-          .liquidateObligation(new BN(0))
-          .accounts({
-            sourceLiquidity: liquidatorUsdcAccount,
-            destinationCollateral: liquidatorSolCollateralAccount,
-            repayReserve: usdcReservePDA,
-            destinationLiquidity: usdcLiquiditySupplyPDA,
-            withdrawReserve: solReservePDA,
-            withdrawReserveCollateralSupply: solCollateralSupplyPDA,
-            //@ts-ignore
-            obligation: obligationPDA,
-            lendingMarket: lendingMarketPDA,
-            lendingMarketAuthority: lendingMarketAuthorityPDA,
-            userTransferAuthority: liquidator.publicKey,
-            tokenProgram: TOKEN_PROGRAM_ID,
-          })
-          .signers([liquidator])
-          .rpc();
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(...(await refreshIxs())));
 
-        assert.fail("Should have failed with InvalidAmount");
-      } catch (error: any) {
+      const obligation = await program.account.obligation.fetch(obligationPDA);
+      if (obligation.borrowedValue.lte(obligation.unhealthyBorrowValue)) {
+        console.log("Skipping - position cannot be made unhealthy with current setup");
+        return;
+      }
+
+      const liquidateIx = await program.methods
+        .liquidateObligation(new BN(1 * 1e6))
+        .accounts({
+          sourceLiquidity: liquidatorUsdcAccount,
+          destinationCollateral: liquidatorSolCollateralAccount,
+          repayReserve: usdcReservePDA,
+          destinationLiquidity: usdcLiquiditySupplyPDA,
+          withdrawReserve: solReservePDA,
+          withdrawReserveCollateralSupply: solCollateralSupplyPDA,
+          //@ts-ignore
+          obligation: obligationPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          userTransferAuthority: liquidator.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          withdrawTokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .instruction();
+
+      const liquidatorBalanceBefore = await getAccount(connection, liquidatorUsdcAccount);
+
+      // The first liquidation leaves the obligation stale, so the close factor
+      // cannot be applied a second time on the same pre-liquidation values.
+      const tx = new anchor.web3.Transaction()
+        .add(...(await refreshIxs()))
+        .add(liquidateIx)
+        .add(liquidateIx);
+
+      try {
+        await provider.sendAndConfirm(tx, [liquidator]);
+        assert.fail("Should have failed with ObligationStale");
+      } catch (error: any) {
+        assert.include(error.toString(), "ObligationStale");
+      }
+
+      const liquidatorBalanceAfter = await getAccount(connection, liquidatorUsdcAccount);
+      assert.equal(liquidatorBalanceAfter.amount, liquidatorBalanceBefore.amount);
+    });
+
+    it("Should fail: liquidate zero amount", async () => {
+      console.log("\n Testing liquidation with zero amount (should fail)...");
+
+      try {
+        await program.methods
+          .liquidateObligation(new BN(0))
+          .accounts({
+            sourceLiquidity: liquidatorUsdcAccount,
+            destinationCollateral: liquidatorSolCollateralAccount,
+            repayReserve: usdcReservePDA,
+            destinationLiquidity: usdcLiquiditySupplyPDA,
+            withdrawReserve: solReservePDA,
+            withdrawReserveCollateralSupply: solCollateralSupplyPDA,
+            //@ts-ignore
+            obligation: obligationPDA,
+            lendingMarket: lendingMarketPDA,
+            lendingMarketAuthority: lendingMarketAuthorityPDA,
+            userTransferAuthority: liquidator.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            withdrawTokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([liquidator])
+          .rpc();
+
+        assert.fail("Should have failed with InvalidAmount");
+      } catch (error: any) {
         console.log("Correctly failed");
         assert.exists(error);
       }
     });
+
+    it("Should fail: liquidate and redeem zero amount", async () => {
+      const liquidatorSolAccount = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          liquidator,
+          solMint,
+          liquidator.publicKey
+        )
+      ).address;
+
+      try {
+        await program.methods
+          .liquidateObligationAndRedeemReserveCollateral(new BN(0))
+          .accounts({
+            sourceLiquidity: liquidatorUsdcAccount,
+            destinationCollateral: liquidatorSolCollateralAccount,
+            repayReserve: usdcReservePDA,
+            destinationLiquidity: usdcLiquiditySupplyPDA,
+            withdrawReserve: solReservePDA,
+            withdrawReserveCollateralSupply: solCollateralSupplyPDA,
+            withdrawReserveLiquiditySupply: solLiquiditySupplyPDA,
+            withdrawLiquidityMint: solMint,
+            destinationWithdrawLiquidity: liquidatorSolAccount,
+            //@ts-ignore
+            obligation: obligationPDA,
+            lendingMarket: lendingMarketPDA,
+            lendingMarketAuthority: lendingMarketAuthorityPDA,
+            userTransferAuthority: liquidator.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            withdrawTokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([liquidator])
+          .rpc();
+
+        assert.fail("Should have failed with InvalidAmount");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidAmount");
+      }
+    });
+
+    it("Should fail: socialize debt of an obligation that still has collateral", async () => {
+      const refreshUsdcIx = await program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: usdcReservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMockUsdc.publicKey,
+        })
+        .instruction();
+
+      const refreshSolIx = await program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: solReservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMockSol.publicKey,
+        })
+        .instruction();
+
+      const obligation = await program.account.obligation.fetch(obligationPDA);
+      assert.isAbove(obligation.depositsLen, 0);
+
+      const remainingAccounts: any[] = [];
+
+      for (let i = 0; i < obligation.depositsLen; i++) {
+        remainingAccounts.push({
+          pubkey: solReservePDA,
+          isWritable: false,
+          isSigner: false,
+        });
+      }
+
+      for (let i = 0; i < obligation.borrowsLen; i++) {
+        remainingAccounts.push({
+          pubkey: usdcReservePDA,
+          isWritable: false,
+          isSigner: false,
+        });
+      }
+
+      const refreshObligationIx = await program.methods
+        .refreshObligation()
+        .accounts({
+          obligation: obligationPDA,
+        })
+        .remainingAccounts(remainingAccounts)
+        .instruction();
+
+      const socializeIx = await program.methods
+        .socializeBadDebt()
+        .accounts({
+          //@ts-ignore
+          obligation: obligationPDA,
+          reserve: usdcReservePDA,
+          lendingMarket: lendingMarketPDA,
+        })
+        .instruction();
+
+      const tx = new anchor.web3.Transaction();
+      tx.add(refreshUsdcIx);
+      tx.add(refreshSolIx);
+      tx.add(refreshObligationIx);
+      tx.add(socializeIx);
+
+      try {
+        await provider.sendAndConfirm(tx, []);
+        assert.fail("Should have failed with ObligationHasCollateral");
+      } catch (error: any) {
+        assert.include(error.toString(), "ObligationHasCollateral");
+      }
+    });
   });
   describe("Deposit Reserve Liquidity", () => {
     anchor.setProvider(anchor.AnchorProvider.env());
@@ -5369,7 +6286,11 @@ describe("Lending Protocol", () => {
 
       console.log("Initializing lending market...");
       [lendingMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -5379,7 +6300,7 @@ describe("Lending Protocol", () => {
       );
 
       await program.methods
-        .initLendingMarket(createQuoteCurrency("USD"))
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -5420,7 +6341,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 50,
         liquidationBonus: 5,
+        maxLiquidationBonus: 5,
         liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 4,
         maxBorrowRate: 30,
@@ -5429,7 +6353,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: createPythFeedId(),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       const adminCollateralAddress = await getAssociatedTokenAddress(
@@ -5766,7 +6707,11 @@ describe("Lending Protocol", () => {
       );
 
       [lendingMarketPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("lending-market"), admin.publicKey.toBuffer()],
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
         program.programId
       );
 
@@ -5779,7 +6724,7 @@ describe("Lending Protocol", () => {
       quoteCurrency.write("USD");
 
       await program.methods
-        .initLendingMarket(Array.from(quoteCurrency))
+        .initLendingMarket(0, Array.from(quoteCurrency), createMarketName("Main"))
         .accounts({
           owner: admin.publicKey,
           //@ts-ignore
@@ -5809,7 +6754,10 @@ describe("Lending Protocol", () => {
         optimalUtilizationRate: 80,
         loanToValueRatio: 50,
         liquidationBonus: 5,
+        maxLiquidationBonus: 5,
         liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
         minBorrowRate: 0,
         optimalBorrowRate: 4,
         maxBorrowRate: 30,
@@ -5818,7 +6766,24 @@ describe("Lending Protocol", () => {
           flashLoanFeeWad: new BN("9000000000000000"),
           hostFeePercentage: 20,
         },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
         pythPriceFeedId: Array(32).fill(1),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
       };
 
       const adminCollateralAddress = await getAssociatedTokenAddress(
@@ -6073,4 +7038,2995 @@ describe("Lending Protocol", () => {
     });
   });
 
-});
+  describe("Flash Loans", () => {
+    anchor.setProvider(anchor.AnchorProvider.env());
+
+    const program = anchor.workspace.lendborrow as Program<Lendborrow>;
+    const provider = anchor.getProvider();
+    const connection = provider.connection;
+
+    let admin: Keypair;
+    let user1: Keypair;
+    let usdcMint: PublicKey;
+    let adminUsdcAccount: PublicKey;
+    let user1UsdcAccount: PublicKey;
+    let lendingMarketPDA: PublicKey;
+    let lendingMarketAuthorityPDA: PublicKey;
+    let reservePDA: PublicKey;
+    let liquiditySupplyPDA: PublicKey;
+    let liquidityFeeReceiverPDA: PublicKey;
+    let collateralMintPDA: PublicKey;
+    let collateralSupplyPDA: PublicKey;
+    let pythPriceMock: Keypair;
+
+    async function confirmTx(signature: string) {
+      const latestBlockhash = await connection.getLatestBlockhash();
+      await connection.confirmTransaction({
+        signature,
+        ...latestBlockhash,
+      });
+      return signature;
+    }
+
+    function createQuoteCurrency(currency: string): number[] {
+      const buffer = Buffer.alloc(32);
+      buffer.write(currency);
+      return Array.from(buffer);
+    }
+
+    function refreshReserveIx() {
+      return program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMock.publicKey,
+        })
+        .instruction();
+    }
+
+    function flashBorrowIx(amount: BN) {
+      return program.methods
+        .flashBorrowReserveLiquidity(amount)
+        .accounts({
+          sourceLiquidity: liquiditySupplyPDA,
+          destinationLiquidity: user1UsdcAccount,
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .instruction();
+    }
+
+    function flashRepayIx(amount: BN, borrowInstructionIndex: number) {
+      return program.methods
+        .flashRepayReserveLiquidity(amount, borrowInstructionIndex)
+        .accounts({
+          sourceLiquidity: user1UsdcAccount,
+          destinationLiquidity: liquiditySupplyPDA,
+          reserveLiquidityFeeReceiver: liquidityFeeReceiverPDA,
+          hostFeeReceiver: null,
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          userTransferAuthority: user1.publicKey,
+          instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .instruction();
+    }
+
+    before(async () => {
+      console.log("\n Setting up Flash Loan Test Environment...");
+
+      admin = Keypair.generate();
+      user1 = Keypair.generate();
+      pythPriceMock = Keypair.generate();
+
+      const sigs = await Promise.all([
+        connection.requestAirdrop(admin.publicKey, 20 * LAMPORTS_PER_SOL),
+        connection.requestAirdrop(user1.publicKey, 20 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(confirmTx));
+
+      usdcMint = await createMint(connection, admin, admin.publicKey, null, 6);
+
+      adminUsdcAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, admin, usdcMint, admin.publicKey)
+      ).address;
+      user1UsdcAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, user1, usdcMint, user1.publicKey)
+      ).address;
+
+      await mintTo(connection, admin, usdcMint, adminUsdcAccount, admin, 100_000 * 1e6);
+      await mintTo(connection, admin, usdcMint, user1UsdcAccount, admin, 1_000 * 1e6);
+
+      [lendingMarketPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      [lendingMarketAuthorityPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("authority"), lendingMarketPDA.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
+        .accounts({
+          owner: admin.publicKey,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      [reservePDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("reserve"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      [liquiditySupplyPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("liquidity-supply"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      [liquidityFeeReceiverPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("fee-receiver"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      [collateralMintPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral-mint"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      [collateralSupplyPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral-supply"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+
+      const config = {
+        optimalUtilizationRate: 80,
+        loanToValueRatio: 50,
+        liquidationBonus: 5,
+        maxLiquidationBonus: 5,
+        liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
+        minBorrowRate: 0,
+        optimalBorrowRate: 4,
+        maxBorrowRate: 30,
+        fees: {
+          borrowFeeWad: new BN("10000000000000000"),
+          flashLoanFeeWad: new BN("9000000000000000"),
+          hostFeePercentage: 20,
+        },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
+        pythPriceFeedId: Array(32).fill(1),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
+      };
+
+      const adminCollateralAddress = await getAssociatedTokenAddress(
+        collateralMintPDA,
+        admin.publicKey
+      );
+
+      await program.methods
+        .initReserve(new BN(50_000 * 1e6), config)
+        .accounts({
+          sourceLiquidity: adminUsdcAccount,
+          //@ts-ignore
+          destinationCollateral: adminCollateralAddress,
+          reserve: reservePDA,
+          liquidityMint: usdcMint,
+          liquiditySupply: liquiditySupplyPDA,
+          liquidityFeeReceiver: liquidityFeeReceiverPDA,
+          pythPrice: pythPriceMock.publicKey,
+          collateralMint: collateralMintPDA,
+          collateralSupply: collateralSupplyPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          owner: admin.publicKey,
+          userTransferAuthority: admin.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([admin])
+        .rpc();
+
+      console.log("Setup complete!\n");
+    });
+
+    it("Should flash borrow and repay in one transaction", async () => {
+      const amount = new BN(10_000 * 1e6);
+
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+      const feeReceiverBefore = await getAccount(connection, liquidityFeeReceiverPDA);
+
+      const tx = new anchor.web3.Transaction()
+        .add(await refreshReserveIx())
+        .add(await flashBorrowIx(amount))
+        .add(await flashRepayIx(amount, 1));
+
+      await provider.sendAndConfirm(tx, [user1]);
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      const feeReceiverAfter = await getAccount(connection, liquidityFeeReceiverPDA);
+
+      // 0.9% fee on 10,000 USDC, all of it to the fee receiver without a host
+      const expectedFee = 90 * 1e6;
+
+      assert.equal(
+        Number(feeReceiverAfter.amount - feeReceiverBefore.amount),
+        expectedFee
+      );
+      assert.equal(
+        reserveAfter.liquidityAvailableAmount.toString(),
+        reserveBefore.liquidityAvailableAmount.toString()
+      );
+      assert.equal(
+        reserveAfter.liquidityBorrowedAmountWads.toString(),
+        reserveBefore.liquidityBorrowedAmountWads.toString()
+      );
+    });
+
+    it("Should fail: flash borrow without repay", async () => {
+      const tx = new anchor.web3.Transaction()
+        .add(await refreshReserveIx())
+        .add(await flashBorrowIx(new BN(1_000 * 1e6)));
+
+      try {
+        await provider.sendAndConfirm(tx, [user1]);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "FlashRepayNotFound");
+      }
+    });
+
+    it("Should fail: repay a different amount than borrowed", async () => {
+      const tx = new anchor.web3.Transaction()
+        .add(await refreshReserveIx())
+        .add(await flashBorrowIx(new BN(1_000 * 1e6)))
+        .add(await flashRepayIx(new BN(999 * 1e6), 1));
+
+      try {
+        await provider.sendAndConfirm(tx, [user1]);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidFlashRepay");
+      }
+    });
+
+    it("Should fail: flash borrow from a reserve not refreshed this slot", async () => {
+      const tx = new anchor.web3.Transaction()
+        .add(await flashBorrowIx(new BN(1_000 * 1e6)))
+        .add(await flashRepayIx(new BN(1_000 * 1e6), 0));
+
+      try {
+        await provider.sendAndConfirm(tx, [user1]);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "ReserveStale");
+      }
+    });
+
+    it("Should fail: repay without a preceding borrow", async () => {
+      const tx = new anchor.web3.Transaction()
+        .add(SystemProgram.transfer({
+          fromPubkey: user1.publicKey,
+          toPubkey: user1.publicKey,
+          lamports: 0,
+        }))
+        .add(await flashRepayIx(new BN(1_000 * 1e6), 0));
+
+      try {
+        await provider.sendAndConfirm(tx, [user1]);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidFlashBorrow");
+      }
+    });
+  });
+
+  describe("Update Reserve Config", () => {
+    anchor.setProvider(anchor.AnchorProvider.env());
+
+    const program = anchor.workspace.lendborrow as Program<Lendborrow>;
+    const provider = anchor.getProvider();
+    const connection = provider.connection;
+
+    let admin: Keypair;
+    let user1: Keypair;
+    let usdcMint: PublicKey;
+    let adminUsdcAccount: PublicKey;
+    let lendingMarketPDA: PublicKey;
+    let lendingMarketAuthorityPDA: PublicKey;
+    let reservePDA: PublicKey;
+    let pythPriceMock: Keypair;
+
+    async function confirmTx(signature: string) {
+      const latestBlockhash = await connection.getLatestBlockhash();
+      await connection.confirmTransaction({
+        signature,
+        ...latestBlockhash,
+      });
+      return signature;
+    }
+
+    function createQuoteCurrency(currency: string): number[] {
+      const buffer = Buffer.alloc(32);
+      buffer.write(currency);
+      return Array.from(buffer);
+    }
+
+    async function updateConfig(config: any, signer: Keypair) {
+      const refreshIx = await program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMock.publicKey,
+        })
+        .instruction();
+
+      return program.methods
+        .updateReserveConfig(config)
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          authority: signer.publicKey,
+        })
+        .preInstructions([refreshIx])
+        .signers([signer])
+        .rpc();
+    }
+
+    before(async () => {
+      console.log("\n Setting up Update Reserve Config Test Environment...");
+
+      admin = Keypair.generate();
+      user1 = Keypair.generate();
+      pythPriceMock = Keypair.generate();
+
+      const sigs = await Promise.all([
+        connection.requestAirdrop(admin.publicKey, 20 * LAMPORTS_PER_SOL),
+        connection.requestAirdrop(user1.publicKey, 20 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(confirmTx));
+
+      usdcMint = await createMint(connection, admin, admin.publicKey, null, 6);
+      adminUsdcAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, admin, usdcMint, admin.publicKey)
+      ).address;
+      await mintTo(connection, admin, usdcMint, adminUsdcAccount, admin, 100_000 * 1e6);
+
+      [lendingMarketPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      [lendingMarketAuthorityPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("authority"), lendingMarketPDA.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
+        .accounts({
+          owner: admin.publicKey,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      [reservePDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("reserve"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      const [liquiditySupplyPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("liquidity-supply"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      const [liquidityFeeReceiverPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("fee-receiver"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      const [collateralMintPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral-mint"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      const [collateralSupplyPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral-supply"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+
+      const config = {
+        optimalUtilizationRate: 80,
+        loanToValueRatio: 50,
+        liquidationBonus: 5,
+        maxLiquidationBonus: 5,
+        liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
+        minBorrowRate: 0,
+        optimalBorrowRate: 4,
+        maxBorrowRate: 30,
+        fees: {
+          borrowFeeWad: new BN("10000000000000000"),
+          flashLoanFeeWad: new BN("9000000000000000"),
+          hostFeePercentage: 20,
+        },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
+        pythPriceFeedId: Array(32).fill(1),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
+      };
+
+      const adminCollateralAddress = await getAssociatedTokenAddress(
+        collateralMintPDA,
+        admin.publicKey
+      );
+
+      await program.methods
+        .initReserve(new BN(10_000 * 1e6), config)
+        .accounts({
+          sourceLiquidity: adminUsdcAccount,
+          //@ts-ignore
+          destinationCollateral: adminCollateralAddress,
+          reserve: reservePDA,
+          liquidityMint: usdcMint,
+          liquiditySupply: liquiditySupplyPDA,
+          liquidityFeeReceiver: liquidityFeeReceiverPDA,
+          pythPrice: pythPriceMock.publicKey,
+          collateralMint: collateralMintPDA,
+          collateralSupply: collateralSupplyPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          owner: admin.publicKey,
+          userTransferAuthority: admin.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([admin])
+        .rpc();
+
+      console.log("Setup complete!\n");
+    });
+
+    it("Should update the rate curve as market owner", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      await updateConfig(
+        { ...reserveBefore.config, optimalBorrowRate: 8, maxBorrowRate: 50 },
+        admin
+      );
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserveAfter.config.optimalBorrowRate, 8);
+      assert.equal(reserveAfter.config.maxBorrowRate, 50);
+      assert.equal(
+        reserveAfter.config.liquidationThreshold,
+        reserveBefore.config.liquidationThreshold
+      );
+    });
+
+    it("Should lower the liquidation threshold within the step size", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      await updateConfig(
+        { ...reserveBefore.config, liquidationThreshold: 50, loanToValueRatio: 45 },
+        admin
+      );
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserveAfter.config.liquidationThreshold, 50);
+    });
+
+    it("Should fail: lower the liquidation threshold beyond the step size", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      try {
+        await updateConfig(
+          { ...reserveBefore.config, liquidationThreshold: 40, loanToValueRatio: 35 },
+          admin
+        );
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "LiquidationThresholdStepTooLarge");
+      }
+    });
+
+    it("Should fail: invalid config", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      try {
+        await updateConfig(
+          { ...reserveBefore.config, loanToValueRatio: 90 },
+          admin
+        );
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidReserveConfig");
+      }
+    });
+
+    it("Should update the close factor and dust value", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      await updateConfig(
+        {
+          ...reserveBefore.config,
+          liquidationCloseFactor: 25,
+          liquidationDustValue: new BN(10 * 1e6),
+        },
+        admin
+      );
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserveAfter.config.liquidationCloseFactor, 25);
+      assert.equal(reserveAfter.config.liquidationDustValue.toNumber(), 10 * 1e6);
+    });
+
+    it("Should fail: zero close factor", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      try {
+        await updateConfig({ ...reserveBefore.config, liquidationCloseFactor: 0 }, admin);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidReserveConfig");
+      }
+    });
+
+    it("Should set a liquidation bonus range", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      await updateConfig({ ...reserveBefore.config, maxLiquidationBonus: 15 }, admin);
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserveAfter.config.maxLiquidationBonus, 15);
+    });
+
+    it("Should fail: max liquidation bonus below the base bonus", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      try {
+        await updateConfig(
+          {
+            ...reserveBefore.config,
+            liquidationBonus: 10,
+            maxLiquidationBonus: 5,
+          },
+          admin
+        );
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidReserveConfig");
+      }
+    });
+
+    it("Should update oracle staleness and confidence bounds", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      await updateConfig(
+        { ...reserveBefore.config, maxPriceAgeSecs: new BN(30), maxConfidenceBps: 100 },
+        admin
+      );
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserveAfter.config.maxPriceAgeSecs.toNumber(), 30);
+      assert.equal(reserveAfter.config.maxConfidenceBps, 100);
+    });
+
+    it("Should fail: invalid oracle bounds", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      for (const bounds of [
+        { maxPriceAgeSecs: new BN(0) },
+        { maxPriceAgeSecs: new BN(3_601) },
+        { maxConfidenceBps: 0 },
+        { maxConfidenceBps: 10_001 },
+      ]) {
+        try {
+          await updateConfig({ ...reserveBefore.config, ...bounds }, admin);
+          assert.fail("Should have failed");
+        } catch (error: any) {
+          assert.include(error.toString(), "InvalidOracleConfig");
+        }
+      }
+    });
+
+    it("Should fail: switching a spot reserve to EMA-based pricing", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      for (const priceMode of [{ ema: {} }, { spotEmaMinMax: {} }]) {
+        try {
+          await updateConfig({ ...reserveBefore.config, priceMode }, admin);
+          assert.fail("Should have failed");
+        } catch (error: any) {
+          assert.include(error.toString(), "ImmutableReserveConfig");
+        }
+      }
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.deepEqual(reserveAfter.config.priceMode, { spot: {} });
+    });
+
+    it("Should fail: EMA pricing on an oracle without an EMA", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      try {
+        await updateConfig(
+          {
+            ...reserveBefore.config,
+            oracleType: { switchboardOnDemand: {} },
+            priceMode: { ema: {} },
+          },
+          admin
+        );
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidOracleConfig");
+      }
+    });
+
+    it("Should report the peg price for a pegged reserve inside its band", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      await updateConfig(
+        {
+          ...reserveBefore.config,
+          priceMode: { pegged: {} },
+          peggedPrice: new BN(1_000_000),
+          maxPegDeviationBps: 100,
+        },
+        admin
+      );
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.deepEqual(reserveAfter.config.priceMode, { pegged: {} });
+      assert.isFalse(reserveAfter.depegged);
+      assert.equal(reserveAfter.liquidityMarketPrice.toNumber(), 1_000_000);
+    });
+
+    it("Should flag the reserve as depegged outside its band", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      // The live price is 1.00, so a 1.05 peg with a 1% band is broken
+      await updateConfig({ ...reserveBefore.config, peggedPrice: new BN(1_050_000) }, admin);
+
+      // Let the slot advance so the refresh re-reads the price under the new peg
+      await new Promise(resolve => setTimeout(resolve, 500));
+
+      await program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMock.publicKey,
+        })
+        .rpc();
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.isTrue(reserveAfter.depegged);
+      assert.equal(reserveAfter.liquidityMarketPrice.toNumber(), 1_000_000);
+
+      await updateConfig(
+        { ...reserveAfter.config, priceMode: { spot: {} }, peggedPrice: new BN(0), maxPegDeviationBps: 0 },
+        admin
+      );
+    });
+
+    it("Should fail: pegged mode without a peg price", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      try {
+        await updateConfig(
+          { ...reserveBefore.config, priceMode: { pegged: {} }, peggedPrice: new BN(0), maxPegDeviationBps: 100 },
+          admin
+        );
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidOracleConfig");
+      }
+    });
+
+    it("Should set and clear a secondary oracle as market owner", async () => {
+      const secondaryOracle = Keypair.generate().publicKey;
+
+      await program.methods
+        .setReserveSecondaryOracle()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          authority: admin.publicKey,
+          secondaryOracle,
+        })
+        .signers([admin])
+        .rpc();
+
+      let reserve = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserve.secondaryOracle.toString(), secondaryOracle.toString());
+      assert.isTrue(reserve.liquiditySecondaryMarketPrice.gtn(0));
+
+      await program.methods
+        .setReserveSecondaryOracle()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          authority: admin.publicKey,
+          secondaryOracle: null,
+        })
+        .signers([admin])
+        .rpc();
+
+      reserve = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserve.secondaryOracle.toString(), PublicKey.default.toString());
+      assert.equal(reserve.liquiditySecondaryMarketPrice.toNumber(), 0);
+    });
+
+    it("Should fail: non-owner sets a secondary oracle", async () => {
+      try {
+        await program.methods
+          .setReserveSecondaryOracle()
+          .accounts({
+            reserve: reservePDA,
+            //@ts-ignore
+            lendingMarket: lendingMarketPDA,
+            authority: user1.publicKey,
+            secondaryOracle: Keypair.generate().publicKey,
+          })
+          .signers([user1])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidRiskManager");
+      }
+    });
+
+    it("Should fail: non-owner updates config", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      try {
+        await updateConfig({ ...reserveBefore.config, maxBorrowRate: 60 }, user1);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidRiskManager");
+      }
+    });
+
+    it("Should let the risk manager update risk parameters", async () => {
+      await program.methods
+        .setLendingMarketRiskManager(user1.publicKey)
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          //@ts-ignore
+          owner: admin.publicKey,
+        })
+        .signers([admin])
+        .rpc();
+
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+      await updateConfig({ ...reserveBefore.config, maxBorrowRate: 60 }, user1);
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserveAfter.config.maxBorrowRate, 60);
+    });
+
+    it("Should fail: risk manager changes fees", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      try {
+        await updateConfig({ ...reserveBefore.config, protocolTakeRate: 20 }, user1);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidFeeManager");
+      }
+    });
+
+    it("Should let the fee manager redirect the fee receiver", async () => {
+      const feeManager = Keypair.generate();
+      await confirmTx(
+        await connection.requestAirdrop(feeManager.publicKey, 2 * LAMPORTS_PER_SOL)
+      );
+
+      await program.methods
+        .setLendingMarketFeeManager(feeManager.publicKey)
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          //@ts-ignore
+          owner: admin.publicKey,
+        })
+        .signers([admin])
+        .rpc();
+
+      const treasury = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          feeManager,
+          usdcMint,
+          feeManager.publicKey
+        )
+      ).address;
+
+      try {
+        await program.methods
+          .setReserveFeeReceiver()
+          .accounts({
+            reserve: reservePDA,
+            //@ts-ignore
+            lendingMarket: lendingMarketPDA,
+            authority: user1.publicKey,
+            newFeeReceiver: treasury,
+          })
+          .signers([user1])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidFeeManager");
+      }
+
+      await program.methods
+        .setReserveFeeReceiver()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          authority: feeManager.publicKey,
+          newFeeReceiver: treasury,
+        })
+        .signers([feeManager])
+        .rpc();
+
+      const reserve = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserve.liquidityFeeReceiver.toBase58(), treasury.toBase58());
+    });
+
+    it("Should route a share of fees to the insurance vault", async () => {
+      const [insuranceVaultPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("insurance-vault"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initInsuranceVault()
+        .accounts({
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          reserve: reservePDA,
+          mint: usdcMint,
+          insuranceVault: insuranceVaultPDA,
+          payer: user1.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user1])
+        .rpc();
+
+      const vault = await getAccount(connection, insuranceVaultPDA);
+      assert.equal(vault.owner.toBase58(), lendingMarketAuthorityPDA.toBase58());
+      assert.equal(Number(vault.amount), 0);
+
+      try {
+        await program.methods
+          .setInsuranceFeePercentage(10)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            authority: user1.publicKey,
+          })
+          .signers([user1])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidFeeManager");
+      }
+
+      try {
+        await program.methods
+          .setInsuranceFeePercentage(101)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            authority: admin.publicKey,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidConfig");
+      }
+
+      await program.methods
+        .setInsuranceFeePercentage(10)
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          authority: admin.publicKey,
+        })
+        .signers([admin])
+        .rpc();
+
+      const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.equal(market.insuranceFeePercentage, 10);
+
+      try {
+        await program.methods
+          .drawInsurance(new BN(1))
+          .accounts({
+            reserve: reservePDA,
+            //@ts-ignore
+            lendingMarket: lendingMarketPDA,
+            mint: usdcMint,
+            insuranceVault: insuranceVaultPDA,
+            reserveLiquiditySupply: (await program.account.reserve.fetch(reservePDA))
+              .liquiditySupply,
+            owner: user1.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user1])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidOwner");
+      }
+    });
+
+    it("Should fail: non-owner rotates the risk manager", async () => {
+      try {
+        await program.methods
+          .setLendingMarketRiskManager(Keypair.generate().publicKey)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            //@ts-ignore
+            owner: user1.publicKey,
+          })
+          .signers([user1])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidOwner");
+      }
+    });
+
+    async function queueChange(change: any, signer: Keypair, newFeeReceiver?: PublicKey) {
+      const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      const [queuedChangePDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("queued-change"),
+          lendingMarketPDA.toBuffer(),
+          market.queuedChangeCount.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+
+      await program.methods
+        .queueAdminChange(change)
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          //@ts-ignore
+          queuedChange: queuedChangePDA,
+          reserve: reservePDA,
+          newFeeReceiver: newFeeReceiver ?? null,
+          authority: signer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([signer])
+        .rpc();
+
+      return queuedChangePDA;
+    }
+
+    async function executeChange(queuedChangePDA: PublicKey, proposer: PublicKey) {
+      const refreshIx = await program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMock.publicKey,
+        })
+        .instruction();
+
+      return program.methods
+        .executeAdminChange()
+        .accounts({
+          queuedChange: queuedChangePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          reserve: reservePDA,
+          proposer,
+        })
+        .preInstructions([refreshIx])
+        .rpc();
+    }
+
+    it("Should require queued changes once a timelock is set", async () => {
+      await program.methods
+        .setTimelockDelay(new BN(4))
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          //@ts-ignore
+          owner: admin.publicKey,
+        })
+        .signers([admin])
+        .rpc();
+
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      try {
+        await updateConfig({ ...reserveBefore.config, maxBorrowRate: 70 }, admin);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "TimelockRequired");
+      }
+    });
+
+    it("Should apply a queued config change after the delay", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+      const queuedChangePDA = await queueChange(
+        { reserveConfig: { 0: { ...reserveBefore.config, maxBorrowRate: 70 } } },
+        admin
+      );
+
+      const queued = await program.account.queuedAdminChange.fetch(queuedChangePDA);
+      assert.equal(queued.etaSlot.toNumber(), queued.queuedSlot.toNumber() + 4);
+
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+      await executeChange(queuedChangePDA, admin.publicKey);
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserveAfter.config.maxBorrowRate, 70);
+      assert.isNull(await connection.getAccountInfo(queuedChangePDA));
+    });
+
+    it("Should fail: shorten the timelock without queueing", async () => {
+      try {
+        await program.methods
+          .setTimelockDelay(new BN(0))
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            //@ts-ignore
+            owner: admin.publicKey,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "TimelockRequired");
+      }
+    });
+
+    it("Should fail: swap the secondary oracle or propose an owner without queueing", async () => {
+      try {
+        await program.methods
+          .setReserveSecondaryOracle()
+          .accounts({
+            reserve: reservePDA,
+            //@ts-ignore
+            lendingMarket: lendingMarketPDA,
+            authority: admin.publicKey,
+            secondaryOracle: Keypair.generate().publicKey,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "TimelockRequired");
+      }
+
+      try {
+        await program.methods
+          .proposeLendingMarketOwner(user1.publicKey)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            //@ts-ignore
+            owner: admin.publicKey,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "TimelockRequired");
+      }
+    });
+
+    it("Should apply a queued owner proposal after the delay", async () => {
+      const queuedChangePDA = await queueChange(
+        { pendingOwner: { 0: user1.publicKey } },
+        admin
+      );
+
+      let market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.isTrue(market.pendingOwner.equals(PublicKey.default));
+
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+      await executeChange(queuedChangePDA, admin.publicKey);
+
+      market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.isTrue(market.pendingOwner.equals(user1.publicKey));
+      assert.isTrue(market.owner.equals(admin.publicKey));
+    });
+
+    it("Should apply a queued insurance fee share after the delay", async () => {
+      try {
+        await program.methods
+          .setInsuranceFeePercentage(5)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            authority: admin.publicKey,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "TimelockRequired");
+      }
+
+      const queuedChangePDA = await queueChange({ insuranceFeePercentage: { 0: 5 } }, admin);
+
+      let market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.equal(market.insuranceFeePercentage, 10);
+
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+      await executeChange(queuedChangePDA, admin.publicKey);
+
+      market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.equal(market.insuranceFeePercentage, 5);
+    });
+
+    it("Should apply a queued fee manager and fee receiver after the delay", async () => {
+      const feeManager = Keypair.generate();
+      const treasury = (
+        await getOrCreateAssociatedTokenAccount(connection, admin, usdcMint, feeManager.publicKey)
+      ).address;
+
+      try {
+        await program.methods
+          .setLendingMarketFeeManager(feeManager.publicKey)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            //@ts-ignore
+            owner: admin.publicKey,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "TimelockRequired");
+      }
+
+      try {
+        await program.methods
+          .setReserveFeeReceiver()
+          .accounts({
+            reserve: reservePDA,
+            //@ts-ignore
+            lendingMarket: lendingMarketPDA,
+            authority: admin.publicKey,
+            newFeeReceiver: treasury,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "TimelockRequired");
+      }
+
+      const feeManagerChangePDA = await queueChange(
+        { feeManager: { 0: feeManager.publicKey } },
+        admin
+      );
+      const feeReceiverChangePDA = await queueChange(
+        { feeReceiver: { 0: treasury } },
+        admin,
+        treasury
+      );
+
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+      await executeChange(feeManagerChangePDA, admin.publicKey);
+      await executeChange(feeReceiverChangePDA, admin.publicKey);
+
+      const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.isTrue(market.feeManager.equals(feeManager.publicKey));
+      const reserve = await program.account.reserve.fetch(reservePDA);
+      assert.isTrue(reserve.liquidityFeeReceiver.equals(treasury));
+    });
+
+    it("Should fail: execute before the eta, then cancel", async () => {
+      await program.methods
+        .setTimelockDelay(new BN(1_000_000))
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          //@ts-ignore
+          owner: admin.publicKey,
+        })
+        .signers([admin])
+        .rpc();
+
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+      const queuedChangePDA = await queueChange(
+        { reserveConfig: { 0: { ...reserveBefore.config, loanToValueRatio: 45 } } },
+        user1
+      );
+
+      try {
+        await executeChange(queuedChangePDA, user1.publicKey);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "TimelockNotElapsed");
+      }
+
+      await program.methods
+        .cancelAdminChange()
+        .accounts({
+          queuedChange: queuedChangePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          proposer: user1.publicKey,
+          authority: admin.publicKey,
+        })
+        .signers([admin])
+        .rpc();
+
+      assert.isNull(await connection.getAccountInfo(queuedChangePDA));
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserveAfter.config.loanToValueRatio, reserveBefore.config.loanToValueRatio);
+    });
+  });
+
+  describe("Emergency Pause", () => {
+    anchor.setProvider(anchor.AnchorProvider.env());
+
+    const program = anchor.workspace.lendborrow as Program<Lendborrow>;
+    const provider = anchor.getProvider();
+    const connection = provider.connection;
+
+    let admin: Keypair;
+    let user1: Keypair;
+    let usdcMint: PublicKey;
+    let adminUsdcAccount: PublicKey;
+    let lendingMarketPDA: PublicKey;
+    let lendingMarketAuthorityPDA: PublicKey;
+    let reservePDA: PublicKey;
+    let pythPriceMock: Keypair;
+    let guardian: Keypair;
+    let liquiditySupplyPDA: PublicKey;
+    let collateralMintPDA: PublicKey;
+    let user1UsdcAccount: PublicKey;
+    let user1CollateralAccount: PublicKey;
+
+    const PAUSE_DEPOSIT = 1 << 0;
+    const PAUSE_BORROW = 1 << 2;
+
+    async function confirmTx(signature: string) {
+      const latestBlockhash = await connection.getLatestBlockhash();
+      await connection.confirmTransaction({
+        signature,
+        ...latestBlockhash,
+      });
+      return signature;
+    }
+
+    function createQuoteCurrency(currency: string): number[] {
+      const buffer = Buffer.alloc(32);
+      buffer.write(currency);
+      return Array.from(buffer);
+    }
+
+    function deposit(amount: BN) {
+      return program.methods
+        .depositReserveLiquidity(amount)
+        .accounts({
+          sourceLiquidity: user1UsdcAccount,
+          destinationCollateral: user1CollateralAccount,
+          reserve: reservePDA,
+          reserveLiquiditySupply: liquiditySupplyPDA,
+          reserveCollateralMint: collateralMintPDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          userTransferAuthority: user1.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user1])
+        .rpc();
+    }
+
+    function setMarketPauseFlags(flags: number, signer: Keypair) {
+      return program.methods
+        .setLendingMarketPauseFlags(flags)
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          authority: signer.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+    }
+
+    function setReservePauseFlags(flags: number, signer: Keypair) {
+      return program.methods
+        .setReservePauseFlags(flags)
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          authority: signer.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+    }
+
+    before(async () => {
+      console.log("\n Setting up Emergency Pause Test Environment...");
+
+      admin = Keypair.generate();
+      user1 = Keypair.generate();
+      guardian = Keypair.generate();
+      pythPriceMock = Keypair.generate();
+
+      const sigs = await Promise.all([
+        connection.requestAirdrop(admin.publicKey, 20 * LAMPORTS_PER_SOL),
+        connection.requestAirdrop(user1.publicKey, 20 * LAMPORTS_PER_SOL),
+        connection.requestAirdrop(guardian.publicKey, 20 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(confirmTx));
+
+      usdcMint = await createMint(connection, admin, admin.publicKey, null, 6);
+      adminUsdcAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, admin, usdcMint, admin.publicKey)
+      ).address;
+      await mintTo(connection, admin, usdcMint, adminUsdcAccount, admin, 100_000 * 1e6);
+
+      [lendingMarketPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      [lendingMarketAuthorityPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("authority"), lendingMarketPDA.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
+        .accounts({
+          owner: admin.publicKey,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      [reservePDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("reserve"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      [liquiditySupplyPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("liquidity-supply"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      const [liquidityFeeReceiverPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("fee-receiver"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      [collateralMintPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral-mint"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      const [collateralSupplyPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral-supply"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+
+      const config = {
+        optimalUtilizationRate: 80,
+        loanToValueRatio: 50,
+        liquidationBonus: 5,
+        maxLiquidationBonus: 5,
+        liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
+        minBorrowRate: 0,
+        optimalBorrowRate: 4,
+        maxBorrowRate: 30,
+        fees: {
+          borrowFeeWad: new BN("10000000000000000"),
+          flashLoanFeeWad: new BN("9000000000000000"),
+          hostFeePercentage: 20,
+        },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
+        pythPriceFeedId: Array(32).fill(1),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
+      };
+
+      const adminCollateralAddress = await getAssociatedTokenAddress(
+        collateralMintPDA,
+        admin.publicKey
+      );
+
+      await program.methods
+        .initReserve(new BN(10_000 * 1e6), config)
+        .accounts({
+          sourceLiquidity: adminUsdcAccount,
+          //@ts-ignore
+          destinationCollateral: adminCollateralAddress,
+          reserve: reservePDA,
+          liquidityMint: usdcMint,
+          liquiditySupply: liquiditySupplyPDA,
+          liquidityFeeReceiver: liquidityFeeReceiverPDA,
+          pythPrice: pythPriceMock.publicKey,
+          collateralMint: collateralMintPDA,
+          collateralSupply: collateralSupplyPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          owner: admin.publicKey,
+          userTransferAuthority: admin.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([admin])
+        .rpc();
+
+      user1UsdcAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, user1, usdcMint, user1.publicKey)
+      ).address;
+      await mintTo(connection, admin, usdcMint, user1UsdcAccount, admin, 10_000 * 1e6);
+
+      user1CollateralAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, user1, collateralMintPDA, user1.publicKey)
+      ).address;
+
+      console.log("Setup complete!\n");
+    });
+
+    it("Should set the guardian as market owner", async () => {
+      await program.methods
+        .setLendingMarketGuardian(guardian.publicKey)
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          owner: admin.publicKey,
+        })
+        .signers([admin])
+        .rpc();
+
+      const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.equal(market.guardian.toString(), guardian.publicKey.toString());
+    });
+
+    it("Should fail: non-owner sets the guardian", async () => {
+      try {
+        await program.methods
+          .setLendingMarketGuardian(user1.publicKey)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            owner: user1.publicKey,
+          })
+          .signers([user1])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidOwner");
+      }
+    });
+
+    it("Should block deposits while the market is paused by the guardian", async () => {
+      await setMarketPauseFlags(PAUSE_DEPOSIT, guardian);
+
+      const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.equal(market.pausedOperations, PAUSE_DEPOSIT);
+
+      try {
+        await deposit(new BN(100 * 1e6));
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "OperationPaused");
+      }
+    });
+
+    it("Should fail: guardian clears a pause flag", async () => {
+      try {
+        await setMarketPauseFlags(0, guardian);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "GuardianCannotUnpause");
+      }
+    });
+
+    it("Should allow the guardian to add further pause flags", async () => {
+      await setMarketPauseFlags(PAUSE_DEPOSIT | PAUSE_BORROW, guardian);
+
+      const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.equal(market.pausedOperations, PAUSE_DEPOSIT | PAUSE_BORROW);
+    });
+
+    it("Should resume deposits after the owner unpauses the market", async () => {
+      await setMarketPauseFlags(0, admin);
+      await deposit(new BN(100 * 1e6));
+
+      const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.equal(market.pausedOperations, 0);
+    });
+
+    it("Should block deposits while the reserve is paused", async () => {
+      await setReservePauseFlags(PAUSE_DEPOSIT, guardian);
+
+      const reserve = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserve.pausedOperations, PAUSE_DEPOSIT);
+
+      try {
+        await deposit(new BN(100 * 1e6));
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "OperationPaused");
+      }
+
+      await setReservePauseFlags(0, admin);
+      await deposit(new BN(100 * 1e6));
+    });
+
+    it("Should fail: unknown pause flags", async () => {
+      try {
+        await setMarketPauseFlags(1 << 7, admin);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidPauseFlags");
+      }
+    });
+
+    it("Should fail: unauthorized account pauses the market", async () => {
+      try {
+        await setMarketPauseFlags(PAUSE_DEPOSIT, user1);
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidGuardian");
+      }
+    });
+  });
+
+  describe("Reserve Caps", () => {
+    anchor.setProvider(anchor.AnchorProvider.env());
+
+    const program = anchor.workspace.lendborrow as Program<Lendborrow>;
+    const provider = anchor.getProvider();
+    const connection = provider.connection;
+
+    let admin: Keypair;
+    let user1: Keypair;
+    let usdcMint: PublicKey;
+    let adminUsdcAccount: PublicKey;
+    let lendingMarketPDA: PublicKey;
+    let lendingMarketAuthorityPDA: PublicKey;
+    let reservePDA: PublicKey;
+    let pythPriceMock: Keypair;
+    let liquiditySupplyPDA: PublicKey;
+    let collateralMintPDA: PublicKey;
+    let user1UsdcAccount: PublicKey;
+    let user1CollateralAccount: PublicKey;
+
+    async function confirmTx(signature: string) {
+      const latestBlockhash = await connection.getLatestBlockhash();
+      await connection.confirmTransaction({
+        signature,
+        ...latestBlockhash,
+      });
+      return signature;
+    }
+
+    function createQuoteCurrency(currency: string): number[] {
+      const buffer = Buffer.alloc(32);
+      buffer.write(currency);
+      return Array.from(buffer);
+    }
+
+    function deposit(amount: BN) {
+      return program.methods
+        .depositReserveLiquidity(amount)
+        .accounts({
+          sourceLiquidity: user1UsdcAccount,
+          destinationCollateral: user1CollateralAccount,
+          reserve: reservePDA,
+          reserveLiquiditySupply: liquiditySupplyPDA,
+          reserveCollateralMint: collateralMintPDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          userTransferAuthority: user1.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user1])
+        .rpc();
+    }
+
+    async function updateConfig(config: any) {
+      const refreshIx = await program.methods
+        .refreshReserve()
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMock.publicKey,
+        })
+        .instruction();
+
+      return program.methods
+        .updateReserveConfig(config)
+        .accounts({
+          reserve: reservePDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          authority: admin.publicKey,
+        })
+        .preInstructions([refreshIx])
+        .signers([admin])
+        .rpc();
+    }
+
+    before(async () => {
+      console.log("\n Setting up Reserve Caps Test Environment...");
+
+      admin = Keypair.generate();
+      user1 = Keypair.generate();
+      pythPriceMock = Keypair.generate();
+
+      const sigs = await Promise.all([
+        connection.requestAirdrop(admin.publicKey, 20 * LAMPORTS_PER_SOL),
+        connection.requestAirdrop(user1.publicKey, 20 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(confirmTx));
+
+      usdcMint = await createMint(connection, admin, admin.publicKey, null, 6);
+      adminUsdcAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, admin, usdcMint, admin.publicKey)
+      ).address;
+      await mintTo(connection, admin, usdcMint, adminUsdcAccount, admin, 100_000 * 1e6);
+
+      [lendingMarketPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      [lendingMarketAuthorityPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("authority"), lendingMarketPDA.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
+        .accounts({
+          owner: admin.publicKey,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      [reservePDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("reserve"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      [liquiditySupplyPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("liquidity-supply"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      const [liquidityFeeReceiverPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("fee-receiver"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      [collateralMintPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral-mint"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+      const [collateralSupplyPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral-supply"), lendingMarketPDA.toBuffer(), usdcMint.toBuffer()],
+        program.programId
+      );
+
+      const config = {
+        optimalUtilizationRate: 80,
+        loanToValueRatio: 50,
+        liquidationBonus: 5,
+        maxLiquidationBonus: 5,
+        liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
+        minBorrowRate: 0,
+        optimalBorrowRate: 4,
+        maxBorrowRate: 30,
+        fees: {
+          borrowFeeWad: new BN("10000000000000000"),
+          flashLoanFeeWad: new BN("9000000000000000"),
+          hostFeePercentage: 20,
+        },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
+        pythPriceFeedId: Array(32).fill(1),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
+      };
+
+      const adminCollateralAddress = await getAssociatedTokenAddress(
+        collateralMintPDA,
+        admin.publicKey
+      );
+
+      await program.methods
+        .initReserve(new BN(10_000 * 1e6), config)
+        .accounts({
+          sourceLiquidity: adminUsdcAccount,
+          //@ts-ignore
+          destinationCollateral: adminCollateralAddress,
+          reserve: reservePDA,
+          liquidityMint: usdcMint,
+          liquiditySupply: liquiditySupplyPDA,
+          liquidityFeeReceiver: liquidityFeeReceiverPDA,
+          pythPrice: pythPriceMock.publicKey,
+          collateralMint: collateralMintPDA,
+          collateralSupply: collateralSupplyPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          owner: admin.publicKey,
+          userTransferAuthority: admin.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([admin])
+        .rpc();
+
+      user1UsdcAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, user1, usdcMint, user1.publicKey)
+      ).address;
+      await mintTo(connection, admin, usdcMint, user1UsdcAccount, admin, 10_000 * 1e6);
+
+      user1CollateralAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, user1, collateralMintPDA, user1.publicKey)
+      ).address;
+
+      console.log("Setup complete!\n");
+    });
+
+    it("Should deposit up to the deposit limit", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      // 10,000 USDC of initial liquidity plus 500 USDC of headroom
+      await updateConfig({ ...reserveBefore.config, depositLimit: new BN(10_500 * 1e6) });
+
+      await deposit(new BN(500 * 1e6));
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserveAfter.liquidityAvailableAmount.toNumber(), 10_500 * 1e6);
+    });
+
+    it("Should fail: deposit beyond the deposit limit", async () => {
+      try {
+        await deposit(new BN(1));
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "DepositLimitExceeded");
+      }
+    });
+
+    it("Should accept deposits again after the limit is raised", async () => {
+      const reserveBefore = await program.account.reserve.fetch(reservePDA);
+
+      await updateConfig({ ...reserveBefore.config, depositLimit: new BN(20_000 * 1e6) });
+      await deposit(new BN(100 * 1e6));
+
+      const reserveAfter = await program.account.reserve.fetch(reservePDA);
+      assert.equal(reserveAfter.liquidityAvailableAmount.toNumber(), 10_600 * 1e6);
+    });
+  });
+
+  describe("Token-2022 Reserves", () => {
+    anchor.setProvider(anchor.AnchorProvider.env());
+
+    const program = anchor.workspace.lendborrow as Program<Lendborrow>;
+    const provider = anchor.getProvider();
+    const connection = provider.connection;
+
+    // 1% transfer fee, as used by PYUSD-style mints
+    const TRANSFER_FEE_BPS = 100;
+
+    let admin: Keypair;
+    let user1: Keypair;
+    let feeMint: PublicKey;
+    let lendingMarketPDA: PublicKey;
+    let lendingMarketAuthorityPDA: PublicKey;
+    let pythPriceMock: Keypair;
+
+    async function confirmTx(signature: string) {
+      const latestBlockhash = await connection.getLatestBlockhash();
+      await connection.confirmTransaction({
+        signature,
+        ...latestBlockhash,
+      });
+      return signature;
+    }
+
+    function createQuoteCurrency(currency: string): number[] {
+      const buffer = Buffer.alloc(32);
+      buffer.write(currency);
+      return Array.from(buffer);
+    }
+
+    async function createToken2022Mint(
+      extensions: ExtensionType[],
+      extensionIxs: (mint: PublicKey) => TransactionInstruction[]
+    ): Promise<PublicKey> {
+      const mint = Keypair.generate();
+      const mintLen = getMintLen(extensions);
+      const lamports = await connection.getMinimumBalanceForRentExemption(mintLen);
+
+      const tx = new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: admin.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space: mintLen,
+          lamports,
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        ...extensionIxs(mint.publicKey),
+        createInitializeMintInstruction(
+          mint.publicKey,
+          6,
+          admin.publicKey,
+          null,
+          TOKEN_2022_PROGRAM_ID
+        )
+      );
+      await sendAndConfirmTransaction(connection, tx, [admin, mint]);
+
+      return mint.publicKey;
+    }
+
+    function reservePDAs(mint: PublicKey) {
+      const pda = (seed: string) =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from(seed), lendingMarketPDA.toBuffer(), mint.toBuffer()],
+          program.programId
+        )[0];
+
+      return {
+        reserve: pda("reserve"),
+        liquiditySupply: pda("liquidity-supply"),
+        liquidityFeeReceiver: pda("fee-receiver"),
+        collateralMint: pda("collateral-mint"),
+        collateralSupply: pda("collateral-supply"),
+      };
+    }
+
+    async function initReserve(mint: PublicKey, amount: BN) {
+      const pdas = reservePDAs(mint);
+
+      const adminLiquidityAccount = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          admin,
+          mint,
+          admin.publicKey,
+          false,
+          undefined,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ).address;
+      await mintTo(
+        connection,
+        admin,
+        mint,
+        adminLiquidityAccount,
+        admin,
+        100_000 * 1e6,
+        [],
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      const adminCollateralAddress = await getAssociatedTokenAddress(
+        pdas.collateralMint,
+        admin.publicKey,
+        false,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      const config = {
+        optimalUtilizationRate: 80,
+        loanToValueRatio: 50,
+        liquidationBonus: 5,
+        maxLiquidationBonus: 5,
+        liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
+        minBorrowRate: 0,
+        optimalBorrowRate: 4,
+        maxBorrowRate: 30,
+        fees: {
+          borrowFeeWad: new BN("10000000000000000"),
+          flashLoanFeeWad: new BN("9000000000000000"),
+          hostFeePercentage: 20,
+        },
+        protocolTakeRate: 10,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
+        pythPriceFeedId: Array(32).fill(1),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
+      };
+
+      await program.methods
+        .initReserve(amount, config)
+        .accounts({
+          sourceLiquidity: adminLiquidityAccount,
+          //@ts-ignore
+          destinationCollateral: adminCollateralAddress,
+          reserve: pdas.reserve,
+          liquidityMint: mint,
+          liquiditySupply: pdas.liquiditySupply,
+          liquidityFeeReceiver: pdas.liquidityFeeReceiver,
+          pythPrice: pythPriceMock.publicKey,
+          collateralMint: pdas.collateralMint,
+          collateralSupply: pdas.collateralSupply,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          owner: admin.publicKey,
+          userTransferAuthority: admin.publicKey,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([admin])
+        .rpc();
+
+      return pdas;
+    }
+
+    before(async () => {
+      console.log("\n Setting up Token-2022 Reserves Test Environment...");
+
+      admin = Keypair.generate();
+      user1 = Keypair.generate();
+      pythPriceMock = Keypair.generate();
+
+      const sigs = await Promise.all([
+        connection.requestAirdrop(admin.publicKey, 20 * LAMPORTS_PER_SOL),
+        connection.requestAirdrop(user1.publicKey, 20 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(confirmTx));
+
+      [lendingMarketPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      [lendingMarketAuthorityPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("authority"), lendingMarketPDA.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
+        .accounts({
+          owner: admin.publicKey,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      feeMint = await createToken2022Mint([ExtensionType.TransferFeeConfig], (mint) => [
+        createInitializeTransferFeeConfigInstruction(
+          mint,
+          admin.publicKey,
+          admin.publicKey,
+          TRANSFER_FEE_BPS,
+          BigInt(1_000_000 * 1e6),
+          TOKEN_2022_PROGRAM_ID
+        ),
+      ]);
+
+      console.log("Setup complete!\n");
+    });
+
+    it("Should credit initial liquidity net of the transfer fee", async () => {
+      const pdas = await initReserve(feeMint, new BN(10_000 * 1e6));
+
+      const reserve = await program.account.reserve.fetch(pdas.reserve);
+      assert.equal(reserve.liquidityAvailableAmount.toNumber(), 9_900 * 1e6);
+      assert.equal(reserve.collateralMintTotalSupply.toNumber(), 9_900 * 1e6);
+
+      const supply = await getAccount(
+        connection,
+        pdas.liquiditySupply,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      assert.equal(Number(supply.amount), 9_900 * 1e6);
+    });
+
+    it("Should mint collateral for the amount received on deposit", async () => {
+      const pdas = reservePDAs(feeMint);
+
+      const user1LiquidityAccount = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          user1,
+          feeMint,
+          user1.publicKey,
+          false,
+          undefined,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ).address;
+      await mintTo(
+        connection,
+        admin,
+        feeMint,
+        user1LiquidityAccount,
+        admin,
+        1_000 * 1e6,
+        [],
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      const user1CollateralAccount = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          user1,
+          pdas.collateralMint,
+          user1.publicKey,
+          false,
+          undefined,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ).address;
+
+      await program.methods
+        .depositReserveLiquidity(new BN(1_000 * 1e6))
+        .accounts({
+          sourceLiquidity: user1LiquidityAccount,
+          destinationCollateral: user1CollateralAccount,
+          reserve: pdas.reserve,
+          reserveLiquiditySupply: pdas.liquiditySupply,
+          reserveCollateralMint: pdas.collateralMint,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          userTransferAuthority: user1.publicKey,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .signers([user1])
+        .rpc();
+
+      const reserve = await program.account.reserve.fetch(pdas.reserve);
+      assert.equal(reserve.liquidityAvailableAmount.toNumber(), 10_890 * 1e6);
+
+      const collateral = await getAccount(
+        connection,
+        user1CollateralAccount,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      assert.equal(Number(collateral.amount), 990 * 1e6);
+    });
+
+    it("Should fail: init reserve for a mint with a permanent delegate", async () => {
+      const delegateMint = await createToken2022Mint([ExtensionType.PermanentDelegate], (mint) => [
+        createInitializePermanentDelegateInstruction(mint, admin.publicKey, TOKEN_2022_PROGRAM_ID),
+      ]);
+
+      try {
+        await initReserve(delegateMint, new BN(1_000 * 1e6));
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "UnsupportedMintExtension");
+      }
+    });
+  });
+
+  describe("Isolation Mode", () => {
+    anchor.setProvider(anchor.AnchorProvider.env());
+
+    const program = anchor.workspace.lendborrow as Program<Lendborrow>;
+    const provider = anchor.getProvider();
+    const connection = provider.connection;
+
+    let admin: Keypair;
+    let user: Keypair;
+    let lendingMarketPDA: PublicKey;
+    let lendingMarketAuthorityPDA: PublicKey;
+    let obligationPDA: PublicKey;
+    let pythPriceMock: Keypair;
+    let iso: any;
+    let usdc: any;
+    let usdt: any;
+
+    async function confirmTx(signature: string) {
+      const latestBlockhash = await connection.getLatestBlockhash();
+      await connection.confirmTransaction({
+        signature,
+        ...latestBlockhash,
+      });
+      return signature;
+    }
+
+    function createQuoteCurrency(currency: string): number[] {
+      const buffer = Buffer.alloc(32);
+      buffer.write(currency);
+      return Array.from(buffer);
+    }
+
+    async function setupReserve(overrides: any) {
+      const mint = await createMint(connection, admin, admin.publicKey, null, 6);
+      const adminLiquidityAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, admin, mint, admin.publicKey)
+      ).address;
+      await mintTo(connection, admin, mint, adminLiquidityAccount, admin, 100_000 * 1e6);
+
+      const pda = (seed: string) =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from(seed), lendingMarketPDA.toBuffer(), mint.toBuffer()],
+          program.programId
+        )[0];
+
+      const reserve = {
+        mint,
+        reserve: pda("reserve"),
+        liquiditySupply: pda("liquidity-supply"),
+        liquidityFeeReceiver: pda("fee-receiver"),
+        collateralMint: pda("collateral-mint"),
+        collateralSupply: pda("collateral-supply"),
+        adminCollateral: PublicKey.default,
+      };
+
+      reserve.adminCollateral = await getAssociatedTokenAddress(
+        reserve.collateralMint,
+        admin.publicKey
+      );
+
+      const config = {
+        optimalUtilizationRate: 80,
+        loanToValueRatio: 50,
+        liquidationBonus: 5,
+        maxLiquidationBonus: 5,
+        liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
+        minBorrowRate: 0,
+        optimalBorrowRate: 4,
+        maxBorrowRate: 30,
+        fees: {
+          borrowFeeWad: new BN(0),
+          flashLoanFeeWad: new BN(0),
+          hostFeePercentage: 0,
+        },
+        protocolTakeRate: 0,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
+        pythPriceFeedId: Array(32).fill(1),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
+        ...overrides,
+      };
+
+      await program.methods
+        .initReserve(new BN(10_000 * 1e6), config)
+        .accounts({
+          sourceLiquidity: adminLiquidityAccount,
+          //@ts-ignore
+          destinationCollateral: reserve.adminCollateral,
+          reserve: reserve.reserve,
+          liquidityMint: mint,
+          liquiditySupply: reserve.liquiditySupply,
+          liquidityFeeReceiver: reserve.liquidityFeeReceiver,
+          pythPrice: pythPriceMock.publicKey,
+          collateralMint: reserve.collateralMint,
+          collateralSupply: reserve.collateralSupply,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          owner: admin.publicKey,
+          userTransferAuthority: admin.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([admin])
+        .rpc();
+
+      return reserve;
+    }
+
+    function refreshReserveIx(reserve: PublicKey) {
+      return program.methods
+        .refreshReserve()
+        .accounts({
+          reserve,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMock.publicKey,
+        })
+        .instruction();
+    }
+
+    async function depositCollateral(reserve: any, amount: BN) {
+      const userCollateral = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          user,
+          reserve.collateralMint,
+          user.publicKey
+        )
+      ).address;
+      await transfer(
+        connection,
+        admin,
+        reserve.adminCollateral,
+        userCollateral,
+        admin.publicKey,
+        BigInt(amount.toString())
+      );
+
+      return program.methods
+        .depositObligationCollateral(amount)
+        .accounts({
+          sourceCollateral: userCollateral,
+          destinationCollateral: reserve.collateralSupply,
+          reserve: reserve.reserve,
+          //@ts-ignore
+          obligation: obligationPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          obligationOwner: user.publicKey,
+          userTransferAuthority: user.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .preInstructions([await refreshReserveIx(reserve.reserve)])
+        .signers([user])
+        .rpc();
+    }
+
+    async function borrow(reserve: any, amount: BN) {
+      const userLiquidityAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, user, reserve.mint, user.publicKey)
+      ).address;
+      const obligation = await program.account.obligation.fetch(obligationPDA);
+      const reserveAccount = (pubkey: PublicKey) => ({
+        pubkey,
+        isWritable: false,
+        isSigner: false,
+      });
+
+      const refreshAccounts = [reserveAccount(iso.reserve)];
+      if (obligation.borrowsLen > 0) {
+        refreshAccounts.push(reserveAccount(reserve.reserve));
+      }
+
+      const refreshObligationIx = await program.methods
+        .refreshObligation()
+        .accounts({
+          obligation: obligationPDA,
+        })
+        .remainingAccounts(refreshAccounts)
+        .instruction();
+
+      return program.methods
+        .borrowObligationLiquidity(amount)
+        .accounts({
+          sourceLiquidity: reserve.liquiditySupply,
+          destinationLiquidity: userLiquidityAccount,
+          borrowReserve: reserve.reserve,
+          borrowReserveLiquidityFeeReceiver: reserve.liquidityFeeReceiver,
+          //@ts-ignore
+          obligation: obligationPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          obligationOwner: user.publicKey,
+          hostFeeReceiver: null,
+          isolatedCollateralReserve: iso.reserve,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts([reserveAccount(iso.reserve), reserveAccount(reserve.reserve)])
+        .preInstructions([
+          await refreshReserveIx(reserve.reserve),
+          await refreshReserveIx(iso.reserve),
+          refreshObligationIx,
+        ])
+        .signers([user])
+        .rpc();
+    }
+
+    before(async () => {
+      console.log("\n Setting up Isolation Mode Test Environment...");
+
+      admin = Keypair.generate();
+      user = Keypair.generate();
+      pythPriceMock = Keypair.generate();
+
+      const sigs = await Promise.all([
+        connection.requestAirdrop(admin.publicKey, 20 * LAMPORTS_PER_SOL),
+        connection.requestAirdrop(user.publicKey, 20 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(confirmTx));
+
+      [lendingMarketPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      [lendingMarketAuthorityPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("authority"), lendingMarketPDA.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
+        .accounts({
+          owner: admin.publicKey,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      // $100 of debt may be backed by the isolated asset across all obligations
+      iso = await setupReserve({
+        isolationMode: true,
+        isolatedDebtCeiling: new BN(100 * 1e6),
+      });
+      usdc = await setupReserve({});
+      usdt = await setupReserve({ isolationBorrowable: true });
+
+      [obligationPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("obligation"),
+          lendingMarketPDA.toBuffer(),
+          user.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      await program.methods
+        .initObligation(0)
+        .accounts({
+          //@ts-ignore
+          obligation: obligationPDA,
+          lendingMarket: lendingMarketPDA,
+          owner: user.publicKey,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([user])
+        .rpc();
+
+      console.log("Setup complete!\n");
+    });
+
+    it("Should mark the obligation as isolated on isolated deposit", async () => {
+      await depositCollateral(iso, new BN(1_000 * 1e6));
+
+      const obligation = await program.account.obligation.fetch(obligationPDA);
+      assert.equal(obligation.isolatedCollateralReserve.toBase58(), iso.reserve.toBase58());
+    });
+
+    it("Should fail: add other collateral next to isolated collateral", async () => {
+      try {
+        await depositCollateral(usdc, new BN(100 * 1e6));
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "IsolatedCollateralConflict");
+      }
+    });
+
+    it("Should fail: borrow a reserve that is not isolation-borrowable", async () => {
+      try {
+        await borrow(usdc, new BN(50 * 1e6));
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "IsolationBorrowNotAllowed");
+      }
+    });
+
+    it("Should fail: make an existing reserve isolation-borrowable", async () => {
+      const usdcReserve = await program.account.reserve.fetch(usdc.reserve);
+
+      try {
+        await program.methods
+          .updateReserveConfig({ ...usdcReserve.config, isolationBorrowable: true })
+          .accounts({
+            reserve: usdc.reserve,
+            //@ts-ignore
+            lendingMarket: lendingMarketPDA,
+            authority: admin.publicKey,
+          })
+          .preInstructions([await refreshReserveIx(usdc.reserve)])
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "ImmutableReserveConfig");
+      }
+    });
+
+    it("Should borrow an isolation-borrowable reserve up to the debt ceiling", async () => {
+      await borrow(usdt, new BN(50 * 1e6));
+
+      const isoReserve = await program.account.reserve.fetch(iso.reserve);
+      assert.equal(isoReserve.isolatedDebt.toNumber(), 50 * 1e6);
+    });
+
+    it("Should fail: borrow past the isolated debt ceiling", async () => {
+      try {
+        await borrow(usdt, new BN(60 * 1e6));
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "IsolatedDebtCeilingExceeded");
+      }
+    });
+  });
+
+  describe("E-Mode", () => {
+    anchor.setProvider(anchor.AnchorProvider.env());
+
+    const program = anchor.workspace.lendborrow as Program<Lendborrow>;
+    const provider = anchor.getProvider();
+    const connection = provider.connection;
+
+    let admin: Keypair;
+    let user: Keypair;
+    let lendingMarketPDA: PublicKey;
+    let lendingMarketAuthorityPDA: PublicKey;
+    let obligationPDA: PublicKey;
+    let pythPriceMock: Keypair;
+    let sol: any;
+    let stSol: any;
+    let userStSolAccount: PublicKey;
+
+    async function confirmTx(signature: string) {
+      const latestBlockhash = await connection.getLatestBlockhash();
+      await connection.confirmTransaction({
+        signature,
+        ...latestBlockhash,
+      });
+      return signature;
+    }
+
+    function createQuoteCurrency(currency: string): number[] {
+      const buffer = Buffer.alloc(32);
+      buffer.write(currency);
+      return Array.from(buffer);
+    }
+
+    async function setupReserve(overrides: any) {
+      const mint = await createMint(connection, admin, admin.publicKey, null, 6);
+      const adminLiquidityAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, admin, mint, admin.publicKey)
+      ).address;
+      await mintTo(connection, admin, mint, adminLiquidityAccount, admin, 100_000 * 1e6);
+
+      const pda = (seed: string) =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from(seed), lendingMarketPDA.toBuffer(), mint.toBuffer()],
+          program.programId
+        )[0];
+
+      const reserve = {
+        mint,
+        reserve: pda("reserve"),
+        liquiditySupply: pda("liquidity-supply"),
+        liquidityFeeReceiver: pda("fee-receiver"),
+        collateralMint: pda("collateral-mint"),
+        collateralSupply: pda("collateral-supply"),
+        adminCollateral: PublicKey.default,
+      };
+
+      reserve.adminCollateral = await getAssociatedTokenAddress(
+        reserve.collateralMint,
+        admin.publicKey
+      );
+
+      const config = {
+        optimalUtilizationRate: 80,
+        loanToValueRatio: 50,
+        liquidationBonus: 5,
+        maxLiquidationBonus: 5,
+        liquidationThreshold: 55,
+        liquidationCloseFactor: 50,
+        liquidationDustValue: new BN(0),
+        minBorrowRate: 0,
+        optimalBorrowRate: 4,
+        maxBorrowRate: 30,
+        fees: {
+          borrowFeeWad: new BN(0),
+          flashLoanFeeWad: new BN(0),
+          hostFeePercentage: 0,
+        },
+        protocolTakeRate: 0,
+        depositLimit: new BN("1000000000000000"),
+        borrowLimit: new BN("1000000000000000"),
+        isolationMode: false,
+        isolatedDebtCeiling: new BN(0),
+        isolationBorrowable: false,
+        emodeCategory: 0,
+        oracleType: { pyth: {} },
+        pythPriceFeedId: Array(32).fill(1),
+        maxPriceAgeSecs: new BN(60),
+        maxConfidenceBps: 500,
+        priceMode: { spot: {} },
+        peggedPrice: new BN(0),
+        maxPegDeviationBps: 0,
+        secondaryOracleType: { pyth: {} },
+        secondaryPythPriceFeedId: Array(32).fill(0),
+        maxOracleDivergenceBps: 100,
+        oracleDivergenceFallback: false,
+        ...overrides,
+      };
+
+      await program.methods
+        .initReserve(new BN(10_000 * 1e6), config)
+        .accounts({
+          sourceLiquidity: adminLiquidityAccount,
+          //@ts-ignore
+          destinationCollateral: reserve.adminCollateral,
+          reserve: reserve.reserve,
+          liquidityMint: mint,
+          liquiditySupply: reserve.liquiditySupply,
+          liquidityFeeReceiver: reserve.liquidityFeeReceiver,
+          pythPrice: pythPriceMock.publicKey,
+          collateralMint: reserve.collateralMint,
+          collateralSupply: reserve.collateralSupply,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          owner: admin.publicKey,
+          userTransferAuthority: admin.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([admin])
+        .rpc();
+
+      return reserve;
+    }
+
+    function refreshReserveIx(reserve: PublicKey) {
+      return program.methods
+        .refreshReserve()
+        .accounts({
+          reserve,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          pythPrice: pythPriceMock.publicKey,
+        })
+        .instruction();
+    }
+
+    async function depositCollateral(reserve: any, amount: BN) {
+      const userCollateral = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          user,
+          reserve.collateralMint,
+          user.publicKey
+        )
+      ).address;
+      await transfer(
+        connection,
+        admin,
+        reserve.adminCollateral,
+        userCollateral,
+        admin.publicKey,
+        BigInt(amount.toString())
+      );
+
+      return program.methods
+        .depositObligationCollateral(amount)
+        .accounts({
+          sourceCollateral: userCollateral,
+          destinationCollateral: reserve.collateralSupply,
+          reserve: reserve.reserve,
+          //@ts-ignore
+          obligation: obligationPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          obligationOwner: user.publicKey,
+          userTransferAuthority: user.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .preInstructions([await refreshReserveIx(reserve.reserve)])
+        .signers([user])
+        .rpc();
+    }
+
+    async function borrowStSol(amount: BN) {
+      const obligation = await program.account.obligation.fetch(obligationPDA);
+      const reserveAccount = (pubkey: PublicKey) => ({
+        pubkey,
+        isWritable: false,
+        isSigner: false,
+      });
+
+      const refreshAccounts = [reserveAccount(sol.reserve)];
+      if (obligation.borrowsLen > 0) {
+        refreshAccounts.push(reserveAccount(stSol.reserve));
+      }
+
+      const refreshObligationIx = await program.methods
+        .refreshObligation()
+        .accounts({
+          obligation: obligationPDA,
+        })
+        .remainingAccounts(refreshAccounts)
+        .instruction();
+
+      return program.methods
+        .borrowObligationLiquidity(amount)
+        .accounts({
+          sourceLiquidity: stSol.liquiditySupply,
+          destinationLiquidity: userStSolAccount,
+          borrowReserve: stSol.reserve,
+          borrowReserveLiquidityFeeReceiver: stSol.liquidityFeeReceiver,
+          //@ts-ignore
+          obligation: obligationPDA,
+          lendingMarket: lendingMarketPDA,
+          lendingMarketAuthority: lendingMarketAuthorityPDA,
+          obligationOwner: user.publicKey,
+          hostFeeReceiver: null,
+          isolatedCollateralReserve: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts([reserveAccount(sol.reserve), reserveAccount(stSol.reserve)])
+        .preInstructions([
+          await refreshReserveIx(stSol.reserve),
+          await refreshReserveIx(sol.reserve),
+          refreshObligationIx,
+        ])
+        .signers([user])
+        .rpc();
+    }
+
+    before(async () => {
+      console.log("\n Setting up E-Mode Test Environment...");
+
+      admin = Keypair.generate();
+      user = Keypair.generate();
+      pythPriceMock = Keypair.generate();
+
+      const sigs = await Promise.all([
+        connection.requestAirdrop(admin.publicKey, 20 * LAMPORTS_PER_SOL),
+        connection.requestAirdrop(user.publicKey, 20 * LAMPORTS_PER_SOL),
+      ]);
+      await Promise.all(sigs.map(confirmTx));
+
+      [lendingMarketPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("lending-market"),
+          admin.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      [lendingMarketAuthorityPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("authority"), lendingMarketPDA.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initLendingMarket(0, createQuoteCurrency("USD"), createMarketName("Main"))
+        .accounts({
+          owner: admin.publicKey,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      // Both reserves are in category 1 but keep a 50% LTV on their own
+      sol = await setupReserve({ emodeCategory: 1 });
+      stSol = await setupReserve({ emodeCategory: 1 });
+
+      userStSolAccount = (
+        await getOrCreateAssociatedTokenAccount(connection, user, stSol.mint, user.publicKey)
+      ).address;
+
+      [obligationPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("obligation"),
+          lendingMarketPDA.toBuffer(),
+          user.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      await program.methods
+        .initObligation(0)
+        .accounts({
+          //@ts-ignore
+          obligation: obligationPDA,
+          lendingMarket: lendingMarketPDA,
+          owner: user.publicKey,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([user])
+        .rpc();
+
+      console.log("Setup complete!\n");
+    });
+
+    it("Should configure an e-mode category", async () => {
+      await program.methods
+        .setEmodeCategory(1, 90, 93)
+        .accounts({
+          lendingMarket: lendingMarketPDA,
+          authority: admin.publicKey,
+        })
+        .signers([admin])
+        .rpc();
+
+      const market = await program.account.lendingMarket.fetch(lendingMarketPDA);
+      assert.equal(market.emodeCategories[0].loanToValueRatio, 90);
+      assert.equal(market.emodeCategories[0].liquidationThreshold, 93);
+    });
+
+    it("Should fail: lower an e-mode threshold by more than the max step", async () => {
+      try {
+        await program.methods
+          .setEmodeCategory(1, 80, 85)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            authority: admin.publicKey,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "LiquidationThresholdStepTooLarge");
+      }
+    });
+
+    it("Should fail: disable an e-mode category in one step", async () => {
+      try {
+        await program.methods
+          .setEmodeCategory(1, 0, 0)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            authority: admin.publicKey,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "LiquidationThresholdStepTooLarge");
+      }
+    });
+
+    it("Should fail: configure e-mode category as non-owner", async () => {
+      try {
+        await program.methods
+          .setEmodeCategory(1, 95, 97)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            authority: user.publicKey,
+          })
+          .signers([user])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidRiskManager");
+      }
+    });
+
+    it("Should fail: configure e-mode category with LTV above threshold", async () => {
+      try {
+        await program.methods
+          .setEmodeCategory(2, 95, 90)
+          .accounts({
+            lendingMarket: lendingMarketPDA,
+            authority: admin.publicKey,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidConfig");
+      }
+    });
+
+    it("Should fail: enter a disabled e-mode category", async () => {
+      try {
+        await program.methods
+          .setObligationEmode(2)
+          .accounts({
+            obligation: obligationPDA,
+            //@ts-ignore
+            lendingMarket: lendingMarketPDA,
+            obligationOwner: user.publicKey,
+          })
+          .signers([user])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidEModeCategory");
+      }
+    });
+
+    it("Should borrow above the reserve LTV inside e-mode", async () => {
+      await program.methods
+        .setObligationEmode(1)
+        .accounts({
+          obligation: obligationPDA,
+          //@ts-ignore
+          lendingMarket: lendingMarketPDA,
+          obligationOwner: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+
+      await depositCollateral(sol, new BN(100 * 1e6));
+
+      // 80% of the collateral value, above the 50% reserve LTV
+      await borrowStSol(new BN(80 * 1e6));
+
+      const obligation = await program.account.obligation.fetch(obligationPDA);
+      assert.equal(obligation.emodeCategory, 1);
+      assert.equal(obligation.borrowsLen, 1);
+    });
+
+    it("Should fail: leave e-mode with open borrows", async () => {
+      try {
+        await program.methods
+          .setObligationEmode(0)
+          .accounts({
+            obligation: obligationPDA,
+            //@ts-ignore
+            lendingMarket: lendingMarketPDA,
+            obligationOwner: user.publicKey,
+          })
+          .signers([user])
+          .rpc();
+        assert.fail("Should have failed");
+      } catch (error: any) {
+        assert.include(error.toString(), "ObligationHasBorrows");
+      }
+    });
+  });
+
+});
\ No newline at end of file